@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use color_eyre::eyre::Result;
+use time::{Duration, OffsetDateTime};
 use tokio::{
     fs::{create_dir_all, File},
     io::AsyncReadExt,
@@ -10,6 +11,7 @@ use metlink_gtfs_lib::{
     client::reqwest_client_with_api_key,
     realtime::{
         self,
+        archive::compact,
         service_alerts::ServiceAlertRealtimeApi,
         trip_updates::TripUpdateRealtimeApi,
         utils::{download_latest_if_needed, CachedRealtimeApi},
@@ -17,16 +19,32 @@ use metlink_gtfs_lib::{
     },
 };
 
+// How long to keep the archived downloads for.
+const ARCHIVE_RETENTION: Duration = Duration::weeks(26);
+
 pub async fn cron_realtime_api<T: CachedRealtimeApi>(api: T) {
     let mut interval = tokio::time::interval(api.min_fetch_frequency());
     interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
+    let mut compacted_on = None;
     loop {
         interval.tick().await;
         match download_latest_if_needed(&api).await {
             Ok(_) => log::info!("Updated {}.", api.name()),
             Err(e) => log::error!("Service alerts update failed: {:?}", e),
         }
+
+        // Archives are by day, so there's only something new to compact once a day.
+        let now = OffsetDateTime::now_utc();
+        if compacted_on != Some(now.date()) {
+            match compact(&api, now, Some(ARCHIVE_RETENTION)).await {
+                Ok(compaction) => {
+                    log::info!("Compacted {}: {:?}", api.name(), compaction);
+                    compacted_on = Some(now.date());
+                }
+                Err(e) => log::error!("Compacting {} failed: {:?}", api.name(), e),
+            }
+        }
     }
 }
 
@@ -43,6 +43,9 @@ impl Time {
             })
         }
     }
+    pub fn minutes_since_midnight(&self) -> u32 {
+        self.hour as u32 * 60 + self.minute as u32
+    }
     fn to_time(&self) -> (time::Time, u8) {
         (
             time::Time::try_from_hms(self.hour % 24, self.minute, self.second).unwrap(),
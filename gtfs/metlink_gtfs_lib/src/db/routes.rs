@@ -42,6 +42,9 @@ impl RouteDb {
     pub fn get_route<'a>(&'a self, id: &String) -> Option<&'a Route> {
         self.routes.get(id)
     }
+    pub fn iter(&self) -> impl Iterator<Item = &Route> {
+        self.routes.values()
+    }
 }
 
 impl From<&crate::gtfs::data::GtfsData> for RouteDb {
@@ -8,12 +8,17 @@ pub struct Service {
     dates: BTreeSet<Date>,
 }
 
-impl Service {}
+impl Service {
+    pub fn runs_on(&self, date: Date) -> bool {
+        self.dates.contains(&date)
+    }
+}
 impl From<&crate::gtfs::data::calendar::Calendar> for Service {
     fn from(calendar: &crate::gtfs::data::calendar::Calendar) -> Self {
         let mut dates = BTreeSet::new();
         let mut curr = calendar.start_date;
-        while curr < calendar.end_date {
+        // The end date is the last day the service runs, not the day after.
+        while curr <= calendar.end_date {
             let matches = match curr.weekday() {
                 Weekday::Monday => calendar.monday,
                 Weekday::Tuesday => calendar.tuesday,
@@ -81,8 +86,9 @@ impl From<&crate::gtfs::data::GtfsData> for ServiceDb {
                 .iter()
                 .map(|service| {
                     let mut s = Service::from(service);
+                    // Removed dates are dropped from the service, and the rest kept.
                     if let Some(remove) = cal_rem_dates.get(&s.id) {
-                        s.dates.retain(|v| remove.contains(v));
+                        s.dates.retain(|v| !remove.contains(v));
                     }
                     if let Some(add) = cal_add_dates.get_mut(&s.id) {
                         s.dates.append(add);
@@ -94,3 +100,65 @@ impl From<&crate::gtfs::data::GtfsData> for ServiceDb {
         }
     }
 }
+
+#[cfg(test)]
+mod test_services {
+    use super::*;
+    use crate::gtfs::data::calendar::{Calendar, CalendarDate, CalendarDateExceptionType};
+    use crate::gtfs::data::GtfsData;
+
+    fn date(day: u8) -> Date {
+        Date::try_from_ymd(2021, 3, day).unwrap()
+    }
+
+    // Weekdays from Monday the 1st to Friday the 5th of March 2021.
+    fn weekdays() -> Calendar {
+        Calendar {
+            service_id: "WEEKDAY".to_string(),
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: false,
+            sunday: false,
+            start_date: date(1),
+            end_date: date(5),
+        }
+    }
+
+    #[test]
+    fn test_end_date_inclusive() {
+        let service = Service::from(&weekdays());
+        assert!(service.runs_on(date(1)));
+        assert!(service.runs_on(date(5)));
+        assert!(!service.runs_on(date(6)));
+    }
+
+    #[test]
+    fn test_calendar_dates() {
+        let exception = |day, exception_type| CalendarDate {
+            service_id: "WEEKDAY".to_string(),
+            date: date(day),
+            exception_type,
+        };
+        let db = ServiceDb::from(&GtfsData {
+            agency: vec![],
+            calendar: vec![weekdays()],
+            calendar_date: vec![
+                exception(3, CalendarDateExceptionType::ServiceRemoved),
+                exception(6, CalendarDateExceptionType::ServiceAdded),
+            ],
+            feed_info: vec![],
+            route: vec![],
+            stop: vec![],
+            stop_time: vec![],
+            trip: vec![],
+        });
+        let service = db.get_service(&"WEEKDAY".to_string()).unwrap();
+        assert!(service.runs_on(date(2)));
+        assert!(!service.runs_on(date(3)));
+        assert!(service.runs_on(date(4)));
+        assert!(service.runs_on(date(6)));
+    }
+}
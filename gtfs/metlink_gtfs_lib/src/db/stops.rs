@@ -46,6 +46,9 @@ impl StopDb {
     pub fn get_stop<'a>(&'a self, id: &String) -> Option<&'a Stop> {
         self.stops.get(id)
     }
+    pub fn iter(&self) -> impl Iterator<Item = &Stop> {
+        self.stops.values()
+    }
 }
 
 impl From<&crate::gtfs::data::GtfsData> for StopDb {
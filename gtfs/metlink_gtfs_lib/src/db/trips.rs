@@ -6,15 +6,15 @@ use crate::datatypes::time::Time;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TripStop {
-    stop_id: String,
-    arrival_time: Time,
-    departure_time: Time,
-    stop_sequence: u16,
+    pub stop_id: String,
+    pub arrival_time: Time,
+    pub departure_time: Time,
+    pub stop_sequence: u16,
     // pickup_type: PickupDropoffType,
     // drop_off_type: PickupDropoffType,
-    shape_dist_traveled: Option<f64>,
-    stop_headsign: String,
-    timepoint: bool,
+    pub shape_dist_traveled: Option<f64>,
+    pub stop_headsign: String,
+    pub timepoint: bool,
 }
 impl From<&crate::gtfs::data::stop::StopTime> for TripStop {
     fn from(stop: &crate::gtfs::data::stop::StopTime) -> Self {
@@ -69,6 +69,9 @@ impl TripDb {
     pub fn get_trip<'a>(&'a self, id: &String) -> Option<&'a Trip> {
         self.trips.get(id)
     }
+    pub fn iter(&self) -> impl Iterator<Item = &Trip> {
+        self.trips.values()
+    }
 }
 
 impl From<&crate::gtfs::data::GtfsData> for TripDb {
@@ -19,6 +19,10 @@ pub enum Error {
     Reuest(#[from] reqwest::Error),
     #[error("Serde JSON error: {0:?})")]
     SerdeJson(#[from] serde_json::Error),
+    #[error("Invalid GTFS-RT protobuf: {0}")]
+    Protobuf(String),
+    #[error("Invalid snapshot archive: {0}")]
+    Archive(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -2,7 +2,7 @@ use crate::datatypes::time::{deserialize_time_struct, Time};
 use super::utils::deserialize_num_bool;
 use serde::{Deserialize, Deserializer, Serialize};
 
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub enum StopLocationType {
     /// A location where passengers board or disembark from a transit vehicle. Is called a platform when defined within a parent_station.
     StopOrPlatform,
@@ -1,4 +1,7 @@
+pub mod archive;
 pub mod load;
+pub mod protobuf;
+pub mod replay;
 pub mod service_alerts;
 pub mod utils;
 pub mod trip_updates;
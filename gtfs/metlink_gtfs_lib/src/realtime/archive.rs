@@ -0,0 +1,482 @@
+//! Compacts the cached downloads. Every download is kept in a file of its own, and most are the
+//! same as the one before, so once a day is over its downloads are moved into one gzipped JSON
+//! lines file. A download that's the same as the one before it, apart from its header, only has
+//! its header and when it was made kept. The header's timestamp changes every download.
+//!
+//! zstd would compress better, but gzip is already in the dependency tree through `zip`.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use time::{Date, Duration, OffsetDateTime, UtcOffset};
+use tokio::task::spawn_blocking;
+
+use super::protobuf::split_feed_header;
+use super::utils::{
+    download_time, load_from_file, CachedRealtimeApi, CachedSnapshot, FeedFormat, RealtimeFeed,
+};
+use crate::error::{Error, Result};
+
+const ARCHIVE_SUFFIX: &str = ".jsonl.gz";
+const ARCHIVE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// One line of an archive.
+#[derive(Deserialize, Serialize)]
+struct ArchivedSnapshot {
+    #[serde(with = "time::serde::timestamp")]
+    time: OffsetDateTime,
+    format: FeedFormat,
+    /// Of the download without its header.
+    hash: u64,
+    /// The download as it was for JSON, and base64 encoded for protobuf. Left out when it's the
+    ///  same as the one before it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    /// The header of a download that's left out, encoded like the body. Without it, the download
+    ///  is the one before it exactly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    header: Option<String>,
+}
+
+// Listing an archive only needs the times, so the bodies are skipped over.
+#[derive(Deserialize)]
+struct ArchivedTime {
+    #[serde(with = "time::serde::timestamp")]
+    time: OffsetDateTime,
+}
+
+type Snapshot = (OffsetDateTime, FeedFormat, Arc<Vec<u8>>);
+
+// FNV-1a, which unlike `DefaultHasher` gives the same hash from one build to the next.
+fn content_hash(body: &[u8]) -> u64 {
+    body.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// Downloads are named with the offset they were made in, but archives are by the UTC day.
+fn utc_date(time: OffsetDateTime) -> Date {
+    time.to_offset(UtcOffset::UTC).date()
+}
+
+/// The day an archive is of, if `path` is one.
+pub(crate) fn archive_day(path: &Path) -> Option<Date> {
+    let name = path.file_name()?.to_str()?;
+    Date::parse(name.strip_suffix(ARCHIVE_SUFFIX)?, ARCHIVE_DATE_FORMAT).ok()
+}
+
+fn archive_file(folder: &Path, day: Date) -> PathBuf {
+    folder.join(format!("{}{}", day.format(ARCHIVE_DATE_FORMAT), ARCHIVE_SUFFIX))
+}
+
+fn encode_body(body: &[u8], format: FeedFormat) -> Result<String> {
+    match format {
+        FeedFormat::Json => std::str::from_utf8(body)
+            .map(|body| body.to_string())
+            .map_err(|e| Error::Archive(format!("JSON download isn't UTF-8: {}", e))),
+        FeedFormat::Protobuf => Ok(base64::encode(body)),
+    }
+}
+
+fn decode_body(body: String, format: FeedFormat) -> Result<Vec<u8>> {
+    match format {
+        FeedFormat::Json => Ok(body.into_bytes()),
+        FeedFormat::Protobuf => base64::decode(body).map_err(|e| Error::Archive(e.to_string())),
+    }
+}
+
+/// Splits a download into its header and the rest, each encoded as they were. `None` for a
+///  download that can't be read, which is only ever the same as the one before if it's identical.
+fn split_header(body: &[u8], format: FeedFormat) -> Option<(Vec<u8>, Vec<u8>)> {
+    match format {
+        FeedFormat::Json => {
+            let mut feed: serde_json::Value = serde_json::from_slice(body).ok()?;
+            let header = feed.as_object_mut()?.remove("header")?;
+            Some((
+                serde_json::to_vec(&header).ok()?,
+                serde_json::to_vec(&feed).ok()?,
+            ))
+        }
+        FeedFormat::Protobuf => split_feed_header(body).ok(),
+    }
+}
+
+/// The download before a left out one, with the left out one's header in place of its own.
+fn with_header(previous: &[u8], header: &[u8], format: FeedFormat) -> Result<Vec<u8>> {
+    match format {
+        FeedFormat::Json => {
+            let mut feed: serde_json::Value = serde_json::from_slice(previous)?;
+            feed.as_object_mut()
+                .ok_or_else(|| Error::Archive("JSON download isn't an object".to_string()))?
+                .insert("header".to_string(), serde_json::from_slice(header)?);
+            Ok(serde_json::to_vec(&feed)?)
+        }
+        FeedFormat::Protobuf => {
+            let (_, rest) = split_feed_header(previous)?;
+            Ok([header, &rest].concat())
+        }
+    }
+}
+
+fn archive_lines(path: &Path) -> Result<std::io::Lines<BufReader<MultiGzDecoder<File>>>> {
+    Ok(BufReader::new(MultiGzDecoder::new(File::open(path)?)).lines())
+}
+
+/// When each download in an archive was made, in the order they're stored.
+pub(crate) fn read_archive_times(path: &Path) -> Result<Vec<OffsetDateTime>> {
+    archive_lines(path)?
+        .map(|line| Ok(serde_json::from_str::<ArchivedTime>(&line?)?.time))
+        .collect()
+}
+
+fn read_archive(path: &Path) -> Result<Vec<Snapshot>> {
+    let mut snapshots: Vec<Snapshot> = vec![];
+    for line in archive_lines(path)? {
+        let archived: ArchivedSnapshot = serde_json::from_str(&line?)?;
+        let body = match archived.body {
+            Some(body) => Arc::new(decode_body(body, archived.format)?),
+            None => {
+                let previous = snapshots
+                    .last()
+                    .map(|(_, _, body)| body.clone())
+                    .ok_or_else(|| {
+                        Error::Archive(format!("{:?} starts with a repeated download", path))
+                    })?;
+                match archived.header {
+                    Some(header) => Arc::new(with_header(
+                        &previous,
+                        &decode_body(header, archived.format)?,
+                        archived.format,
+                    )?),
+                    None => previous,
+                }
+            }
+        };
+        snapshots.push((archived.time, archived.format, body));
+    }
+    Ok(snapshots)
+}
+
+/// Writes the archive, returning how many downloads were the same as the one before. It's
+///  written alongside and moved into place, so a run that stops part way leaves the old one.
+fn write_archive(path: &Path, snapshots: &[Snapshot]) -> Result<usize> {
+    let partial = path.with_extension("partial");
+    let mut writer = GzEncoder::new(BufWriter::new(File::create(&partial)?), Compression::best());
+    let mut previous = None;
+    let mut repeated = 0;
+    for (time, format, body) in snapshots {
+        let (header, rest) = match split_header(body, *format) {
+            Some((header, rest)) => (Some(header), rest),
+            None => (None, body.to_vec()),
+        };
+        let hash = content_hash(&rest);
+        let (body, header) = if previous == Some((*format, hash)) {
+            repeated += 1;
+            let header = header
+                .map(|header| encode_body(&header, *format))
+                .transpose()?;
+            (None, header)
+        } else {
+            (Some(encode_body(body, *format)?), None)
+        };
+        previous = Some((*format, hash));
+        serde_json::to_writer(
+            &mut writer,
+            &ArchivedSnapshot {
+                time: *time,
+                format: *format,
+                hash,
+                body,
+                header,
+            },
+        )?;
+        writer.write_all(b"\n")?;
+    }
+    writer.finish()?.flush()?;
+    fs::rename(&partial, path)?;
+    Ok(repeated)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Compaction {
+    /// Downloads moved into an archive.
+    pub archived: usize,
+    /// Downloads in the archives written that were the same as the one before, so only their
+    ///  time is kept.
+    pub repeated: usize,
+    /// Archives deleted for being older than the retention period.
+    pub expired: usize,
+}
+
+fn compact_folder(
+    folder: &Path,
+    now: OffsetDateTime,
+    retention: Option<Duration>,
+) -> Result<Compaction> {
+    fs::create_dir_all(folder)?;
+    let today = utc_date(now);
+    let mut downloads: BTreeMap<Date, Vec<(OffsetDateTime, PathBuf)>> = BTreeMap::new();
+    let mut archives: BTreeMap<Date, PathBuf> = BTreeMap::new();
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if let Some(time) = download_time(&path) {
+            if utc_date(time) < today {
+                downloads.entry(utc_date(time)).or_default().push((time, path));
+            }
+        } else if let Some(day) = archive_day(&path) {
+            archives.insert(day, path);
+        }
+    }
+
+    let mut compaction = Compaction::default();
+    for (day, files) in downloads {
+        let archive = archive_file(folder, day);
+        let mut snapshots = match archives.get(&day) {
+            Some(existing) => read_archive(existing)?,
+            None => vec![],
+        };
+        for (time, path) in &files {
+            let format = FeedFormat::from_path(path).unwrap_or(FeedFormat::Json);
+            snapshots.push((*time, format, Arc::new(fs::read(path)?)));
+        }
+        snapshots.sort_by_key(|(time, _, _)| *time);
+        // From a run that stopped after writing the archive but before deleting the downloads.
+        snapshots.dedup_by(|a, b| a.0 == b.0 && a.2 == b.2);
+        compaction.repeated += write_archive(&archive, &snapshots)?;
+        for (_, path) in &files {
+            fs::remove_file(path)?;
+        }
+        compaction.archived += files.len();
+        archives.insert(day, archive);
+    }
+
+    if let Some(retention) = retention {
+        let cutoff = utc_date(now - retention);
+        for (_, path) in archives.range(..cutoff) {
+            fs::remove_file(path)?;
+            compaction.expired += 1;
+        }
+    }
+    Ok(compaction)
+}
+
+/// Moves the downloads from before `now`'s day (in UTC) into an archive per day, then deletes
+///  the archives from more than `retention` ago. Today's downloads are left until it's over.
+pub async fn compact<T: CachedRealtimeApi>(
+    api: &T,
+    now: OffsetDateTime,
+    retention: Option<Duration>,
+) -> Result<Compaction> {
+    let folder = api.cache_folder();
+    spawn_blocking(move || compact_folder(&folder, now, retention))
+        .await
+        .unwrap()
+}
+
+/// Loads cached downloads, whether they're in a file of their own or archived. Downloads are
+///  normally loaded in order, so the last archive read is kept rather than read again for each.
+#[derive(Default)]
+pub struct SnapshotReader {
+    archive: Option<(PathBuf, Vec<Snapshot>)>,
+}
+
+impl SnapshotReader {
+    pub async fn load<I: 'static + RealtimeFeed + Send>(
+        &mut self,
+        snapshot: &CachedSnapshot,
+    ) -> Result<I> {
+        let (archive, index) = match snapshot {
+            CachedSnapshot::File(path) => return load_from_file(path.clone()).await,
+            CachedSnapshot::Archived { archive, index } => (archive, *index),
+        };
+        if self.archive.as_ref().map(|(path, _)| path) != Some(archive) {
+            let path = archive.clone();
+            let snapshots = spawn_blocking(move || read_archive(&path)).await.unwrap()?;
+            self.archive = Some((archive.clone(), snapshots));
+        }
+        let (_, format, body) = self
+            .archive
+            .as_ref()
+            .and_then(|(_, snapshots)| snapshots.get(index))
+            .cloned()
+            .ok_or_else(|| Error::Archive(format!("{:?} has no download {}", archive, index)))?;
+        spawn_blocking(move || I::decode(&body, format))
+            .await
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test_archive {
+    use super::*;
+    use crate::realtime::protobuf::test_protobuf::{field, number};
+    use crate::realtime::trip_updates::TripUpdateRoot;
+
+    /// A folder of its own for a test, deleted when it's done even if the test fails.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn trip_update_json(timestamp: i64, trip_id: &str) -> String {
+        format!(
+            r#"{{
+              "header": {{ "gtfs_realtime_version": "2.0", "incrementality": 0, "timestamp": {} }},
+              "entity": [{{ "id": "1", "trip_update": {{ "trip": {{ "trip_id": "{}" }} }} }}]
+            }}"#,
+            timestamp, trip_id
+        )
+    }
+
+    fn trip_update_protobuf(timestamp: i64, trip_id: &str) -> Vec<u8> {
+        let header = [field(1, b"2.0"), number(3, timestamp as u64)].concat();
+        let trip_update = field(3, &field(1, &field(1, trip_id.as_bytes())));
+        // The entities first, as the fields of a message can come in any order
+        [
+            field(2, &[field(1, b"1"), trip_update].concat()),
+            field(1, &header),
+        ]
+        .concat()
+    }
+
+    fn timestamps(path: &Path) -> Vec<(i64, i64, String)> {
+        read_archive(path)
+            .unwrap()
+            .into_iter()
+            .map(|(time, format, body)| {
+                let feed = TripUpdateRoot::decode(&body, format).unwrap();
+                let trip_id = feed.entity[0].trip_update.trip.trip_id.clone().unwrap();
+                (
+                    time.unix_timestamp(),
+                    feed.header.timestamp.unix_timestamp(),
+                    trip_id,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compact_folder() {
+        let dir = TempDir::new("archive-compact");
+        let download = |name: &str, format: FeedFormat, body: Vec<u8>| {
+            fs::write(dir.0.join(format!("{}.{}", name, format.extension())), body).unwrap()
+        };
+        let json = |timestamp, trip_id| trip_update_json(timestamp, trip_id).into_bytes();
+        download(
+            "2021-02-16 06.55.00+0000",
+            FeedFormat::Json,
+            json(1613458500, "A"),
+        );
+        download(
+            "2021-02-16 07.00.00+0000",
+            FeedFormat::Json,
+            json(1613458800, "A"),
+        );
+        download(
+            "2021-02-16 07.05.00+0000",
+            FeedFormat::Json,
+            json(1613459100, "B"),
+        );
+        download(
+            "2021-02-16 07.10.00+0000",
+            FeedFormat::Protobuf,
+            trip_update_protobuf(1613459400, "B"),
+        );
+        download(
+            "2021-02-16 07.15.00+0000",
+            FeedFormat::Protobuf,
+            trip_update_protobuf(1613459700, "B"),
+        );
+        download(
+            "2021-02-17 07.00.00+0000",
+            FeedFormat::Json,
+            json(1613545200, "A"),
+        );
+
+        // 2021-02-17 11:00 UTC, so that day's download is left as it is
+        let now = OffsetDateTime::from_unix_timestamp(1613559600);
+        let compaction = compact_folder(&dir.0, now, None).unwrap();
+        assert_eq!(5, compaction.archived);
+        // Only the header's timestamp changed, and a change of format is never a repeat
+        assert_eq!(2, compaction.repeated);
+        let archive = dir.0.join("2021-02-16.jsonl.gz");
+        assert_eq!(2, fs::read_dir(&dir.0).unwrap().count());
+        assert_eq!(
+            vec![
+                (1613458500, 1613458500, "A".to_string()),
+                (1613458800, 1613458800, "A".to_string()),
+                (1613459100, 1613459100, "B".to_string()),
+                (1613459400, 1613459400, "B".to_string()),
+                (1613459700, 1613459700, "B".to_string()),
+            ],
+            timestamps(&archive)
+        );
+
+        // A later download of the same day is added to its archive
+        download(
+            "2021-02-16 07.20.00+0000",
+            FeedFormat::Json,
+            json(1613460000, "B"),
+        );
+        let compaction = compact_folder(&dir.0, now, None).unwrap();
+        assert_eq!(1, compaction.archived);
+        assert_eq!(2, compaction.repeated);
+        assert_eq!(6, timestamps(&archive).len());
+    }
+
+    #[test]
+    fn test_partial_archive() {
+        let dir = TempDir::new("archive-partial");
+        // Left by a run that stopped part way through writing the archive
+        fs::write(dir.0.join("2021-02-16.jsonl.partial"), b"\x1f\x8b").unwrap();
+        fs::write(
+            dir.0.join("2021-02-16 06.55.00+0000.json"),
+            trip_update_json(1613458500, "A"),
+        )
+        .unwrap();
+
+        let now = OffsetDateTime::from_unix_timestamp(1613559600);
+        let compaction = compact_folder(&dir.0, now, None).unwrap();
+        assert_eq!(1, compaction.archived);
+        let names: Vec<String> = fs::read_dir(&dir.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert_eq!(vec!["2021-02-16.jsonl.gz".to_string()], names);
+        assert_eq!(1, timestamps(&dir.0.join(&names[0])).len());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let dir = TempDir::new("archive-expiry");
+        for day in &["2021-02-15", "2021-02-16", "2021-02-17"] {
+            fs::write(dir.0.join(format!("{}.jsonl.gz", day)), b"").unwrap();
+        }
+
+        // 2021-03-18 11:00 UTC, so anything before the 16th of February is over 30 days old
+        let now = OffsetDateTime::from_unix_timestamp(1616065200);
+        let compaction = compact_folder(&dir.0, now, Some(Duration::days(30))).unwrap();
+        assert_eq!(1, compaction.expired);
+        assert!(!dir.0.join("2021-02-15.jsonl.gz").exists());
+        assert!(dir.0.join("2021-02-16.jsonl.gz").exists());
+        assert!(dir.0.join("2021-02-17.jsonl.gz").exists());
+    }
+}
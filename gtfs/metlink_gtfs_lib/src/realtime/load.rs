@@ -12,7 +12,8 @@ use tokio::{
     task::spawn_blocking,
 };
 
-use super::service_alerts::{ServiceAlertEntity, ServiceAlertRoot};
+use super::service_alerts::{ServiceAlertEntity, ServiceAlertRealtimeApi, ServiceAlertRoot};
+use super::utils::load_all_from_files;
 use crate::{error::Result, utils::IF_MODIFIED_SINCE_DATE_FORMAT};
 
 pub const MIN_FETCH_FREQUENCY: Duration = Duration::from_secs(5 * 60);
@@ -71,13 +72,11 @@ pub async fn load_all_service_alerts(
 ) -> Result<Vec<ServiceAlertEntity>> {
     get_service_alerts_if_outdated(cache_dir, client).await?;
 
-    let mut read_dirs = read_dir(get_servicealert_cache_folder(cache_dir)).await?;
+    // Through the API's cache so archived downloads are read too.
+    let api = ServiceAlertRealtimeApi::new(cache_dir, client.clone());
     let mut entities = vec![];
-    while let Some(e) = read_dirs.next_entry().await? {
-        match load_service_alert_from_file(e.path()).await {
-            Ok(root) => entities.extend(root.entity),
-            _ => {}
-        }
+    for root in load_all_from_files::<_, ServiceAlertRoot>(&api).await? {
+        entities.extend(root.entity);
     }
 
     // Earliest to latest
@@ -0,0 +1,677 @@
+//! Decodes the standard GTFS-Realtime protobuf `FeedMessage` into the same types as the JSON
+//! feeds. Only the fields those types have are read; anything else, including extensions, is
+//! skipped over.
+//!
+//! See <https://developers.google.com/transit/gtfs-realtime/reference> for the field numbers.
+
+use time::OffsetDateTime;
+
+use super::service_alerts::{
+    AlertInformedEntity, AlertTimeRange, ServiceAlert, ServiceAlertCause, ServiceAlertEffect,
+    ServiceAlertEntity, ServiceAlertHeader, ServiceAlertIncrementability, ServiceAlertRoot,
+    ServiceAlertSeverity, TranslatedText, Translatation, TripEntity,
+};
+use super::trip_updates::{
+    StopScheduleRelationship, StopTimeEvent, StopTimeUpdate, TripDescriptor,
+    TripScheduleRelationship, TripUpdate, TripUpdateEntity, TripUpdateRoot, VehicleDescriptor,
+};
+use super::vehicle_positions::{
+    OccupancyStatus, Position, VehiclePosition, VehiclePositionEntity, VehiclePositionRoot,
+};
+use super::utils::RealtimeFeed;
+use crate::error::{Error, Result};
+
+// An alert without an end to its active period runs until further notice.
+const UNTIL_FURTHER_NOTICE: i64 = 253_402_300_799; // 9999-12-31T23:59:59Z
+
+fn invalid(message: &str) -> Error {
+    Error::Protobuf(message.to_string())
+}
+
+enum Value<'a> {
+    Varint(u64),
+    // Only `Position.odometer` is a double, and that isn't kept.
+    Fixed64,
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+impl<'a> Value<'a> {
+    fn uint(&self) -> Result<u64> {
+        match self {
+            Value::Varint(n) => Ok(*n),
+            _ => Err(invalid("Expected a varint")),
+        }
+    }
+    // Negative int32s are sign extended to 64 bits before they're encoded.
+    fn int32(&self) -> Result<i32> {
+        Ok(self.uint()? as i64 as i32)
+    }
+    fn int64(&self) -> Result<i64> {
+        Ok(self.uint()? as i64)
+    }
+    fn float(&self) -> Result<f64> {
+        match self {
+            Value::Fixed32(bits) => Ok(f32::from_bits(*bits).into()),
+            _ => Err(invalid("Expected a float")),
+        }
+    }
+    fn bytes(&self) -> Result<&'a [u8]> {
+        match self {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err(invalid("Expected a length-delimited field")),
+        }
+    }
+    fn string(&self) -> Result<String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|e| invalid(&e.to_string()))
+    }
+    fn timestamp(&self) -> Result<OffsetDateTime> {
+        Ok(OffsetDateTime::from_unix_timestamp(self.int64()?))
+    }
+}
+
+fn varint(buf: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = buf.split_first().ok_or_else(|| invalid("Truncated varint"))?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("Varint is too long"))
+}
+
+fn take<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if buf.len() < len {
+        return Err(invalid("Truncated field"));
+    }
+    let (taken, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(taken)
+}
+
+/// Reads the number and value of the next field in a message.
+fn next_field<'a>(buf: &mut &'a [u8]) -> Result<(u64, Value<'a>)> {
+    let key = varint(buf)?;
+    let value = match key & 0x7 {
+        0 => Value::Varint(varint(buf)?),
+        1 => {
+            take(buf, 8)?;
+            Value::Fixed64
+        }
+        2 => {
+            let len = varint(buf)? as usize;
+            Value::Bytes(take(buf, len)?)
+        }
+        5 => {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(take(buf, 4)?);
+            Value::Fixed32(u32::from_le_bytes(bytes))
+        }
+        wire_type => {
+            return Err(Error::Protobuf(format!(
+                "Unsupported wire type {}",
+                wire_type
+            )))
+        }
+    };
+    Ok((key >> 3, value))
+}
+
+/// Calls `f` with the number and value of each field in a message, in the order they're encoded.
+fn for_each_field<'a, F>(mut buf: &'a [u8], mut f: F) -> Result<()>
+where
+    F: FnMut(u64, Value<'a>) -> Result<()>,
+{
+    while !buf.is_empty() {
+        let (field, value) = next_field(&mut buf)?;
+        f(field, value)?;
+    }
+    Ok(())
+}
+
+/// Splits a `FeedMessage` into its header and everything else, both still encoded. The fields
+///  of a message can be in any order, so joining them back together gives the same feed.
+pub(crate) fn split_feed_header(mut buf: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut header = vec![];
+    let mut rest = vec![];
+    while !buf.is_empty() {
+        let start = buf;
+        let (field, _) = next_field(&mut buf)?;
+        let encoded = &start[..start.len() - buf.len()];
+        match field {
+            1 => header.extend_from_slice(encoded),
+            _ => rest.extend_from_slice(encoded),
+        }
+    }
+    Ok((header, rest))
+}
+
+fn header(buf: &[u8]) -> Result<ServiceAlertHeader> {
+    let mut header = ServiceAlertHeader {
+        gtfs_realtime_version: String::new(),
+        timestamp: OffsetDateTime::unix_epoch(),
+        incrementality: ServiceAlertIncrementability::FullDataset,
+    };
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => header.gtfs_realtime_version = value.string()?,
+            2 => {
+                header.incrementality = match value.uint()? {
+                    1 => ServiceAlertIncrementability::Differential,
+                    _ => ServiceAlertIncrementability::FullDataset,
+                }
+            }
+            3 => header.timestamp = value.timestamp()?,
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(header)
+}
+
+/// Reads the header and entities of a `FeedMessage`. `entity` is given the id, field number and
+///  contents of each entity's trip update, vehicle position or alert.
+fn feed_message<T, F>(buf: &[u8], mut entity: F) -> Result<(ServiceAlertHeader, Vec<T>)>
+where
+    F: FnMut(String, u64, &[u8]) -> Result<Option<T>>,
+{
+    let mut feed_header = None;
+    let mut entities = vec![];
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => feed_header = Some(header(value.bytes()?)?),
+            2 => {
+                let mut id = String::new();
+                let mut contents = None;
+                for_each_field(value.bytes()?, |field, value| {
+                    match field {
+                        1 => id = value.string()?,
+                        3..=5 => contents = Some((field, value.bytes()?)),
+                        _ => {}
+                    }
+                    Ok(())
+                })?;
+                if let Some((field, contents)) = contents {
+                    entities.extend(entity(id, field, contents)?);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+    let feed_header = feed_header.ok_or_else(|| invalid("FeedMessage without a header"))?;
+    Ok((feed_header, entities))
+}
+
+fn trip_descriptor(buf: &[u8]) -> Result<TripDescriptor> {
+    let mut trip = TripDescriptor {
+        trip_id: None,
+        route_id: None,
+        direction_id: None,
+        start_time: None,
+        start_date: None,
+        schedule_relationship: TripScheduleRelationship::Scheduled,
+    };
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => trip.trip_id = Some(value.string()?),
+            2 => trip.start_time = Some(value.string()?),
+            3 => trip.start_date = Some(value.string()?),
+            // Values added to the reference since, like `NEW`, are left as scheduled.
+            4 => {
+                if let Some(relationship) = TripScheduleRelationship::from_number(value.uint()?) {
+                    trip.schedule_relationship = relationship;
+                }
+            }
+            5 => trip.route_id = Some(value.string()?),
+            6 => trip.direction_id = Some(value.uint()? as u8),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(trip)
+}
+
+fn vehicle_descriptor(buf: &[u8]) -> Result<VehicleDescriptor> {
+    let mut vehicle = VehicleDescriptor {
+        id: None,
+        label: None,
+        license_plate: None,
+    };
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => vehicle.id = Some(value.string()?),
+            2 => vehicle.label = Some(value.string()?),
+            3 => vehicle.license_plate = Some(value.string()?),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(vehicle)
+}
+
+fn stop_time_event(buf: &[u8]) -> Result<StopTimeEvent> {
+    let mut event = StopTimeEvent {
+        delay: None,
+        time: None,
+        uncertainty: None,
+    };
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => event.delay = Some(value.int32()?),
+            2 => event.time = Some(value.timestamp()?),
+            3 => event.uncertainty = Some(value.int32()?),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(event)
+}
+
+fn stop_time_update(buf: &[u8]) -> Result<StopTimeUpdate> {
+    let mut update = StopTimeUpdate {
+        stop_sequence: None,
+        stop_id: None,
+        arrival: None,
+        departure: None,
+        schedule_relationship: StopScheduleRelationship::Scheduled,
+    };
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => update.stop_sequence = Some(value.uint()? as u16),
+            2 => update.arrival = Some(stop_time_event(value.bytes()?)?),
+            3 => update.departure = Some(stop_time_event(value.bytes()?)?),
+            4 => update.stop_id = Some(value.string()?),
+            5 => {
+                if let Some(relationship) = StopScheduleRelationship::from_number(value.uint()?) {
+                    update.schedule_relationship = relationship;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(update)
+}
+
+fn trip_update(buf: &[u8]) -> Result<TripUpdate> {
+    let mut trip = None;
+    let mut update = TripUpdate {
+        trip: trip_descriptor(&[])?,
+        vehicle: None,
+        stop_time_update: vec![],
+        timestamp: None,
+        delay: None,
+    };
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => trip = Some(trip_descriptor(value.bytes()?)?),
+            2 => update
+                .stop_time_update
+                .push(stop_time_update(value.bytes()?)?),
+            3 => update.vehicle = Some(vehicle_descriptor(value.bytes()?)?),
+            4 => update.timestamp = Some(value.timestamp()?),
+            5 => update.delay = Some(value.int32()?),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    update.trip = trip.ok_or_else(|| invalid("TripUpdate without a trip"))?;
+    Ok(update)
+}
+
+fn position(buf: &[u8]) -> Result<Position> {
+    let mut position = Position {
+        latitude: 0.0,
+        longitude: 0.0,
+        bearing: None,
+        speed: None,
+    };
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => position.latitude = value.float()?,
+            2 => position.longitude = value.float()?,
+            3 => position.bearing = Some(value.float()?),
+            5 => position.speed = Some(value.float()?),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(position)
+}
+
+fn vehicle_position(buf: &[u8]) -> Result<VehiclePosition> {
+    let mut vehicle = VehiclePosition {
+        trip: None,
+        vehicle: None,
+        position: None,
+        stop_id: None,
+        current_stop_sequence: None,
+        timestamp: None,
+        occupancy_status: None,
+    };
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => vehicle.trip = Some(trip_descriptor(value.bytes()?)?),
+            2 => vehicle.position = Some(position(value.bytes()?)?),
+            3 => vehicle.current_stop_sequence = Some(value.uint()? as u16),
+            5 => vehicle.timestamp = Some(value.timestamp()?),
+            7 => vehicle.stop_id = Some(value.string()?),
+            8 => vehicle.vehicle = Some(vehicle_descriptor(value.bytes()?)?),
+            9 => vehicle.occupancy_status = OccupancyStatus::from_number(value.uint()?),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(vehicle)
+}
+
+fn translated_string(buf: &[u8]) -> Result<Translatation> {
+    let mut translation = vec![];
+    for_each_field(buf, |field, value| {
+        if field == 1 {
+            let mut text = TranslatedText {
+                language: String::new(),
+                text: String::new(),
+            };
+            for_each_field(value.bytes()?, |field, value| {
+                match field {
+                    1 => text.text = value.string()?,
+                    2 => text.language = value.string()?,
+                    _ => {}
+                }
+                Ok(())
+            })?;
+            translation.push(text);
+        }
+        Ok(())
+    })?;
+    Ok(Translatation { translation })
+}
+
+fn time_range(buf: &[u8]) -> Result<AlertTimeRange> {
+    let mut range = AlertTimeRange {
+        start: OffsetDateTime::unix_epoch(),
+        end: OffsetDateTime::from_unix_timestamp(UNTIL_FURTHER_NOTICE),
+    };
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => range.start = value.timestamp()?,
+            2 => range.end = value.timestamp()?,
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(range)
+}
+
+// Selectors for an agency, or a route without its type, have nowhere to go and are dropped.
+fn entity_selector(buf: &[u8]) -> Result<Option<AlertInformedEntity>> {
+    let (mut route_id, mut route_type, mut trip_id, mut stop_id) = (None, None, None, None);
+    for_each_field(buf, |field, value| {
+        match field {
+            2 => route_id = Some(value.string()?),
+            3 => route_type = Some(value.int32()?),
+            4 => trip_id = trip_descriptor(value.bytes()?)?.trip_id,
+            5 => stop_id = Some(value.string()?),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(match (trip_id, stop_id, route_id, route_type) {
+        (Some(trip_id), _, _, _) => Some(AlertInformedEntity::Trip {
+            trip: TripEntity { trip_id },
+        }),
+        (_, Some(stop_id), _, _) => Some(AlertInformedEntity::Stop { stop_id }),
+        (_, _, Some(route_id), Some(route_type)) => Some(AlertInformedEntity::Route {
+            route_id,
+            route_type,
+        }),
+        _ => None,
+    })
+}
+
+fn cause(n: u64) -> ServiceAlertCause {
+    match n {
+        2 => ServiceAlertCause::OtherCause,
+        3 => ServiceAlertCause::TechnicalProblem,
+        4 => ServiceAlertCause::Strike,
+        5 => ServiceAlertCause::Demonstration,
+        6 => ServiceAlertCause::Accident,
+        7 => ServiceAlertCause::Holiday,
+        8 => ServiceAlertCause::Weather,
+        9 => ServiceAlertCause::Maintenance,
+        10 => ServiceAlertCause::Construction,
+        11 => ServiceAlertCause::PoliceActivity,
+        12 => ServiceAlertCause::MedicalEmergency,
+        _ => ServiceAlertCause::UnknownCause,
+    }
+}
+
+fn effect(n: u64) -> ServiceAlertEffect {
+    match n {
+        1 => ServiceAlertEffect::NoService,
+        2 => ServiceAlertEffect::ReducedService,
+        3 => ServiceAlertEffect::SignificantDelays,
+        4 => ServiceAlertEffect::Detour,
+        5 => ServiceAlertEffect::AdditionalService,
+        6 => ServiceAlertEffect::ModifiedService,
+        7 => ServiceAlertEffect::OtherEffect,
+        9 => ServiceAlertEffect::StopMoved,
+        10 => ServiceAlertEffect::NoEffect,
+        11 => ServiceAlertEffect::AccessibilityIssue,
+        _ => ServiceAlertEffect::UnknownEffect,
+    }
+}
+
+fn severity(n: u64) -> ServiceAlertSeverity {
+    match n {
+        2 => ServiceAlertSeverity::Info,
+        3 => ServiceAlertSeverity::Warning,
+        4 => ServiceAlertSeverity::Severe,
+        _ => ServiceAlertSeverity::UnknownSeverity,
+    }
+}
+
+fn alert(buf: &[u8]) -> Result<ServiceAlert> {
+    let mut alert = ServiceAlert {
+        active_period: vec![],
+        effect: ServiceAlertEffect::UnknownEffect,
+        cause: ServiceAlertCause::UnknownCause,
+        description_text: Translatation {
+            translation: vec![],
+        },
+        header_text: Translatation {
+            translation: vec![],
+        },
+        informed_entity: vec![],
+        severity_level: ServiceAlertSeverity::UnknownSeverity,
+    };
+    for_each_field(buf, |field, value| {
+        match field {
+            1 => alert.active_period.push(time_range(value.bytes()?)?),
+            5 => alert
+                .informed_entity
+                .extend(entity_selector(value.bytes()?)?),
+            6 => alert.cause = cause(value.uint()?),
+            7 => alert.effect = effect(value.uint()?),
+            10 => alert.header_text = translated_string(value.bytes()?)?,
+            11 => alert.description_text = translated_string(value.bytes()?)?,
+            14 => alert.severity_level = severity(value.uint()?),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(alert)
+}
+
+pub fn decode_trip_updates(buf: &[u8]) -> Result<TripUpdateRoot> {
+    let (header, entity) = feed_message(buf, |id, field, contents| {
+        Ok(match field {
+            3 => Some(TripUpdateEntity {
+                id,
+                trip_update: trip_update(contents)?,
+            }),
+            _ => None,
+        })
+    })?;
+    Ok(TripUpdateRoot { header, entity })
+}
+
+pub fn decode_vehicle_positions(buf: &[u8]) -> Result<VehiclePositionRoot> {
+    let (header, entity) = feed_message(buf, |id, field, contents| {
+        Ok(match field {
+            4 => Some(VehiclePositionEntity {
+                id,
+                vehicle: vehicle_position(contents)?,
+            }),
+            _ => None,
+        })
+    })?;
+    Ok(VehiclePositionRoot { header, entity })
+}
+
+/// The protobuf has no time for each alert, like Metlink's JSON does, so they're given the
+///  feed's.
+pub fn decode_service_alerts(buf: &[u8]) -> Result<ServiceAlertRoot> {
+    let mut alerts = vec![];
+    let (header, _) = feed_message(buf, |id, field, contents| {
+        if field == 5 {
+            alerts.push((id, alert(contents)?));
+        }
+        Ok(None::<()>)
+    })?;
+    let timestamp = header.timestamp;
+    Ok(ServiceAlertRoot {
+        header,
+        entity: alerts
+            .into_iter()
+            .map(|(id, alert)| ServiceAlertEntity {
+                alert,
+                id,
+                timestamp,
+            })
+            .collect(),
+    })
+}
+
+impl RealtimeFeed for TripUpdateRoot {
+    fn from_protobuf(buf: &[u8]) -> Result<Self> {
+        decode_trip_updates(buf)
+    }
+}
+
+impl RealtimeFeed for VehiclePositionRoot {
+    fn from_protobuf(buf: &[u8]) -> Result<Self> {
+        decode_vehicle_positions(buf)
+    }
+}
+
+impl RealtimeFeed for ServiceAlertRoot {
+    fn from_protobuf(buf: &[u8]) -> Result<Self> {
+        decode_service_alerts(buf)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_protobuf {
+    use super::*;
+
+    pub(crate) fn varint(mut n: u64) -> Vec<u8> {
+        let mut bytes = vec![];
+        while n >= 0x80 {
+            bytes.push((n as u8) | 0x80);
+            n >>= 7;
+        }
+        bytes.push(n as u8);
+        bytes
+    }
+
+    /// A length-delimited field.
+    pub(crate) fn field(number: u64, contents: &[u8]) -> Vec<u8> {
+        [
+            &varint(number << 3 | 2)[..],
+            &varint(contents.len() as u64),
+            contents,
+        ]
+        .concat()
+    }
+
+    pub(crate) fn number(number: u64, value: u64) -> Vec<u8> {
+        [varint(number << 3), varint(value)].concat()
+    }
+
+    pub(crate) fn feed_message(timestamp: i64, entities: &[Vec<u8>]) -> Vec<u8> {
+        let header = [field(1, b"2.0"), number(3, timestamp as u64)].concat();
+        let entities: Vec<Vec<u8>> = entities.iter().map(|entity| field(2, entity)).collect();
+        [field(1, &header), entities.concat()].concat()
+    }
+
+    fn trip_update_entity(id: &str, trip: &[u8], stop_time_update: &[u8]) -> Vec<u8> {
+        let trip_update = [field(1, trip), field(2, stop_time_update)].concat();
+        [field(1, id.as_bytes()), field(3, &trip_update)].concat()
+    }
+
+    #[test]
+    fn test_decode_trip_updates() {
+        let feed = decode_trip_updates(&feed_message(
+            1613455200,
+            &[
+                trip_update_entity(
+                    "1",
+                    &[field(1, b"HVL-1930"), number(4, 3)].concat(),
+                    &[number(1, 2), number(5, 1)].concat(),
+                ),
+                // `NEW` and a stop relationship from after this was written
+                trip_update_entity(
+                    "2",
+                    &[field(1, b"HVL-2000"), number(4, 8)].concat(),
+                    &[
+                        number(1, 4),
+                        number(5, 9),
+                        // A delay of -60, sign extended
+                        field(2, &number(1, -60i64 as u64)),
+                    ]
+                    .concat(),
+                ),
+            ],
+        ))
+        .unwrap();
+
+        assert_eq!(1613455200, feed.header.timestamp.unix_timestamp());
+        assert_eq!(2, feed.entity.len());
+        let cancelled = &feed.entity[0].trip_update;
+        assert_eq!(Some("HVL-1930".to_string()), cancelled.trip.trip_id);
+        assert_eq!(
+            TripScheduleRelationship::Canceled,
+            cancelled.trip.schedule_relationship
+        );
+        assert_eq!(
+            StopScheduleRelationship::Skipped,
+            cancelled.stop_time_update[0].schedule_relationship
+        );
+        let new = &feed.entity[1].trip_update;
+        assert_eq!(Some("HVL-2000".to_string()), new.trip.trip_id);
+        assert_eq!(
+            TripScheduleRelationship::Scheduled,
+            new.trip.schedule_relationship
+        );
+        assert_eq!(
+            StopScheduleRelationship::Scheduled,
+            new.stop_time_update[0].schedule_relationship
+        );
+        assert_eq!(Some(-60), new.stop_time_update[0].delay());
+    }
+
+    #[test]
+    fn test_split_feed_header() {
+        let header = field(1, &number(3, 1613455200));
+        let entity = field(2, &field(1, b"1"));
+        let (split_header, rest) = split_feed_header(&[&entity[..], &header].concat()).unwrap();
+        assert_eq!(header, split_header);
+        assert_eq!(entity, rest);
+    }
+}
@@ -0,0 +1,293 @@
+//! Replays the snapshots the cron has downloaded, in the order they were downloaded, as a
+//! timeline of what changed from one to the next.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::archive::SnapshotReader;
+use super::service_alerts::{ServiceAlertEntity, ServiceAlertRealtimeApi, ServiceAlertRoot};
+use super::trip_updates::{
+    TripDescriptor, TripScheduleRelationship, TripUpdate, TripUpdateRealtimeApi, TripUpdateRoot,
+};
+use super::utils::{all_cache_files, CachedRealtimeApi, CachedSnapshot};
+use super::vehicle_positions::{
+    VehiclePosition, VehiclePositionRoot, VehiclePositionsRealtimeApi,
+};
+use crate::error::Result;
+
+/// Which feed a snapshot is from. Snapshots downloaded at the same time are replayed in this
+///  order.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FeedKind {
+    ServiceAlerts,
+    TripUpdates,
+    VehiclePositions,
+}
+
+#[derive(Debug)]
+pub enum Snapshot {
+    ServiceAlerts(ServiceAlertRoot),
+    TripUpdates(TripUpdateRoot),
+    VehiclePositions(VehiclePositionRoot),
+}
+
+/// A trip on a particular day. Trip ids are reused every day the trip runs.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TripKey {
+    pub trip_id: String,
+    pub start_date: Option<String>,
+}
+
+impl TripKey {
+    fn of(trip: &TripDescriptor) -> Option<Self> {
+        Some(Self {
+            trip_id: trip.trip_id.clone()?,
+            start_date: trip.start_date.clone(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum StateChange {
+    AlertAdded(ServiceAlertEntity),
+    AlertRemoved { id: String },
+    TripCancelled { trip: TripKey },
+    /// A cancelled trip that's running again.
+    TripReinstated { trip: TripKey },
+    /// Delays are in seconds, but only changes to the minute are counted. A trip first seen less
+    ///  than a minute late hasn't changed.
+    DelayChanged {
+        trip: TripKey,
+        from: Option<i32>,
+        to: Option<i32>,
+    },
+    VehicleAssigned { trip: TripKey, vehicle_id: String },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReplayEvent {
+    /// When the snapshot with the change was downloaded.
+    #[serde(with = "time::serde::timestamp")]
+    pub time: OffsetDateTime,
+    pub change: StateChange,
+}
+
+/// The last known state of a trip, from both the trip updates and vehicle positions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TripState {
+    pub cancelled: bool,
+    pub delay: Option<i32>,
+    pub vehicle_id: Option<String>,
+}
+
+// Metlink only send the next stop, so its delay stands in for the trip's if there isn't one.
+fn trip_delay(update: &TripUpdate) -> Option<i32> {
+    update
+        .delay
+        .or_else(|| update.stop_time_update.iter().find_map(|stop| stop.delay()))
+}
+
+/// The realtime state, as rebuilt from the snapshots replayed so far.
+#[derive(Debug, Default)]
+pub struct ReplayState {
+    alerts: BTreeMap<String, ServiceAlertEntity>,
+    trips: BTreeMap<TripKey, TripState>,
+    vehicles: BTreeMap<String, VehiclePosition>,
+}
+
+impl ReplayState {
+    /// The alerts in the last service alerts snapshot.
+    pub fn alerts(&self) -> impl Iterator<Item = &ServiceAlertEntity> {
+        self.alerts.values()
+    }
+
+    /// Every trip seen so far. Trips stay once they drop out of the feed, so this is how they were
+    ///  last seen.
+    pub fn trips(&self) -> impl Iterator<Item = (&TripKey, &TripState)> {
+        self.trips.iter()
+    }
+
+    /// Where each vehicle was last seen.
+    pub fn vehicles(&self) -> impl Iterator<Item = (&String, &VehiclePosition)> {
+        self.vehicles.iter()
+    }
+
+    /// Updates the state from the next snapshot, returning what changed.
+    pub fn apply(&mut self, snapshot: Snapshot) -> Vec<StateChange> {
+        match snapshot {
+            Snapshot::ServiceAlerts(root) => self.apply_alerts(root),
+            Snapshot::TripUpdates(root) => self.apply_trip_updates(root),
+            Snapshot::VehiclePositions(root) => self.apply_vehicle_positions(root),
+        }
+    }
+
+    // Each snapshot has every alert, so those missing from it have been removed.
+    fn apply_alerts(&mut self, root: ServiceAlertRoot) -> Vec<StateChange> {
+        let mut alerts: BTreeMap<String, ServiceAlertEntity> = root
+            .entity
+            .into_iter()
+            .map(|entity| (entity.id.clone(), entity))
+            .collect();
+        let mut changes: Vec<StateChange> = self
+            .alerts
+            .keys()
+            .filter(|id| !alerts.contains_key(*id))
+            .map(|id| StateChange::AlertRemoved { id: id.clone() })
+            .collect();
+        changes.extend(
+            alerts
+                .values()
+                .filter(|entity| !self.alerts.contains_key(&entity.id))
+                .map(|entity| StateChange::AlertAdded(entity.clone())),
+        );
+        std::mem::swap(&mut self.alerts, &mut alerts);
+        changes
+    }
+
+    fn apply_trip_updates(&mut self, root: TripUpdateRoot) -> Vec<StateChange> {
+        let mut changes = vec![];
+        for update in root.trip_updates() {
+            let key = match TripKey::of(&update.trip) {
+                Some(key) => key,
+                None => continue,
+            };
+            let cancelled = matches!(
+                update.trip.schedule_relationship,
+                TripScheduleRelationship::Canceled | TripScheduleRelationship::Deleted
+            );
+            let delay = trip_delay(update);
+            let state = self.trips.entry(key.clone()).or_default();
+            if cancelled != state.cancelled {
+                changes.push(match cancelled {
+                    true => StateChange::TripCancelled { trip: key.clone() },
+                    false => StateChange::TripReinstated { trip: key.clone() },
+                });
+            }
+            let delay_changed = match (state.delay, delay) {
+                // Every trip turns up at some point, so only one already running late is news.
+                (None, Some(delay)) => delay / 60 != 0,
+                (from, to) => from.map(|d| d / 60) != to.map(|d| d / 60),
+            };
+            if !cancelled && delay_changed {
+                changes.push(StateChange::DelayChanged {
+                    trip: key,
+                    from: state.delay,
+                    to: delay,
+                });
+            }
+            state.cancelled = cancelled;
+            if !cancelled {
+                state.delay = delay;
+            }
+        }
+        changes
+    }
+
+    fn apply_vehicle_positions(&mut self, root: VehiclePositionRoot) -> Vec<StateChange> {
+        let mut changes = vec![];
+        for entity in root.entity {
+            let vehicle = entity.vehicle;
+            let vehicle_id = match vehicle.vehicle_id() {
+                Some(id) => id.to_string(),
+                None => continue,
+            };
+            if let Some(key) = vehicle.trip.as_ref().and_then(TripKey::of) {
+                let state = self.trips.entry(key.clone()).or_default();
+                if state.vehicle_id.as_ref() != Some(&vehicle_id) {
+                    state.vehicle_id = Some(vehicle_id.clone());
+                    changes.push(StateChange::VehicleAssigned {
+                        trip: key,
+                        vehicle_id: vehicle_id.clone(),
+                    });
+                }
+            }
+            self.vehicles.insert(vehicle_id, vehicle);
+        }
+        changes
+    }
+}
+
+/// Steps through the cached snapshots of all three feeds, one at a time, so only one is in memory
+///  at once.
+pub struct Replay {
+    files: VecDeque<(OffsetDateTime, FeedKind, CachedSnapshot)>,
+    // One per feed, so each keeps its archive while the feeds are interleaved.
+    readers: BTreeMap<FeedKind, SnapshotReader>,
+    pub state: ReplayState,
+}
+
+impl Replay {
+    pub async fn open(
+        service_alerts: &ServiceAlertRealtimeApi,
+        trip_updates: &TripUpdateRealtimeApi,
+        vehicle_positions: &VehiclePositionsRealtimeApi,
+    ) -> Result<Self> {
+        async fn files_of<T: CachedRealtimeApi>(
+            api: &T,
+            kind: FeedKind,
+        ) -> Result<Vec<(OffsetDateTime, FeedKind, CachedSnapshot)>> {
+            Ok(all_cache_files(api)
+                .await?
+                .into_iter()
+                .map(|(time, snapshot)| (time, kind, snapshot))
+                .collect())
+        }
+        let mut files = files_of(service_alerts, FeedKind::ServiceAlerts).await?;
+        files.extend(files_of(trip_updates, FeedKind::TripUpdates).await?);
+        files.extend(files_of(vehicle_positions, FeedKind::VehiclePositions).await?);
+        files.sort_by_key(|(time, kind, _)| (time.unix_timestamp(), *kind));
+        Ok(Self {
+            files: files.into(),
+            readers: BTreeMap::new(),
+            state: ReplayState::default(),
+        })
+    }
+
+    /// Only replays the snapshots downloaded from `start` up to but not including `end`. Anything
+    ///  already in the feed at `start` shows up as a change in the first snapshot.
+    pub fn between(mut self, start: OffsetDateTime, end: OffsetDateTime) -> Self {
+        self.files
+            .retain(|(time, _, _)| start <= *time && *time < end);
+        self
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Replays the next snapshot, returning when it was downloaded and what changed. Snapshots
+    ///  that can't be read are logged and skipped.
+    pub async fn next_snapshot(&mut self) -> Option<(OffsetDateTime, Vec<StateChange>)> {
+        while let Some((time, kind, cached)) = self.files.pop_front() {
+            let reader = self.readers.entry(kind).or_default();
+            let snapshot = match kind {
+                FeedKind::ServiceAlerts => reader.load(&cached).await.map(Snapshot::ServiceAlerts),
+                FeedKind::TripUpdates => reader.load(&cached).await.map(Snapshot::TripUpdates),
+                FeedKind::VehiclePositions => reader
+                    .load(&cached)
+                    .await
+                    .map(Snapshot::VehiclePositions),
+            };
+            match snapshot {
+                Ok(snapshot) => return Some((time, self.state.apply(snapshot))),
+                Err(e) => log::warn!("Unable to load {:?}: {:?}", cached, e),
+            }
+        }
+        None
+    }
+
+    /// Replays every remaining snapshot into one timeline.
+    pub async fn timeline(&mut self) -> Vec<ReplayEvent> {
+        let mut events = vec![];
+        while let Some((time, changes)) = self.next_snapshot().await {
+            events.extend(
+                changes
+                    .into_iter()
+                    .map(|change| ReplayEvent { time, change }),
+            );
+        }
+        events
+    }
+}
@@ -90,7 +90,7 @@ where
     })
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ServiceAlertHeader {
     pub gtfs_realtime_version: String,
     #[serde(with = "time::serde::timestamp")]
@@ -99,7 +99,7 @@ pub struct ServiceAlertHeader {
     pub incrementality: ServiceAlertIncrementability,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ServiceAlertEntity {
     pub alert: ServiceAlert,
     pub id: String,
@@ -122,7 +122,7 @@ pub enum ServiceAlertEffect {
     NoEffect,
     AccessibilityIssue,
 }
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ServiceAlertCause {
     UnknownCause,
@@ -147,7 +147,7 @@ pub enum ServiceAlertSeverity {
     Severe,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ServiceAlert {
     pub active_period: Vec<AlertTimeRange>,
     pub effect: ServiceAlertEffect,
@@ -166,7 +166,7 @@ impl ServiceAlert {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AlertTimeRange {
     #[serde(with = "time::serde::timestamp")]
     pub start: OffsetDateTime,
@@ -174,7 +174,7 @@ pub struct AlertTimeRange {
     pub end: OffsetDateTime,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Translatation {
     pub translation: Vec<TranslatedText>,
 }
@@ -210,13 +210,13 @@ impl Translatation {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TranslatedText {
     pub language: String,
     pub text: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum AlertInformedEntity {
     Route { route_id: String, route_type: i32 },
@@ -224,7 +224,7 @@ pub enum AlertInformedEntity {
     Trip { trip: TripEntity },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TripEntity {
     pub trip_id: String,
 }
@@ -4,7 +4,8 @@ use reqwest::Client;
 use time::{ OffsetDateTime};
 use serde::{Deserialize, Deserializer, Serialize};
 
-use super::utils::CachedRealtimeApi;
+use super::service_alerts::ServiceAlertHeader;
+use super::utils::{CachedRealtimeApi, NumberOrName};
 
 pub struct TripUpdateRealtimeApi {
     cache_dir: PathBuf,
@@ -37,4 +38,210 @@ impl CachedRealtimeApi for TripUpdateRealtimeApi {
     fn min_fetch_frequency(&self) -> Duration {
         Duration::from_secs(5 * 60)
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TripUpdateRoot {
+    pub header: ServiceAlertHeader,
+    #[serde(default)]
+    pub entity: Vec<TripUpdateEntity>,
+}
+
+impl TripUpdateRoot {
+    pub fn trip_updates(&self) -> impl Iterator<Item = &TripUpdate> {
+        self.entity.iter().map(|e| &e.trip_update)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TripUpdateEntity {
+    pub id: String,
+    pub trip_update: TripUpdate,
+}
+
+/// Realtime changes to a trip: whether it's running at all, and how late it is at each stop.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TripUpdate {
+    pub trip: TripDescriptor,
+    #[serde(default)]
+    pub vehicle: Option<VehicleDescriptor>,
+    #[serde(default, deserialize_with = "deserialize_stop_time_updates")]
+    pub stop_time_update: Vec<StopTimeUpdate>,
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub timestamp: Option<OffsetDateTime>,
+    /// Seconds late (or early, if negative) the trip is running overall.
+    #[serde(default)]
+    pub delay: Option<i32>,
+}
+
+// Metlink only sends the next stop, as an object rather than an array of one.
+pub fn deserialize_stop_time_updates<'de, D>(deserializer: D) -> Result<Vec<StopTimeUpdate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(StopTimeUpdate),
+        Many(Vec<StopTimeUpdate>),
+    }
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        Some(OneOrMany::One(update)) => vec![update],
+        Some(OneOrMany::Many(updates)) => updates,
+        None => vec![],
+    })
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TripScheduleRelationship {
+    #[default]
+    Scheduled,
+    /// An extra trip that isn't in the timetable.
+    Added,
+    Unscheduled,
+    Canceled,
+    /// Replaces the timetabled trip with the same id.
+    Replacement,
+    Duplicated,
+    Deleted,
+}
+
+impl TripScheduleRelationship {
+    /// The value of the enum in the GTFS-RT protobuf, and Metlink's JSON.
+    pub fn from_number(n: u64) -> Option<Self> {
+        match n {
+            0 => Some(TripScheduleRelationship::Scheduled),
+            1 => Some(TripScheduleRelationship::Added),
+            2 => Some(TripScheduleRelationship::Unscheduled),
+            3 => Some(TripScheduleRelationship::Canceled),
+            5 => Some(TripScheduleRelationship::Replacement),
+            6 => Some(TripScheduleRelationship::Duplicated),
+            7 => Some(TripScheduleRelationship::Deleted),
+            _ => None,
+        }
+    }
+}
+
+pub fn deserialize_trip_schedule_relationship<'de, D>(
+    deserializer: D,
+) -> Result<TripScheduleRelationship, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Deserialize::deserialize(deserializer)? {
+        // Like the protobuf, values added to the reference since (like `NEW`) are left as scheduled.
+        NumberOrName::Number(n) => {
+            Ok(TripScheduleRelationship::from_number(n.into()).unwrap_or_default())
+        }
+        NumberOrName::Name(s) => {
+            Ok(serde_json::from_value(serde_json::Value::String(s)).unwrap_or_default())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TripDescriptor {
+    #[serde(default)]
+    pub trip_id: Option<String>,
+    #[serde(default)]
+    pub route_id: Option<String>,
+    #[serde(default)]
+    pub direction_id: Option<u8>,
+    /// When the trip was timetabled to leave, like `"08:03:00"`. Trips after midnight are
+    ///  `"24:xx:xx"` and later, on the previous day.
+    #[serde(default)]
+    pub start_time: Option<String>,
+    /// The service date, like `"20210215"`.
+    #[serde(default)]
+    pub start_date: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_trip_schedule_relationship")]
+    pub schedule_relationship: TripScheduleRelationship,
+}
+
+/// The bus, train or ferry running a trip.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct VehicleDescriptor {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub license_plate: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StopScheduleRelationship {
+    #[default]
+    Scheduled,
+    /// The stop isn't served, though the rest of the trip runs.
+    Skipped,
+    NoData,
+    Unscheduled,
+}
+
+impl StopScheduleRelationship {
+    /// The value of the enum in the GTFS-RT protobuf, and Metlink's JSON.
+    pub fn from_number(n: u64) -> Option<Self> {
+        match n {
+            0 => Some(StopScheduleRelationship::Scheduled),
+            1 => Some(StopScheduleRelationship::Skipped),
+            2 => Some(StopScheduleRelationship::NoData),
+            3 => Some(StopScheduleRelationship::Unscheduled),
+            _ => None,
+        }
+    }
+}
+
+pub fn deserialize_stop_schedule_relationship<'de, D>(
+    deserializer: D,
+) -> Result<StopScheduleRelationship, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Deserialize::deserialize(deserializer)? {
+        // As for trips, anything unknown is left as scheduled.
+        NumberOrName::Number(n) => {
+            Ok(StopScheduleRelationship::from_number(n.into()).unwrap_or_default())
+        }
+        NumberOrName::Name(s) => {
+            Ok(serde_json::from_value(serde_json::Value::String(s)).unwrap_or_default())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StopTimeUpdate {
+    #[serde(default)]
+    pub stop_sequence: Option<u16>,
+    #[serde(default)]
+    pub stop_id: Option<String>,
+    #[serde(default)]
+    pub arrival: Option<StopTimeEvent>,
+    #[serde(default)]
+    pub departure: Option<StopTimeEvent>,
+    #[serde(default, deserialize_with = "deserialize_stop_schedule_relationship")]
+    pub schedule_relationship: StopScheduleRelationship,
+}
+
+impl StopTimeUpdate {
+    /// Seconds late at this stop, going by the departure if there is one.
+    pub fn delay(&self) -> Option<i32> {
+        self.departure
+            .as_ref()
+            .and_then(|e| e.delay)
+            .or_else(|| self.arrival.as_ref().and_then(|e| e.delay))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StopTimeEvent {
+    /// Seconds late, or early if negative.
+    #[serde(default)]
+    pub delay: Option<i32>,
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub time: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub uncertainty: Option<i32>,
+}
@@ -3,17 +3,89 @@ use std::{
     time::Duration,
 };
 
-use reqwest::RequestBuilder;
+use reqwest::{header::CONTENT_TYPE, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use time::OffsetDateTime;
 use tokio::{
     fs::{create_dir_all, read_dir, File},
     io::AsyncWriteExt,
+    task::spawn_blocking,
 };
 
+use super::archive::{archive_day, read_archive_times, SnapshotReader};
 use crate::error::Result;
 
-const FILENAME_FORMAT: &str = "%Y-%m-%d %H.%M.%S%z.json";
+// The extension is added to this, and says which format the download is in.
+const FILENAME_FORMAT: &str = "%Y-%m-%d %H.%M.%S%z";
 
+// The JSON feeds have enums as numbers, but they're written as names in the GTFS-RT reference
+//  and by some converters, so both are accepted.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum NumberOrName {
+    Number(u8),
+    Name(String),
+}
+
+/// How a GTFS-RT feed is encoded. Metlink serve JSON, but the standard is protobuf.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    Json,
+    Protobuf,
+}
+
+impl FeedFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            FeedFormat::Json => "json",
+            FeedFormat::Protobuf => "pb",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(FeedFormat::Json),
+            "pb" => Some(FeedFormat::Protobuf),
+            _ => None,
+        }
+    }
+
+    /// Protobuf is served as all of `application/x-protobuf`, `application/protobuf` and
+    ///  `application/octet-stream`, so anything binary is taken to be it.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        if mime.ends_with("json") {
+            Some(FeedFormat::Json)
+        } else if mime.ends_with("protobuf") || mime == "application/octet-stream" {
+            Some(FeedFormat::Protobuf)
+        } else {
+            None
+        }
+    }
+
+    // Without a content type, go with what Metlink serve.
+    fn of_response(response: &Response) -> Self {
+        response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(FeedFormat::from_content_type)
+            .unwrap_or(FeedFormat::Json)
+    }
+}
+
+/// A GTFS-RT feed, which can be read from JSON or protobuf.
+pub trait RealtimeFeed: DeserializeOwned {
+    fn from_protobuf(buf: &[u8]) -> Result<Self>;
+
+    fn decode(buf: &[u8], format: FeedFormat) -> Result<Self> {
+        match format {
+            FeedFormat::Json => Ok(serde_json::from_slice(buf)?),
+            FeedFormat::Protobuf => Self::from_protobuf(buf),
+        }
+    }
+}
 
 pub trait CachedRealtimeApi {
     fn root_cache_dir(&self) -> &Path;
@@ -25,60 +97,116 @@ pub trait CachedRealtimeApi {
         self.root_cache_dir().join(self.name())
     }
 
-    fn cache_file(&self, now: OffsetDateTime) -> PathBuf {
-        self.cache_folder().join(now.format(FILENAME_FORMAT))
+    fn cache_file(&self, now: OffsetDateTime, format: FeedFormat) -> PathBuf {
+        self.cache_folder()
+            .join(now.format(FILENAME_FORMAT))
+            .with_extension(format.extension())
     }
 }
 
-pub async fn all_cache_files<T: CachedRealtimeApi>(
+/// Where a cached download is kept.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CachedSnapshot {
+    /// In a file of its own, as it was downloaded.
+    File(PathBuf),
+    /// Compacted into a day's archive, as the `index`th download in it.
+    Archived { archive: PathBuf, index: usize },
+}
+
+/// When a download was made, if `path` is one.
+pub(crate) fn download_time(path: &Path) -> Option<OffsetDateTime> {
+    FeedFormat::from_path(path)?;
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|name| time::OffsetDateTime::parse(name, FILENAME_FORMAT).ok())
+}
+
+// The downloads that are still in files of their own, and the archives the rest are in.
+async fn read_cache_folder<T: CachedRealtimeApi>(
     api: &T,
-) -> Result<Vec<(OffsetDateTime, PathBuf)>> {
+) -> Result<(Vec<(OffsetDateTime, PathBuf)>, Vec<PathBuf>)> {
     let cache_folder = api.cache_folder();
     create_dir_all(&cache_folder).await?;
 
     let mut read_dirs = read_dir(cache_folder).await?;
 
-    let mut items = vec![];
+    let mut downloads = vec![];
+    let mut archives = vec![];
     while let Some(e) = read_dirs.next_entry().await? {
-        match e.file_name().into_string() {
-            Ok(name) => match time::OffsetDateTime::parse(name, FILENAME_FORMAT) {
-                Ok(date) => items.push((date, e.path())),
-                _ => {}
-            },
-            _ => {}
+        let path = e.path();
+        if let Some(date) = download_time(&path) {
+            downloads.push((date, path));
+        } else if archive_day(&path).is_some() {
+            archives.push(path);
         }
     }
-    items.sort_unstable_by_key(|(date, _)| date.unix_timestamp());
+    downloads.sort_unstable_by_key(|(date, _)| date.unix_timestamp());
+    Ok((downloads, archives))
+}
+
+/// Every cached download, earliest first, including those compacted into archives. Archives that
+///  can't be read are logged and skipped.
+pub async fn all_cache_files<T: CachedRealtimeApi>(
+    api: &T,
+) -> Result<Vec<(OffsetDateTime, CachedSnapshot)>> {
+    let (downloads, archives) = read_cache_folder(api).await?;
+
+    let mut items: Vec<(OffsetDateTime, CachedSnapshot)> = downloads
+        .into_iter()
+        .map(|(date, path)| (date, CachedSnapshot::File(path)))
+        .collect();
+    for archive in archives {
+        let path = archive.clone();
+        match spawn_blocking(move || read_archive_times(&path)).await.unwrap() {
+            Ok(times) => items.extend(times.into_iter().enumerate().map(|(index, date)| {
+                (
+                    date,
+                    CachedSnapshot::Archived {
+                        archive: archive.clone(),
+                        index,
+                    },
+                )
+            })),
+            Err(e) => log::warn!("Unable to read {:?}: {:?}", archive, e),
+        }
+    }
+    // Stable, so an archive's downloads stay in the order they're stored.
+    items.sort_by_key(|(date, _)| date.unix_timestamp());
     Ok(items)
 }
 
-// pub async fn load_from_file<I: 'static + DeserializeOwned + Send>(file: PathBuf) -> Result<I> {
-//     spawn_blocking(move || -> Result<I> {
-//         Ok(serde_json::from_reader(std::fs::File::open(file)?)?)
-//     })
-//     .await
-//     .unwrap()
-// }
-
-// pub async fn load_all_from_files<T: CachedRealtimeApi<I>, I: 'static + DeserializeOwned + Send>(
-//     api: &T,
-// ) -> Result<Vec<I>> {
-//     let all_files = all_cache_files(api).await?;
-
-//     let mut entities = vec![];
-//     for (_, path) in all_files {
-//         match load_from_file::<I>(path).await {
-//             Ok(obj) => entities.push(obj),
-//             _ => {}
-//         }
-//     }
-//     Ok(entities)
-// }
+/// Reads a cached download, in the format its extension says.
+pub async fn load_from_file<I: 'static + RealtimeFeed + Send>(file: PathBuf) -> Result<I> {
+    spawn_blocking(move || -> Result<I> {
+        let format = FeedFormat::from_path(&file).unwrap_or(FeedFormat::Json);
+        I::decode(&std::fs::read(&file)?, format)
+    })
+    .await
+    .unwrap()
+}
+
+/// Loads every cached download, earliest first. Downloads that can't be read are logged and
+///  skipped.
+pub async fn load_all_from_files<T: CachedRealtimeApi, I: 'static + RealtimeFeed + Send>(
+    api: &T,
+) -> Result<Vec<I>> {
+    let all_files = all_cache_files(api).await?;
+
+    let mut reader = SnapshotReader::default();
+    let mut entities = vec![];
+    for (_, snapshot) in all_files {
+        match reader.load::<I>(&snapshot).await {
+            Ok(obj) => entities.push(obj),
+            Err(e) => log::warn!("Unable to load {:?}: {:?}", snapshot, e),
+        }
+    }
+    Ok(entities)
+}
 
 pub async fn download_latest<T: CachedRealtimeApi>(api: &T) -> Result<PathBuf> {
     let now = OffsetDateTime::now_utc();
-    let file = api.cache_file(now);
     let mut response = api.download().send().await?.error_for_status()?;
+    let file = api.cache_file(now, FeedFormat::of_response(&response));
 
     let mut writer = File::create(&file).await?;
     while let Some(mut item) = response.chunk().await? {
@@ -87,9 +215,17 @@ pub async fn download_latest<T: CachedRealtimeApi>(api: &T) -> Result<PathBuf> {
     return Ok(file);
 }
 
+/// Downloads and decodes the feed, without caching it.
+pub async fn fetch_latest<T: CachedRealtimeApi, I: RealtimeFeed>(api: &T) -> Result<I> {
+    let response = api.download().send().await?.error_for_status()?;
+    let format = FeedFormat::of_response(&response);
+    I::decode(&response.bytes().await?, format)
+}
+
 pub async fn download_latest_if_needed<T: CachedRealtimeApi>(api: &T) -> Result<PathBuf> {
-    let all_files = all_cache_files(api).await?;
-    if let Some((date, path)) = all_files.into_iter().next() {
+    // Archives are only of days that are over, so there's no need to read them.
+    let (downloads, _) = read_cache_folder(api).await?;
+    if let Some((date, path)) = downloads.into_iter().next() {
         if OffsetDateTime::now_utc() - date < api.min_fetch_frequency() {
             return Ok(path);
         }
@@ -4,7 +4,9 @@ use reqwest::Client;
 use time::{ OffsetDateTime};
 use serde::{Deserialize, Deserializer, Serialize};
 
-use super::utils::CachedRealtimeApi;
+use super::service_alerts::ServiceAlertHeader;
+use super::trip_updates::{TripDescriptor, VehicleDescriptor};
+use super::utils::{CachedRealtimeApi, NumberOrName};
 
 pub struct VehiclePositionsRealtimeApi {
     cache_dir: PathBuf,
@@ -37,4 +39,108 @@ impl CachedRealtimeApi for VehiclePositionsRealtimeApi {
     fn min_fetch_frequency(&self) -> Duration {
         Duration::from_secs(5 * 60)
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VehiclePositionRoot {
+    pub header: ServiceAlertHeader,
+    #[serde(default)]
+    pub entity: Vec<VehiclePositionEntity>,
+}
+
+impl VehiclePositionRoot {
+    pub fn positions(&self) -> impl Iterator<Item = &VehiclePosition> {
+        self.entity.iter().map(|e| &e.vehicle)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VehiclePositionEntity {
+    pub id: String,
+    pub vehicle: VehiclePosition,
+}
+
+/// Where a vehicle was when it last reported, and the trip it was running.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VehiclePosition {
+    #[serde(default)]
+    pub trip: Option<TripDescriptor>,
+    #[serde(default)]
+    pub vehicle: Option<VehicleDescriptor>,
+    #[serde(default)]
+    pub position: Option<Position>,
+    #[serde(default)]
+    pub stop_id: Option<String>,
+    #[serde(default)]
+    pub current_stop_sequence: Option<u16>,
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub timestamp: Option<OffsetDateTime>,
+    #[serde(default, deserialize_with = "deserialize_occupancy_status")]
+    pub occupancy_status: Option<OccupancyStatus>,
+}
+
+impl VehiclePosition {
+    pub fn vehicle_id(&self) -> Option<&str> {
+        self.vehicle.as_ref().and_then(|v| v.id.as_deref())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Position {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Degrees clockwise from north.
+    #[serde(default)]
+    pub bearing: Option<f64>,
+    /// Metres per second.
+    #[serde(default)]
+    pub speed: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OccupancyStatus {
+    Empty,
+    ManySeatsAvailable,
+    FewSeatsAvailable,
+    StandingRoomOnly,
+    CrushedStandingRoomOnly,
+    Full,
+    NotAcceptingPassengers,
+    NoDataAvailable,
+    NotBoardable,
+}
+
+impl OccupancyStatus {
+    /// The value of the enum in the GTFS-RT protobuf, and Metlink's JSON.
+    pub fn from_number(n: u64) -> Option<Self> {
+        match n {
+            0 => Some(OccupancyStatus::Empty),
+            1 => Some(OccupancyStatus::ManySeatsAvailable),
+            2 => Some(OccupancyStatus::FewSeatsAvailable),
+            3 => Some(OccupancyStatus::StandingRoomOnly),
+            4 => Some(OccupancyStatus::CrushedStandingRoomOnly),
+            5 => Some(OccupancyStatus::Full),
+            6 => Some(OccupancyStatus::NotAcceptingPassengers),
+            7 => Some(OccupancyStatus::NoDataAvailable),
+            8 => Some(OccupancyStatus::NotBoardable),
+            _ => None,
+        }
+    }
+}
+
+pub fn deserialize_occupancy_status<'de, D>(
+    deserializer: D,
+) -> Result<Option<OccupancyStatus>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<NumberOrName>::deserialize(deserializer)? {
+        None => Ok(None),
+        // Like the protobuf, a status added to the reference since is left out.
+        Some(NumberOrName::Number(n)) => Ok(OccupancyStatus::from_number(n.into())),
+        Some(NumberOrName::Name(s)) => {
+            Ok(serde_json::from_value(serde_json::Value::String(s)).ok())
+        }
+    }
+}
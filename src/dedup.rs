@@ -0,0 +1,220 @@
+use crate::parser::Cancellations;
+use crate::reconcile::{location_key, TripStatus};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+// How far back a correction looks for the tweet it's correcting.
+const CORRECTION_WINDOW_MINUTES: i64 = 60;
+
+/// An event, along with every tweet that announced it.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Announcement {
+    pub tweet_ids: Vec<u64>,
+    pub event: Cancellations,
+    /// The links the events came with, from each of those tweets.
+    #[serde(default)]
+    pub links: Vec<String>,
+}
+
+fn same_endpoints(a: &Cancellations, b: &Cancellations) -> bool {
+    a.route() == b.route()
+        && location_key(a.origin()) == location_key(b.origin())
+        && location_key(a.destination()) == location_key(b.destination())
+}
+
+fn same_trip(a: &Cancellations, b: &Cancellations) -> bool {
+    same_endpoints(a, b) && a.time() == b.time()
+}
+
+/// Applies corrections to the events they correct, and collapses repeated announcements of the
+///  same trip into the first one. A repeated delay is updated to the latest, as it's usually
+///  been revised. Stops are compared by their GTFS id when they've been resolved, so resolve
+///  them first.
+pub fn dedup(events: &[(u64, Cancellations)]) -> Vec<Announcement> {
+    let mut ordered: Vec<&(u64, Cancellations)> = events.iter().collect();
+    ordered.sort_by_key(|(id, event)| (*event.tweet_time(), *id));
+
+    let mut announcements: Vec<Announcement> = vec![];
+    for (id, event) in ordered {
+        let existing = match event {
+            Cancellations::Correction { event: corrected } => {
+                let status = TripStatus::from(corrected.as_ref());
+                let recent: Vec<&mut Announcement> = announcements
+                    .iter_mut()
+                    .rev()
+                    .take_while(|a| {
+                        *corrected.tweet_time() - *a.event.tweet_time()
+                            <= Duration::minutes(CORRECTION_WINDOW_MINUTES)
+                    })
+                    .collect();
+                // Usually the time is what's being corrected, so look for the same trip
+                //  endpoints first, then fall back to the same route and time.
+                let position = recent
+                    .iter()
+                    .position(|a| {
+                        same_endpoints(&a.event, corrected) && TripStatus::from(&a.event) == status
+                    })
+                    .or_else(|| {
+                        recent.iter().position(|a| {
+                            a.event.route() == corrected.route()
+                                && a.event.time() == corrected.time()
+                        })
+                    });
+                position.and_then(|idx| recent.into_iter().nth(idx))
+            }
+            _ => announcements
+                .iter_mut()
+                .rev()
+                .find(|a| same_trip(&a.event, event))
+                .filter(|a| TripStatus::from(&a.event) == TripStatus::from(event)),
+        };
+        match existing {
+            Some(announcement) => {
+                match event {
+                    Cancellations::Correction { event: corrected } => {
+                        announcement.event = corrected.as_ref().clone();
+                    }
+                    _ if TripStatus::from(event) == TripStatus::Delayed => {
+                        let cause = announcement.event.cause().cloned();
+                        announcement.event = event.clone();
+                        if announcement.event.cause().is_none() {
+                            *announcement.event.cause_mut() = cause;
+                        }
+                    }
+                    _ => {}
+                }
+                if !announcement.tweet_ids.contains(id) {
+                    announcement.tweet_ids.push(*id);
+                }
+                if let Some(link) = event.link() {
+                    if !announcement.links.iter().any(|l| l == link) {
+                        announcement.links.push(link.to_string());
+                    }
+                }
+            }
+            None => announcements.push(Announcement {
+                tweet_ids: vec![*id],
+                event: match event {
+                    Cancellations::Correction { event } => event.as_ref().clone(),
+                    _ => event.clone(),
+                },
+                links: event.link().map(str::to_string).into_iter().collect(),
+            }),
+        }
+    }
+    announcements
+}
+
+#[cfg(test)]
+mod test_dedup {
+    use super::*;
+    use crate::parser::{Delay, Location, ResolvedStop};
+    use crate::time::convert_time_to_instant;
+    use chrono::{DateTime, Utc};
+
+    fn held(hour: u32, minute: u32, tweeted_at: &str) -> Cancellations {
+        let tweet_time = DateTime::parse_from_rfc3339(tweeted_at)
+            .unwrap()
+            .with_timezone(&Utc);
+        Cancellations::TrainHeld {
+            line: "HVL".to_string(),
+            origin: "UPPE".into(),
+            destination: "WELL".into(),
+            held_at: "EPUN".into(),
+            cause: Some("tresspasser on the tracks".into()),
+            raw_time: "".to_string(),
+            tweet_time,
+            time: convert_time_to_instant(tweet_time, hour, minute).unwrap(),
+            link: None,
+        }
+    }
+
+    #[test]
+    fn test_dedup_repeats() {
+        let mut events = vec![
+            (1, held(19, 30, "2020-10-09T07:17:02Z")),
+            (2, held(19, 30, "2020-10-09T07:27:02Z")),
+            (3, held(20, 0, "2020-10-09T07:27:02Z")),
+        ];
+        *events[1].1.link_mut() = Some("https://t.co/Vg6Fa60KIm".to_string());
+        let announcements = dedup(&events);
+        assert_eq!(2, announcements.len());
+        assert_eq!(vec![1, 2], announcements[0].tweet_ids);
+        assert_eq!(events[0].1, announcements[0].event);
+        assert_eq!(
+            vec!["https://t.co/Vg6Fa60KIm".to_string()],
+            announcements[0].links
+        );
+        assert_eq!(vec![3], announcements[1].tweet_ids);
+        assert!(announcements[1].links.is_empty());
+    }
+
+    #[test]
+    fn test_dedup_revised_delay() {
+        let delayed = |minutes: u32, tweeted_at: &str| {
+            let tweet_time = DateTime::parse_from_rfc3339(tweeted_at)
+                .unwrap()
+                .with_timezone(&Utc);
+            Cancellations::TrainDelayed {
+                line: "HVL".to_string(),
+                origin: "UPPE".into(),
+                destination: "WELL".into(),
+                delayed_from: "UPPE".into(),
+                delay: Delay::Minutes(minutes),
+                cause: None,
+                raw_time: "".to_string(),
+                tweet_time,
+                time: convert_time_to_instant(tweet_time, 19, 30).unwrap(),
+                link: None,
+            }
+        };
+        let events = vec![
+            (1, delayed(15, "2020-10-09T07:17:02Z")),
+            (2, delayed(38, "2020-10-09T07:37:02Z")),
+        ];
+        let announcements = dedup(&events);
+        assert_eq!(1, announcements.len());
+        assert_eq!(vec![1, 2], announcements[0].tweet_ids);
+        assert_eq!(Some(&Delay::Minutes(38)), announcements[0].event.delay());
+    }
+
+    #[test]
+    fn test_dedup_resolved_stops() {
+        let at_stop = |raw: &str| Location {
+            raw: raw.to_string(),
+            stop: Some(ResolvedStop {
+                id: "JOHN".to_string(),
+                lat: -41.2,
+                lon: 174.8,
+            }),
+        };
+        let mut first = held(19, 30, "2020-10-09T07:17:02Z");
+        *first.locations_mut()[0] = at_stop("Johnsonville West");
+        let mut second = held(19, 30, "2020-10-09T07:27:02Z");
+        *second.locations_mut()[0] = at_stop("Johnsonville West - Stop A");
+        let announcements = dedup(&[(1, first), (2, second)]);
+        assert_eq!(1, announcements.len());
+    }
+
+    #[test]
+    fn test_dedup_correction() {
+        let events = vec![
+            (1, held(20, 0, "2020-10-09T07:18:04Z")),
+            (
+                2,
+                Cancellations::Correction {
+                    event: Box::new(held(19, 30, "2020-10-09T07:20:03Z")),
+                },
+            ),
+        ];
+        let announcements = dedup(&events);
+        assert_eq!(
+            vec![Announcement {
+                tweet_ids: vec![1, 2],
+                event: held(19, 30, "2020-10-09T07:20:03Z"),
+                links: vec![],
+            }],
+            announcements
+        );
+    }
+}
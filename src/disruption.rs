@@ -0,0 +1,409 @@
+use crate::parser::{cause_from_text, TRAIN_LINE_NAMES};
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, Offset, TimeZone, Utc,
+};
+use chrono_tz::Pacific::Auckland;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+// The line names used in the rail replacement tweets, and the codes used everywhere else.
+const LINE_NAMES: [(&str, &str); 6] = [
+    ("kapiti", "KPL"),
+    ("kāpiti", "KPL"),
+    ("hutt valley", "HVL"),
+    ("melling", "MEL"),
+    ("johnsonville", "JVL"),
+    ("wairarapa", "WRL"),
+];
+// Longer than any planned works seen so far; anything longer is a date that's been misread.
+const MAX_WINDOW_DAYS: i64 = 120;
+
+lazy_static! {
+    static ref DATE_RE: Regex = Regex::new(&format!(
+        r"\b(?P<weekday>{0})(?: (?P<day>[0-9]{{1,2}})(?:st|nd|rd|th)?(?: (?P<month>{1}))?)?\b|\b(?P<today>[Tt]oday)\b",
+        WEEKDAYS.join("|"),
+        MONTHS.join("|")
+    ))
+    .unwrap();
+    // The hour can follow a letter, as in "Update12:05pm", but not another digit.
+    static ref CLOCK_RE: Regex = Regex::new(
+        r"(?:^|[^0-9.:])(?P<hour>[0-9]{1,2})(?:[.:](?P<minute>[0-9]{2}))?(?P<period>am|pm)\b"
+    )
+    .unwrap();
+    // How the planned works are announced; live updates say "Buses are replacing" or follow an
+    //  "Update 2:30pm:".
+    static ref PLANNED_RE: Regex = Regex::new(&format!(
+        r"^(?:(?:{}): )?(?:Today )?[Bb]uses replace\b",
+        TRAIN_LINE_NAMES.join("|")
+    ))
+    .unwrap();
+    static ref PLANNED_CAUSE_RE: Regex = Regex::new(
+        r"(?i)\b(?:road ?works|maintenance|project|upgrade|resurfacing|festival|parade|celebrations?)\b"
+    )
+    .unwrap();
+    static ref UNTIL_RE: Regex = Regex::new(r"\buntil [0-9]").unwrap();
+    static ref LINE_PREFIX_RE: Regex = Regex::new(&format!(
+        r"^(?P<lines>(?:{0})(?:/(?:{0}))*)\b",
+        TRAIN_LINE_NAMES.join("|")
+    ))
+    .unwrap();
+    static ref LINE_NAME_RE: Regex =
+        Regex::new(r"(?i)\b(?P<name>kapiti|kāpiti|hutt valley|melling|johnsonville|wairarapa) line")
+            .unwrap();
+    static ref ALL_LINES_RE: Regex = Regex::new(r"(?i)\ball lines\b").unwrap();
+    static ref BUS_ROUTES_RE: Regex = Regex::new(
+        r"\b(?:Bus|bus|Route|route) #?(?P<routes>[0-9]+[ex]?(?:(?:, | and | & |/)[0-9]+[ex]?)*)\b"
+    )
+    .unwrap();
+    static ref ROUTE_NUMBER_RE: Regex = Regex::new(r"[0-9]+[ex]?").unwrap();
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum DisruptionKind {
+    /// Buses run instead of trains.
+    RailReplacement,
+    /// Buses take a different route around part of their usual one.
+    Detour,
+    /// A road is closed and buses can't get through.
+    RoadClosure,
+}
+
+/// When a disruption starts and ends, in Wellington time.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct DisruptionWindow {
+    pub start: DateTime<FixedOffset>,
+    /// Exclusive; `None` when the tweet doesn't say when it'll be over.
+    pub end: Option<DateTime<FixedOffset>>,
+}
+
+impl DisruptionWindow {
+    pub fn duration(&self) -> Option<Duration> {
+        self.end.map(|end| end - self.start)
+    }
+}
+
+fn local_instant(date: NaiveDate, time: NaiveTime) -> Option<DateTime<FixedOffset>> {
+    let local = Auckland
+        .from_local_datetime(&date.and_time(time))
+        .earliest()?;
+    Some(local.with_timezone(&local.offset().fix()))
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    MONTHS
+        .iter()
+        .position(|month| *month == name)
+        .map(|idx| idx as u32 + 1)
+}
+
+// "Saturday 10" is the next 10th on or after the tweet (give or take a day for time zones), and
+//  "Saturday" on its own is the next Saturday. A date that isn't on the weekday given has been cut
+//  short ("Wednesday 1..."), so is left out.
+fn resolve_date(
+    today: NaiveDate,
+    weekday: Option<&str>,
+    day: Option<u32>,
+    month: Option<u32>,
+) -> Option<NaiveDate> {
+    let yesterday = today - Duration::days(1);
+    let wanted = WEEKDAYS.iter().position(|w| Some(*w) == weekday);
+    let date = match (day, month) {
+        (Some(day), Some(month)) => {
+            let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+            if date < yesterday - Duration::days(180) {
+                NaiveDate::from_ymd_opt(today.year() + 1, month, day)
+            } else {
+                Some(date)
+            }
+        }
+        (Some(day), None) => {
+            let date = NaiveDate::from_ymd_opt(today.year(), today.month(), day)?;
+            if date < yesterday {
+                let (year, month) = if today.month() == 12 {
+                    (today.year() + 1, 1)
+                } else {
+                    (today.year(), today.month() + 1)
+                };
+                NaiveDate::from_ymd_opt(year, month, day)
+            } else {
+                Some(date)
+            }
+        }
+        (None, _) => {
+            let ahead = (7 + wanted? as u32 - today.weekday().num_days_from_monday()) % 7;
+            Some(today + Duration::days(ahead.into()))
+        }
+    }?;
+    match wanted {
+        Some(wanted) if wanted as u32 != date.weekday().num_days_from_monday() => None,
+        _ => Some(date),
+    }
+}
+
+fn dates_from_text(text: &str, today: NaiveDate) -> Vec<NaiveDate> {
+    let captures: Vec<_> = DATE_RE.captures_iter(text).collect();
+    captures
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, capture)| {
+            if capture.name("today").is_some() {
+                return Some(today);
+            }
+            let day = capture.name("day").and_then(|m| m.as_str().parse().ok());
+            // "Saturday 10 & Sunday 11 October"; the month is only given once, at the end.
+            let month = captures[idx..]
+                .iter()
+                .find_map(|c| c.name("month"))
+                .and_then(|m| month_number(m.as_str()));
+            let month = if day.is_some() { month } else { None };
+            resolve_date(
+                today,
+                capture.name("weekday").map(|m| m.as_str()),
+                day,
+                month,
+            )
+        })
+        .collect()
+}
+
+fn clock_times(text: &str) -> Vec<NaiveTime> {
+    CLOCK_RE
+        .captures_iter(text)
+        .filter_map(|capture| {
+            let hour: u32 = capture.name("hour")?.as_str().parse().ok()?;
+            let minute = capture
+                .name("minute")
+                .map(|m| m.as_str().parse().ok())
+                .unwrap_or(Some(0))?;
+            let hour = match (capture.name("period")?.as_str(), hour) {
+                ("am", 12) => 0,
+                ("pm", 12) => 12,
+                ("pm", hour) => hour + 12,
+                (_, hour) => hour,
+            };
+            NaiveTime::from_hms_opt(hour, minute, 0)
+        })
+        .collect()
+}
+
+/// Works out the window from the dates and times in the tweet, and whether it was planned. A
+///  planned disruption is announced as "Buses replace ..." or gives the dates, without an incident
+///  to blame. An unplanned one is happening now, and it isn't known when it'll end.
+pub fn window_from_text(text: &str, tweet_time: DateTime<Utc>) -> (DisruptionWindow, bool) {
+    let today = tweet_time.with_timezone(&Auckland).naive_local().date();
+    let dates = dates_from_text(text, today);
+    let times = clock_times(text);
+    let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+    let incident = cause_from_text(text)
+        .map(|cause| !PLANNED_CAUSE_RE.is_match(&cause.raw))
+        .unwrap_or(false);
+    let planned = !incident && (PLANNED_RE.is_match(text) || !dates.is_empty());
+
+    if !planned || (dates.is_empty() && times.is_empty()) {
+        return (
+            DisruptionWindow {
+                start: tweet_time
+                    .with_timezone(&tweet_time.with_timezone(&Auckland).offset().fix()),
+                end: None,
+            },
+            planned,
+        );
+    }
+
+    let first = *dates.iter().min().unwrap_or(&today);
+    let last = *dates.iter().max().unwrap_or(&today);
+    let (start_time, end_time) = match times.as_slice() {
+        [] => (midnight, None),
+        [until] if UNTIL_RE.is_match(text) => (midnight, Some(*until)),
+        [from] => (*from, None),
+        [from, to, ..] => (*from, Some(*to)),
+    };
+    let start = local_instant(first, start_time);
+    let end = match end_time {
+        Some(end_time) => local_instant(last, end_time).map(|end| match start {
+            // "7pm to 5am" finishes the next morning.
+            Some(start) if end <= start => end + Duration::days(1),
+            _ => end,
+        }),
+        None => local_instant(last + Duration::days(1), midnight),
+    };
+    let start = start.unwrap_or_else(|| tweet_time.into());
+    (
+        DisruptionWindow {
+            start,
+            end: end.map(|end| end.min(start + Duration::days(MAX_WINDOW_DAYS))),
+        },
+        true,
+    )
+}
+
+/// The train lines or bus routes a disruption tweet names. Empty when it doesn't say.
+pub fn routes_from_text(text: &str) -> Vec<String> {
+    let mut routes: Vec<String> = vec![];
+    let mut add = |route: &str| {
+        if !routes.iter().any(|r| r == route) {
+            routes.push(route.to_string());
+        }
+    };
+    if ALL_LINES_RE.is_match(text) {
+        TRAIN_LINE_NAMES.iter().for_each(|line| add(line));
+    }
+    if let Some(capture) = LINE_PREFIX_RE.captures(text) {
+        capture
+            .name("lines")
+            .unwrap()
+            .as_str()
+            .split('/')
+            .for_each(&mut add);
+    }
+    for capture in LINE_NAME_RE.captures_iter(text) {
+        let name = capture.name("name").unwrap().as_str().to_lowercase();
+        if let Some((_, code)) = LINE_NAMES.iter().find(|(n, _)| *n == name) {
+            add(code);
+        }
+    }
+    for capture in BUS_ROUTES_RE.captures_iter(text) {
+        for route in ROUTE_NUMBER_RE.find_iter(capture.name("routes").unwrap().as_str()) {
+            add(route.as_str());
+        }
+    }
+    routes
+}
+
+/// Trims "Upper Hutt Tuesday 9" down to "Upper Hutt".
+pub fn place_name(raw: &str) -> String {
+    raw.split_whitespace()
+        .take_while(|word| !WEEKDAYS.contains(word) && !["from", "with", "on"].contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test_disruption {
+    use super::*;
+
+    fn tweeted_at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn nz(s: &str) -> Option<DateTime<FixedOffset>> {
+        Some(DateTime::parse_from_rfc3339(s).unwrap())
+    }
+
+    #[test]
+    fn test_window_date_range() {
+        let (window, planned) = window_from_text(
+            "Buses replace some evening train services between Wellington and Johnsonville from Sunday 18 to Thursday 22 October",
+            tweeted_at("2020-10-14T20:35:04Z"),
+        );
+        assert!(planned);
+        assert_eq!(nz("2020-10-18T00:00:00+13:00"), Some(window.start));
+        assert_eq!(nz("2020-10-23T00:00:00+13:00"), window.end);
+    }
+
+    #[test]
+    fn test_window_times() {
+        let (window, planned) = window_from_text(
+            "Bus 236: Whitby: Postgate Drive buses toward Whitby detour for roadworks - 7pm to 5am, Thursday 25 February",
+            tweeted_at("2021-02-21T23:27:04Z"),
+        );
+        assert!(planned);
+        assert_eq!(nz("2021-02-25T19:00:00+13:00"), Some(window.start));
+        assert_eq!(nz("2021-02-26T05:00:00+13:00"), window.end);
+    }
+
+    #[test]
+    fn test_window_unplanned() {
+        let tweet_time = tweeted_at("2021-01-28T03:40:02Z");
+        let (window, planned) = window_from_text(
+            "Bus 25: Highbury: Aro Street and Durham Street, buses cannot pass due to burst watermain.",
+            tweet_time,
+        );
+        assert!(!planned);
+        assert_eq!(tweet_time, window.start);
+        assert_eq!(None, window.end);
+    }
+
+    #[test]
+    fn test_window_weekday_mismatch() {
+        // "Wednesday 1..." was cut off from the 11th, and the 1st isn't a Wednesday
+        let (window, planned) = window_from_text(
+            "Buses replace some train services on the Kapiti Line between Wellington and Porirua from Saturday 7 - Wednesday 1... https://t.co/AxhLqAJApU",
+            tweeted_at("2020-11-04T20:30:18Z"),
+        );
+        assert!(planned);
+        assert_eq!(nz("2020-11-07T00:00:00+13:00"), Some(window.start));
+        assert_eq!(nz("2020-11-08T00:00:00+13:00"), window.end);
+    }
+
+    #[test]
+    fn test_window_updates() {
+        let tweet_time = tweeted_at("2020-11-29T01:27:03Z");
+        let (window, planned) = window_from_text(
+            "KPL Update: 2:30pm: Buses replace train services between Porirua and Waikanae due to a track obstruction",
+            tweet_time,
+        );
+        assert!(!planned);
+        assert_eq!(tweet_time, window.start);
+        assert_eq!(None, window.end);
+
+        let (_, planned) = window_from_text(
+            "Buses replace some evening train services on the Johnsonville Line between Wellington and Johnsonville – Saturd...",
+            tweet_time,
+        );
+        assert!(planned);
+    }
+
+    #[test]
+    fn test_clock_times() {
+        assert_eq!(
+            vec![NaiveTime::from_hms_opt(12, 5, 0).unwrap()],
+            clock_times("KPL: KPL Update12:05pm: Buses replace services between WELL and PORI")
+        );
+        assert!(clock_times("Stop 1205pm").is_empty());
+    }
+
+    #[test]
+    fn test_routes() {
+        assert_eq!(
+            vec!["HVL", "MEL"],
+            routes_from_text("HVL/MEL: Ngauranga Station is currently closed")
+        );
+        assert_eq!(
+            vec!["KPL"],
+            routes_from_text("Buses replace some train services on the Kāpiti Line")
+        );
+        assert_eq!(
+            vec!["110", "130"],
+            routes_from_text("Nevis Street- Road Resurfacing- Bus 110, 130 Detour")
+        );
+        assert_eq!(
+            vec!["3", "18e"],
+            routes_from_text("Constable Street accident - diversion for route 3 and 18e buses")
+        );
+    }
+}
@@ -0,0 +1,466 @@
+use crate::source::TweetSource;
+use crate::tweet_cache::{SourceTag, TweetCache, TweetContent, TweetEntities};
+use chrono::{DateTime, Utc};
+use futures::future::{ready, FutureExt, LocalBoxFuture};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+lazy_static! {
+    static ref ITEM_RE: Regex =
+        Regex::new(r"(?s)<(?:item|entry)(?:\s[^>]*)?>(?P<body>.*?)</(?:item|entry)>").unwrap();
+    static ref CDATA_RE: Regex = Regex::new(r"(?s)^\s*<!\[CDATA\[(?P<text>.*)\]\]>\s*$").unwrap();
+    static ref BREAK_RE: Regex = Regex::new(r"(?i)<br\s*/?>|</p>").unwrap();
+    static ref TAG_RE: Regex = Regex::new(r"<[^>]*>").unwrap();
+    static ref NUMERIC_ENTITY_RE: Regex =
+        Regex::new(r"&#(?P<hex>x)?(?P<code>[0-9a-fA-F]+);").unwrap();
+    static ref WHITESPACE_RE: Regex = Regex::new(r"\s+").unwrap();
+    static ref STATUS_ID_RE: Regex = Regex::new(r"/(?P<id>[0-9]+)/?$").unwrap();
+    static ref HTML_TYPE_RE: Regex = Regex::new(r#"\btype\s*=\s*["']html["']"#).unwrap();
+    static ref ELEMENT_RES: HashMap<&'static str, Regex> = ELEMENTS
+        .iter()
+        .map(|name| {
+            let re = Regex::new(&format!(
+                r"(?s)<{0}(?P<attributes>\s[^>]*)?>(?P<body>.*?)</{0}>",
+                name
+            ));
+            (*name, re.unwrap())
+        })
+        .collect();
+}
+
+/// Every element of an item that's read, in RSS or Atom.
+const ELEMENTS: [&str; 10] = [
+    "title",
+    "guid",
+    "id",
+    "link",
+    "pubDate",
+    "published",
+    "updated",
+    "description",
+    "content",
+    "summary",
+];
+
+/// The text of the first of `names` that's in the item, and whether it's HTML: an RSS
+///  description, or anything Atom marks as `type="html"`. Each has to be one of the `ELEMENTS`.
+fn typed_element(item: &str, names: &[&str]) -> Option<(String, bool)> {
+    names.iter().find_map(|name| {
+        ELEMENT_RES[name].captures(item).map(|capture| {
+            let body = &capture["body"];
+            let text = match CDATA_RE.captures(body) {
+                Some(cdata) => cdata["text"].to_string(),
+                None => unescape_xml(body),
+            };
+            let html = *name == "description"
+                || capture
+                    .name("attributes")
+                    .map(|attributes| HTML_TYPE_RE.is_match(attributes.as_str()))
+                    .unwrap_or(false);
+            (text, html)
+        })
+    })
+}
+
+fn element(item: &str, names: &[&str]) -> Option<String> {
+    typed_element(item, names).map(|(text, _)| text)
+}
+
+/// An element's text as it would be in a tweet. The XML's own escaping has already been undone,
+///  so only HTML is unescaped again.
+fn element_text(item: &str, names: &[&str]) -> Option<String> {
+    typed_element(item, names).map(|(text, html)| {
+        if html {
+            html_to_text(&text)
+        } else {
+            escape_text(&text)
+        }
+    })
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Strips the markup from an HTML post. Tweets come with `&`, `<` and `>` escaped, and the
+///  parser expects that, so those are escaped again afterwards.
+fn html_to_text(html: &str) -> String {
+    let broken = BREAK_RE.replace_all(html, " ");
+    let text = TAG_RE.replace_all(&broken, "");
+    let text = NUMERIC_ENTITY_RE.replace_all(&text, |capture: &regex::Captures| {
+        let radix = if capture.name("hex").is_some() {
+            16
+        } else {
+            10
+        };
+        u32::from_str_radix(&capture["code"], radix)
+            .ok()
+            .and_then(std::char::from_u32)
+            .map(|c| c.to_string())
+            .unwrap_or_default()
+    });
+    escape_text(&unescape_xml(&text.replace("&nbsp;", " ")))
+}
+
+/// Escapes text the way tweets come, on one line.
+fn escape_text(text: &str) -> String {
+    let text = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    WHITESPACE_RE.replace_all(&text, " ").trim().to_string()
+}
+
+// FNV-1a, which unlike `DefaultHasher` gives the same id from one build to the next.
+fn stable_id(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Mastodon statuses have numeric ids at the end of their URL, like tweets do.
+fn status_id(url: &str) -> u64 {
+    STATUS_ID_RE
+        .captures(url)
+        .and_then(|capture| capture["id"].parse().ok())
+        .unwrap_or_else(|| stable_id(url))
+}
+
+/// Reads the items of an RSS feed, or the entries of an Atom one. The title is what's parsed,
+///  with the description kept as the full text.
+pub fn parse_feed(contents: &str) -> Result<Vec<TweetContent>> {
+    ITEM_RE
+        .captures_iter(contents)
+        .map(|capture| {
+            let item = &capture["body"];
+            let text = element_text(item, &["title"]).ok_or("Feed item without a title")?;
+            let key = element(item, &["guid", "id", "link"]).unwrap_or_else(|| text.clone());
+            let created_at = match element(item, &["pubDate"]) {
+                Some(date) => DateTime::parse_from_rfc2822(date.trim())?,
+                None => {
+                    let date = element(item, &["published", "updated"])
+                        .ok_or("Feed item without a date")?;
+                    DateTime::parse_from_rfc3339(date.trim())?
+                }
+            }
+            .with_timezone(&Utc);
+            let full_text = element_text(item, &["description", "content", "summary"])
+                .filter(|description| *description != text);
+            Ok(TweetContent {
+                id: stable_id(&key),
+                created_at,
+                entities: TweetEntities::from_text(&text),
+                text,
+                expanded_text: None,
+                full_text,
+                in_reply_to_status_id: None,
+                source: SourceTag::Feed,
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct NoteTag {
+    #[serde(rename = "type")]
+    kind: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Note {
+    id: String,
+    published: DateTime<Utc>,
+    content: String,
+    #[serde(rename = "inReplyTo", default)]
+    in_reply_to: Option<String>,
+    #[serde(default)]
+    tag: Vec<NoteTag>,
+}
+
+#[derive(Deserialize)]
+struct Activity {
+    #[serde(rename = "type")]
+    kind: String,
+    // A boost only links to what it's boosting, so this isn't always a `Note`.
+    object: serde_json::Value,
+}
+
+/// A saved outbox, or a page of one. The pages after it are either embedded in it, or only
+///  linked to.
+#[derive(Deserialize)]
+struct Outbox {
+    #[serde(rename = "orderedItems", default)]
+    ordered_items: Vec<Activity>,
+    #[serde(default)]
+    first: Option<serde_json::Value>,
+    #[serde(default)]
+    next: Option<serde_json::Value>,
+}
+
+impl Outbox {
+    /// The activities on this page and the embedded ones after it. A page that's only linked to
+    ///  is an error, as importing the rest would quietly leave out everything from there on.
+    fn into_activities(self) -> Result<Vec<Activity>> {
+        let mut activities = self.ordered_items;
+        let mut page = self.first.or(self.next);
+        while let Some(link) = page {
+            if let Some(url) = link.as_str() {
+                return Err(format!(
+                    "The outbox goes on past what was saved, save its pages from {} too",
+                    url
+                )
+                .into());
+            }
+            let outbox: Outbox = serde_json::from_value(link)?;
+            activities.extend(outbox.ordered_items);
+            page = outbox.first.or(outbox.next);
+        }
+        Ok(activities)
+    }
+}
+
+/// Reads the posts from a saved ActivityPub outbox, following the pages embedded in it. Only
+///  posts the account made itself are kept, not boosts.
+pub fn parse_outbox(contents: &str) -> Result<Vec<TweetContent>> {
+    let outbox: Outbox = serde_json::from_str(contents)?;
+    let activities = outbox.into_activities()?;
+    if activities.is_empty() {
+        return Err("No posts in the outbox".into());
+    }
+    let mut posts = vec![];
+    for activity in activities {
+        if activity.kind != "Create" || !activity.object.is_object() {
+            continue;
+        }
+        let note: Note = serde_json::from_value(activity.object)?;
+        let text = html_to_text(&note.content);
+        let names = |kind: &str, prefix: char| -> Vec<String> {
+            note.tag
+                .iter()
+                .filter(|tag| tag.kind == kind)
+                .map(|tag| tag.name.trim_start_matches(prefix).to_string())
+                .collect()
+        };
+        posts.push(TweetContent {
+            id: status_id(&note.id),
+            created_at: note.published,
+            entities: TweetEntities {
+                hashtags: names("Hashtag", '#'),
+                user_mentions: names("Mention", '@'),
+                ..TweetEntities::from_text(&text)
+            },
+            text,
+            expanded_text: None,
+            full_text: None,
+            in_reply_to_status_id: note.in_reply_to.as_deref().map(status_id),
+            source: SourceTag::ActivityPub,
+        });
+    }
+    Ok(posts)
+}
+
+/// A saved RSS or Atom feed.
+pub struct FeedFile {
+    pub path: PathBuf,
+}
+
+impl TweetSource for FeedFile {
+    fn load_into<'a>(&'a self, cache: &'a mut TweetCache) -> LocalBoxFuture<'a, Result<usize>> {
+        let posts = read_to_string(&self.path)
+            .map_err(|e| e.into())
+            .and_then(|contents| parse_feed(&contents));
+        ready(posts.map(|posts| cache.add_all(posts))).boxed_local()
+    }
+}
+
+/// A saved ActivityPub outbox, like the one Mastodon gives for an account.
+pub struct OutboxFile {
+    pub path: PathBuf,
+}
+
+impl TweetSource for OutboxFile {
+    fn load_into<'a>(&'a self, cache: &'a mut TweetCache) -> LocalBoxFuture<'a, Result<usize>> {
+        let posts = read_to_string(&self.path)
+            .map_err(|e| e.into())
+            .and_then(|contents| parse_outbox(&contents));
+        ready(posts.map(|posts| cache.add_all(posts))).boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod test_feed {
+    use super::*;
+    use crate::parser::{parse_tweet, Cancellations};
+
+    #[test]
+    fn test_rss() {
+        let posts = parse_feed(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <rss version="2.0"><channel>
+              <title>Metlink service updates</title>
+              <item>
+                <title>Bus 3: 10:30am Wellington Station to Lyall Bay is cancelled.</title>
+                <description><![CDATA[<p>Bus 3: 10:30am Wellington Station to Lyall Bay is cancelled.</p>]]></description>
+                <pubDate>Sun, 24 Jan 2021 21:00:06 +0000</pubDate>
+                <guid isPermaLink="false">alert-1234</guid>
+              </item>
+            </channel></rss>"#,
+        )
+        .unwrap();
+        assert_eq!(1, posts.len());
+        assert_eq!(SourceTag::Feed, posts[0].source);
+        assert_eq!(None, posts[0].full_text);
+        assert!(matches!(
+            parse_tweet(&posts[0]).unwrap()[..],
+            [Cancellations::BusCancelled { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_atom() {
+        let posts = parse_feed(
+            r#"<feed xmlns="http://www.w3.org/2005/Atom">
+              <entry>
+                <id>tag:metlink.org.nz,2021:alert-1235</id>
+                <title type="html">HVL: The 7:35pm svc from WELL to UPPE is held at WOBU due to a tresspasser on the tracks.</title>
+                <updated>2020-10-09T07:17:02Z</updated>
+                <summary type="html">&lt;p&gt;Updates to follow&amp;#33;&lt;/p&gt;</summary>
+              </entry>
+            </feed>"#,
+        )
+        .unwrap();
+        assert_eq!(1, posts.len());
+        assert_eq!(Some("Updates to follow!".to_string()), posts[0].full_text);
+        assert!(matches!(
+            parse_tweet(&posts[0]).unwrap()[..],
+            [Cancellations::TrainHeld { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_feed_escaping() {
+        let posts = parse_feed(
+            r#"<rss version="2.0"><channel>
+              <item>
+                <title>Bus 3: Stop &amp;lt;A&amp;gt; &amp; B</title>
+                <description>&lt;p&gt;Stop A &amp;amp; B&lt;/p&gt;</description>
+                <pubDate>Sun, 24 Jan 2021 21:00:06 +0000</pubDate>
+              </item>
+            </channel></rss>"#,
+        )
+        .unwrap();
+        // The title is text, so what's left escaped after the XML is part of it
+        assert_eq!("Bus 3: Stop &amp;lt;A&amp;gt; &amp; B", posts[0].text);
+        // The description is HTML, which has its own escaping
+        assert_eq!(Some("Stop A &amp; B".to_string()), posts[0].full_text);
+    }
+
+    #[test]
+    fn test_outbox() {
+        let posts = parse_outbox(
+            r##"{
+              "@context": "https://www.w3.org/ns/activitystreams",
+              "type": "OrderedCollectionPage",
+              "orderedItems": [
+                {
+                  "type": "Create",
+                  "object": {
+                    "id": "https://mastodon.example/users/metlink/statuses/109876543210",
+                    "type": "Note",
+                    "published": "2021-01-24T21:00:06Z",
+                    "inReplyTo": "https://mastodon.example/users/metlink/statuses/109876543200",
+                    "content": "<p>Bus 3: 10:30am Wellington Station to Lyall Bay is cancelled. <a href=\"https://mastodon.example/tags/metlink\" class=\"mention hashtag\">#<span>metlink</span></a></p>",
+                    "tag": [{ "type": "Hashtag", "name": "#metlink" }]
+                  }
+                },
+                {
+                  "type": "Announce",
+                  "object": "https://mastodon.example/users/other/statuses/1"
+                }
+              ]
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(1, posts.len());
+        let post = &posts[0];
+        assert_eq!(109876543210, post.id);
+        assert_eq!(Some(109876543200), post.in_reply_to_status_id);
+        assert_eq!(SourceTag::ActivityPub, post.source);
+        assert_eq!(vec!["metlink".to_string()], post.entities.hashtags);
+        assert_eq!(
+            "Bus 3: 10:30am Wellington Station to Lyall Bay is cancelled. #metlink",
+            post.text
+        );
+        assert!(parse_tweet(post).unwrap().len() == 1);
+    }
+
+    #[test]
+    fn test_outbox_pages() {
+        let outbox = |next: &str| {
+            r#"{
+              "type": "OrderedCollection",
+              "first": {
+                "type": "OrderedCollectionPage",
+                "orderedItems": [{
+                  "type": "Create",
+                  "object": {
+                    "id": "https://mastodon.example/users/metlink/statuses/2",
+                    "published": "2021-01-24T21:05:00Z",
+                    "content": "<p>Bus 3: Updates to follow</p>"
+                  }
+                }],
+                "next": {
+                  "type": "OrderedCollectionPage",
+                  "orderedItems": [{
+                    "type": "Create",
+                    "object": {
+                      "id": "https://mastodon.example/users/metlink/statuses/1",
+                      "published": "2021-01-24T21:00:06Z",
+                      "content": "<p>Bus 3: 10:30am Wellington Station to Lyall Bay is cancelled.</p>"
+                    }
+                  }],
+                  "next": NEXT
+                }
+              }
+            }"#
+            .replace("NEXT", next)
+        };
+        let posts = parse_outbox(&outbox("null")).unwrap();
+        assert_eq!(
+            vec![2, 1],
+            posts.iter().map(|post| post.id).collect::<Vec<_>>()
+        );
+
+        // Only some of the pages were saved
+        let error = parse_outbox(&outbox(
+            r#""https://mastodon.example/users/metlink/outbox?page=3""#,
+        ))
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("https://mastodon.example/users/metlink/outbox?page=3"));
+
+        let error = parse_outbox(
+            r#"{
+              "type": "OrderedCollection",
+              "totalItems": 2,
+              "first": "https://mastodon.example/users/metlink/outbox?page=true"
+            }"#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("https://mastodon.example/users/metlink/outbox?page=true"));
+    }
+}
@@ -0,0 +1,250 @@
+use crate::dedup::Announcement;
+use crate::parser::{Cancellations, Cause, CauseKind};
+use crate::reconcile::{location_key, TripStatus};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use metlink_gtfs_lib::realtime::service_alerts::ServiceAlertCause;
+use serde::{Deserialize, Serialize};
+
+// How long after the last tweet about an incident a new tweet can still be part of it.
+const INCIDENT_WINDOW_MINUTES: i64 = 90;
+
+/// A service caught up in an incident, and how it ended up.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct AffectedService {
+    pub origin: String,
+    pub destination: String,
+    pub time: DateTime<FixedOffset>,
+    /// The last status tweeted for it.
+    pub status: TripStatus,
+    /// The longest delay tweeted for it.
+    pub delay_minutes: Option<u32>,
+}
+
+/// A run of rail tweets about the same problem, like a held train that's later cancelled part
+///  way and then runs late.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Incident {
+    pub line: String,
+    pub cause: Option<Cause>,
+    pub tweet_ids: Vec<u64>,
+    /// When the first tweet went out.
+    pub start: DateTime<Utc>,
+    /// When the last tweet went out.
+    pub end: DateTime<Utc>,
+    pub services: Vec<AffectedService>,
+}
+
+impl Incident {
+    pub fn total_delay_minutes(&self) -> u32 {
+        self.services
+            .iter()
+            .filter_map(|service| service.delay_minutes)
+            .sum()
+    }
+
+    fn service_index(&self, event: &Cancellations) -> Option<usize> {
+        let origin = location_key(event.origin());
+        let destination = location_key(event.destination());
+        self.services.iter().position(|service| {
+            service.time == *event.time()
+                && service.origin == origin
+                && service.destination == destination
+        })
+    }
+}
+
+fn is_rail(event: &Cancellations) -> bool {
+    matches!(
+        event,
+        Cancellations::TrainCancelled { .. }
+            | Cancellations::TrainPartCancelled { .. }
+            | Cancellations::TrainDelayed { .. }
+            | Cancellations::TrainHeld { .. }
+            | Cancellations::TrainReplaced { .. }
+    )
+}
+
+// The catch-all kinds say nothing about whether two causes are the same, so those go by wording.
+fn same_cause(a: &Cause, b: &Cause) -> bool {
+    match a.kind {
+        CauseKind::ServiceAlert(ServiceAlertCause::OtherCause)
+        | CauseKind::ServiceAlert(ServiceAlertCause::UnknownCause) => {
+            a.raw.to_lowercase() == b.raw.to_lowercase()
+        }
+        kind => kind == b.kind,
+    }
+}
+
+/// Groups rail announcements into incidents. A tweet joins an earlier incident on the same line,
+///  if that was last tweeted about recently and the tweet is about one of its services or gives
+///  the same cause. Tweets that don't give a cause join the latest incident on the line.
+pub fn build_incidents(announcements: &[Announcement]) -> Vec<Incident> {
+    let mut ordered: Vec<&Announcement> =
+        announcements.iter().filter(|a| is_rail(&a.event)).collect();
+    ordered.sort_by_key(|a| *a.event.tweet_time());
+
+    let mut incidents: Vec<Incident> = vec![];
+    for announcement in ordered {
+        let event = &announcement.event;
+        let tweet_time = *event.tweet_time();
+        let position = incidents.iter().rposition(|incident| {
+            incident.line == event.route()
+                && tweet_time - incident.end <= Duration::minutes(INCIDENT_WINDOW_MINUTES)
+                && match (&incident.cause, event.cause()) {
+                    (_, None) => true,
+                    (Some(cause), Some(other)) if same_cause(cause, other) => true,
+                    _ => incident.service_index(event).is_some(),
+                }
+        });
+        let incident = match position {
+            Some(idx) => &mut incidents[idx],
+            None => {
+                incidents.push(Incident {
+                    line: event.route().to_string(),
+                    cause: None,
+                    tweet_ids: vec![],
+                    start: tweet_time,
+                    end: tweet_time,
+                    services: vec![],
+                });
+                incidents.last_mut().unwrap()
+            }
+        };
+
+        incident.end = tweet_time;
+        if incident.cause.is_none() {
+            incident.cause = event.cause().cloned();
+        }
+        for id in &announcement.tweet_ids {
+            if !incident.tweet_ids.contains(id) {
+                incident.tweet_ids.push(*id);
+            }
+        }
+        let status = TripStatus::from(event);
+        let delay_minutes = event.delay().and_then(|delay| delay.minutes());
+        match incident.service_index(event) {
+            Some(idx) => {
+                let service = &mut incident.services[idx];
+                service.status = status;
+                service.delay_minutes = service.delay_minutes.max(delay_minutes);
+            }
+            None => incident.services.push(AffectedService {
+                origin: location_key(event.origin()),
+                destination: location_key(event.destination()),
+                time: *event.time(),
+                status,
+                delay_minutes,
+            }),
+        }
+    }
+    incidents
+}
+
+#[cfg(test)]
+mod test_incident {
+    use super::*;
+    use crate::parser::Delay;
+    use crate::time::convert_time_to_instant;
+
+    fn tweeted_at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn announced(id: u64, event: Cancellations) -> Announcement {
+        Announcement {
+            tweet_ids: vec![id],
+            event,
+            links: vec![],
+        }
+    }
+
+    fn held(line: &str, tweet_time: DateTime<Utc>, cause: &str) -> Cancellations {
+        Cancellations::TrainHeld {
+            line: line.to_string(),
+            origin: "UPPE".into(),
+            destination: "WELL".into(),
+            held_at: "EPUN".into(),
+            cause: Some(cause.into()),
+            raw_time: "7:30 pm".to_string(),
+            tweet_time,
+            time: convert_time_to_instant(tweet_time, 19, 30).unwrap(),
+            link: None,
+        }
+    }
+
+    #[test]
+    fn test_held_then_late() {
+        let held_at = tweeted_at("2020-10-09T06:17:02Z");
+        let part_cancelled = Cancellations::TrainPartCancelled {
+            line: "HVL".to_string(),
+            origin: "UPPE".into(),
+            destination: "WELL".into(),
+            cancelled_from: "UPPE".into(),
+            cancelled_to: "WATE".into(),
+            cause: None,
+            raw_time: "7:30 pm".to_string(),
+            tweet_time: tweeted_at("2020-10-09T06:40:02Z"),
+            time: convert_time_to_instant(held_at, 19, 30).unwrap(),
+            link: None,
+        };
+        let delayed = Cancellations::TrainDelayed {
+            line: "HVL".to_string(),
+            origin: "UPPE".into(),
+            destination: "WELL".into(),
+            delayed_from: "WATE".into(),
+            delay: Delay::Minutes(38),
+            cause: Some("tresspasser on the tracks".into()),
+            raw_time: "7:30 pm".to_string(),
+            tweet_time: tweeted_at("2020-10-09T06:52:02Z"),
+            time: convert_time_to_instant(held_at, 19, 30).unwrap(),
+            link: None,
+        };
+        let incidents = build_incidents(&[
+            announced(1, held("HVL", held_at, "tresspasser on the tracks")),
+            announced(2, part_cancelled),
+            announced(3, delayed),
+            // A different line
+            announced(4, held("KPL", held_at, "tresspasser on the tracks")),
+            // Too long after the last tweet
+            announced(
+                5,
+                held(
+                    "HVL",
+                    tweeted_at("2020-10-09T09:00:02Z"),
+                    "tresspasser on the tracks",
+                ),
+            ),
+        ]);
+
+        assert_eq!(3, incidents.len());
+        let incident = &incidents[0];
+        assert_eq!(vec![1, 2, 3], incident.tweet_ids);
+        assert_eq!(held_at, incident.start);
+        assert_eq!(tweeted_at("2020-10-09T06:52:02Z"), incident.end);
+        assert_eq!(1, incident.services.len());
+        assert_eq!(TripStatus::Delayed, incident.services[0].status);
+        assert_eq!(38, incident.total_delay_minutes());
+        assert_eq!(vec![4], incidents[1].tweet_ids);
+        assert_eq!(vec![5], incidents[2].tweet_ids);
+    }
+
+    #[test]
+    fn test_different_causes() {
+        let held_at = tweeted_at("2020-10-09T06:17:02Z");
+        let signal_fault = Cancellations::TrainCancelled {
+            line: "HVL".to_string(),
+            origin: "WELL".into(),
+            destination: "UPPE".into(),
+            cause: Some("signal fault".into()),
+            raw_time: "8:00 pm".to_string(),
+            tweet_time: tweeted_at("2020-10-09T06:30:02Z"),
+            time: convert_time_to_instant(held_at, 20, 0).unwrap(),
+            link: None,
+        };
+        let incidents = build_incidents(&[
+            announced(1, held("HVL", held_at, "tresspasser on the tracks")),
+            announced(2, signal_fault),
+        ]);
+        assert_eq!(2, incidents.len());
+    }
+}
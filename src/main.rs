@@ -1,131 +1,247 @@
-use crate::summary::summarize;
+use crate::dedup::{dedup, Announcement};
+use crate::incident::{build_incidents, Incident};
+use crate::matcher::{TripMatch, TripMatcher};
+use crate::reconcile::{reconcile, TripStatus, TripTimeline};
+use crate::report::{local_date, report, ReportRange};
+use crate::resolver::{FerryRouteResolver, StopResolver};
+use crate::source::{TweetSource, TwitterApi};
+use crate::tracks::{TripTrack, TripTracks};
+use crate::triage::Triage;
+use crate::trip_updates::TripUpdateDetector;
 use crate::tweet_cache::TweetCache;
-use chrono::{DateTime, Utc};
-use chrono_tz::Pacific::Auckland;
-use egg_mode::tweet::user_timeline;
-use egg_mode::tweet::{Timeline, Tweet};
-use parser::Cancellations;
-use std::fs::File;
-use std::io::BufReader;
-
-use egg_mode::Token;
-use serde::Deserialize;
-
+use chrono::{Duration, Utc};
+use metlink_gtfs_lib::client::reqwest_client;
+use metlink_gtfs_lib::db::Database;
+use metlink_gtfs_lib::gtfs::load_gtfs;
+use metlink_gtfs_lib::realtime::trip_updates::{TripUpdateRealtimeApi, TripUpdateRoot};
+use metlink_gtfs_lib::realtime::utils::load_all_from_files;
+use metlink_gtfs_lib::realtime::vehicle_positions::{
+    VehiclePositionRoot, VehiclePositionsRealtimeApi,
+};
+use parser::{parse_thread, Cancellations, ParseDiagnostic};
+use std::fs::{create_dir_all, File};
+use std::path::{Path, PathBuf};
+
+mod dedup;
+mod disruption;
+mod feed;
+mod incident;
+mod matcher;
 mod parser;
+mod reconcile;
+mod replay;
+mod report;
+mod resolver;
+mod source;
 mod summary;
+#[cfg(test)]
+mod test_helpers;
 mod time;
+mod tracks;
+mod triage;
+mod trip_updates;
 mod tweet_cache;
 
-#[derive(Deserialize)]
-struct TwitterTokenInfo {
-    api_key: String,
-    api_secret: String,
-    access_token: String,
-    access_secret: String,
-}
-
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
-fn load_twitter_creds() -> Result<Token> {
-    let file = File::open("./twitter-creds.json")?;
-    let reader = BufReader::new(file);
-    // Read the JSON contents of the file as an instance of `User`.
-    let creds: TwitterTokenInfo = serde_json::from_reader(reader)?;
-
-    let con_token = egg_mode::KeyPair::new(creds.api_key, creds.api_secret);
-    let access_token = egg_mode::KeyPair::new(creds.access_token, creds.access_secret);
-    Ok(egg_mode::Token::Access {
-        consumer: con_token,
-        access: access_token,
-    })
-}
+const DIAGNOSTICS_FILE: &str = "twitter-parse-diagnostics.json";
+// Distinct from the 1 an `Err` from main exits with, so scripts can tell the report was still
+//  written.
+const PARSE_FAILURE_EXIT_CODE: i32 = 2;
+// How far before the range to look for the start of a thread that runs into it.
+const THREAD_LOOKBACK_DAYS: i64 = 1;
 
-async fn load_tweets_from_timeline(
-    cache: &mut TweetCache,
-    timeline_: Timeline,
-    back_to: Option<u64>,
-) -> Result<()> {
-    let mut timeline = timeline_;
-    loop {
-        println!("Fetching tweets {:?} to {:?}", timeline.min_id, back_to);
-        let (inner_timeline, feed_) = timeline.older(back_to).await?;
-        let feed: &Vec<Tweet> = &*feed_;
-        if feed.is_empty() {
-            return Ok(());
-        }
-        cache.add_all_tweets(feed);
-        cache.write()?;
-        timeline = inner_timeline;
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("triage") => return triage::run(&args[1..]),
+        Some("import") => return source::run(&args[1..]).await,
+        Some("replay") => return replay::run(&args[1..]).await,
+        _ => {}
     }
-}
 
-async fn load_tweets(token: &Token, cache: &mut TweetCache) -> Result<()> {
-    let timeline = user_timeline("metlinkwgtn", false, false, token).with_page_size(200);
+    let range = ReportRange::from_args(&args, Utc::now())?;
+    println!(
+        "Reporting {} to {} by {:?}",
+        range.start, range.end, range.bucket
+    );
 
-    load_tweets_from_timeline(
-        cache,
-        timeline,
-        cache.tweets.iter().map(|tweet| tweet.id).max(),
-    )
-    .await?;
-
-    cache.write()?;
-    Ok(())
-}
-
-#[tokio::main]
-pub async fn main() -> Result<()> {
-    let creds = load_twitter_creds()?;
     let mut cache = TweetCache::read()?;
 
     println!("Has recent data: {}", cache.has_recent_data());
 
     if !cache.has_recent_data() {
-        load_tweets(&creds, &mut cache).await?;
+        // Without credentials, this runs on whatever has been cached or imported.
+        match TwitterApi::from_creds_file() {
+            Ok(api) => {
+                api.load_into(&mut cache).await?;
+                cache.write()?;
+            }
+            Err(e) => println!("Not fetching tweets, no Twitter credentials: {}", e),
+        }
     }
 
-    let mut cancellations: [Vec<Cancellations>; 4] = [vec![], vec![], vec![], vec![]];
-    let mut broken = Vec::with_capacity(10);
-
-    let time = &Utc::now()
-        .with_timezone(&Auckland)
-        .format("%YW%WD1 00:00:00 %z")
-        .to_string();
-    println!("{} -> {}", Utc::now(), time);
-    let previous_monday: DateTime<Utc> = DateTime::parse_from_str(time, "%YW%WD%u %H:%M:%S %z")
-        .unwrap()
-        .into();
-
-    for tweet in cache.tweets {
-        let diff = (previous_monday - tweet.created_at).num_weeks();
-        println!(
-            "{} - {} = {} = {}",
-            previous_monday,
-            tweet.created_at,
-            previous_monday - tweet.created_at,
-            diff
-        );
-        if 0 <= diff && diff < 4 {
-            match parser::parse_tweet(&tweet) {
-                Ok(parsed_cancellation) => cancellations[diff as usize].extend(parsed_cancellation),
-                Err(err) => broken.push((tweet, err)),
+    let triage = Triage::read()?;
+    let mut parsed: Vec<(u64, Cancellations)> = vec![];
+    let mut diagnostics: Vec<ParseDiagnostic> = vec![];
+
+    let (start, end) = range.utc_bounds();
+    for thread in cache.threads_between(start - Duration::days(THREAD_LOOKBACK_DAYS), end) {
+        let results = parse_thread(&thread, |tweet| triage.parse_tweet(tweet));
+        for (tweet, result) in thread.into_iter().zip(results) {
+            if !range.contains(local_date(&tweet.created_at)) {
+                continue;
+            }
+            match result {
+                Ok(parsed_cancellation) => {
+                    parsed.extend(parsed_cancellation.into_iter().map(|c| (tweet.id, c)))
+                }
+                Err(error) => diagnostics.push(ParseDiagnostic {
+                    tweet_id: tweet.id,
+                    created_at: tweet.created_at,
+                    text: tweet.text.clone(),
+                    error,
+                }),
             }
         }
     }
 
-    serde_json::to_writer_pretty(File::create("twitter-cancellations.json")?, &cancellations)?;
+    // Written before anything that needs the GTFS, so a failed download doesn't lose them
+    serde_json::to_writer_pretty(File::create(DIAGNOSTICS_FILE)?, &diagnostics)?;
+
+    let cache_dir = PathBuf::from("./.cache");
+    create_dir_all(&cache_dir)?;
+    let db = match load_gtfs(&cache_dir, &reqwest_client()?).await {
+        Ok(db) => Some(db),
+        Err(e) => {
+            eprintln!(
+                "Unable to load the GTFS, skipping stop resolution, trip matching and the realtime feeds: {:?}",
+                e
+            );
+            None
+        }
+    };
+    if let Some(db) = &db {
+        // Resolved first, so that the same stop written two ways is deduped as one
+        StopResolver::new(&db.stops).resolve_all(parsed.iter_mut().map(|(_, event)| event));
+        FerryRouteResolver::new(&db.routes).resolve_all(parsed.iter_mut().map(|(_, event)| event));
+    }
+    let announcements: Vec<Announcement> = dedup(&parsed);
+
+    serde_json::to_writer_pretty(File::create("twitter-cancellations.json")?, &announcements)?;
+    let cancellations: Vec<Cancellations> = announcements.iter().map(|a| a.event.clone()).collect();
+
+    let timelines: Vec<TripTimeline> = reconcile(&cancellations);
+    serde_json::to_writer_pretty(File::create("twitter-trip-timelines.json")?, &timelines)?;
+    println!(
+        "{} trips disrupted, {} of them reinstated",
+        timelines.len(),
+        timelines
+            .iter()
+            .filter(|t| t.effective_status() == Some(TripStatus::Reinstated))
+            .count()
+    );
+
+    let incidents: Vec<Incident> = build_incidents(&announcements);
+    serde_json::to_writer_pretty(File::create("twitter-incidents.json")?, &incidents)?;
+    println!(
+        "{} rail incidents, {} minutes of delays between them",
+        incidents.len(),
+        incidents
+            .iter()
+            .map(Incident::total_delay_minutes)
+            .sum::<u32>()
+    );
+
+    for bucket in report(&cancellations, &range) {
+        println!("{} to {}: {:#?}", bucket.start, bucket.end, bucket.summary)
+    }
 
-    for cans in &cancellations {
-        println!("{:#?}", summarize(cans))
+    if let Some(db) = &db {
+        compare_with_gtfs(db, &cancellations, &cache_dir, &range).await?;
     }
 
-    if !broken.is_empty() {
-        panic!(
-            "Unable to parse: {:#?}\n Total of {} tweets failed to parse",
-            broken,
-            broken.len()
-        )
+    if !diagnostics.is_empty() {
+        eprintln!(
+            "{} tweets failed to parse, see {}. Run `triage` to record what to do with them.",
+            diagnostics.len(),
+            DIAGNOSTICS_FILE
+        );
+        std::process::exit(PARSE_FAILURE_EXIT_CODE);
     }
 
     Ok(())
 }
+
+/// Matches the tweeted events to the timetable, and checks them against the realtime feeds.
+async fn compare_with_gtfs(
+    db: &Database,
+    cancellations: &[Cancellations],
+    cache_dir: &Path,
+    range: &ReportRange,
+) -> Result<()> {
+    let matcher = TripMatcher::new(db);
+    let matches: Vec<(&Cancellations, Option<TripMatch>)> = cancellations
+        .iter()
+        .map(|c| (c, matcher.match_trip(c)))
+        .collect();
+    serde_json::to_writer_pretty(File::create("twitter-trip-matches.json")?, &matches)?;
+    println!(
+        "Matched {} of {} to a trip, {} ambiguously",
+        matches.iter().filter(|(_, m)| m.is_some()).count(),
+        matches.len(),
+        matches
+            .iter()
+            .filter(|(_, m)| m.as_ref().map(TripMatch::is_ambiguous).unwrap_or(false))
+            .count()
+    );
+
+    let vehicle_position_api = VehiclePositionsRealtimeApi::new(cache_dir, reqwest_client()?);
+    let snapshots: Vec<VehiclePositionRoot> = load_all_from_files(&vehicle_position_api).await?;
+    let tracks = TripTracks::from_snapshots(&snapshots);
+    // Only unambiguous matches, as one of the other candidates could be the trip that ran.
+    let cancelled_trips: Vec<(&Cancellations, &String)> = matches
+        .iter()
+        .filter(|(c, _)| TripStatus::from(*c) == TripStatus::Cancelled)
+        .filter_map(|(c, m)| match m.as_ref().map(|m| &m.trip_ids[..]) {
+            Some([trip_id]) => Some((*c, trip_id)),
+            _ => None,
+        })
+        .collect();
+    let running: Vec<(&Cancellations, &TripTrack)> = cancelled_trips
+        .iter()
+        .filter_map(|(c, trip_id)| {
+            let track = tracks.get(trip_id, c.time().naive_local().date())?;
+            Some((*c, track)).filter(|_| track.moved())
+        })
+        .collect();
+    serde_json::to_writer_pretty(
+        File::create("twitter-cancelled-but-running.json")?,
+        &running,
+    )?;
+    println!(
+        "{} of {} cancelled trips had a vehicle moving on them, from {} trips in {} vehicle position snapshots",
+        running.len(),
+        cancelled_trips.len(),
+        tracks.len(),
+        snapshots.len()
+    );
+
+    // The realtime feed is downloaded every few minutes, so the same trip turns up in download
+    //  after download; only its latest state is kept.
+    let trip_update_api = TripUpdateRealtimeApi::new(cache_dir, reqwest_client()?);
+    let feeds: Vec<TripUpdateRoot> = load_all_from_files(&trip_update_api).await?;
+    let realtime: Vec<Cancellations> = TripUpdateDetector::new(db)
+        .detect_latest(&feeds)
+        .into_iter()
+        .filter(|event| range.contains(local_date(event.time())))
+        .collect();
+    serde_json::to_writer_pretty(File::create("realtime-cancellations.json")?, &realtime)?;
+    println!(
+        "{} events from {} realtime trip update downloads",
+        realtime.len(),
+        feeds.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,275 @@
+use crate::parser::{Cancellations, Location};
+use crate::resolver::normalise_name;
+use chrono::{DateTime, Datelike, FixedOffset, Timelike};
+use metlink_gtfs_lib::db::stops::Stop;
+use metlink_gtfs_lib::db::trips::Trip;
+use metlink_gtfs_lib::db::Database;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Bus tweets name the suburb the trip starts in, not the exact stop.
+const ORIGIN_RADIUS_METRES: f64 = 1500.0;
+const EARTH_RADIUS_METRES: f64 = 6_371_000.0;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum MatchConfidence {
+    /// Only the route and departure time line up.
+    Low,
+    /// The route, departure time and one of the origin or destination line up.
+    Medium,
+    /// The route, departure time, origin and destination all line up.
+    High,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct TripMatch {
+    pub trip_ids: Vec<String>,
+    pub confidence: MatchConfidence,
+}
+
+impl TripMatch {
+    pub fn is_ambiguous(&self) -> bool {
+        self.trip_ids.len() > 1
+    }
+}
+
+pub fn distance_metres(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat_a, lon_a) = (a.0.to_radians(), a.1.to_radians());
+    let (lat_b, lon_b) = (b.0.to_radians(), b.1.to_radians());
+    let h = ((lat_b - lat_a) / 2.0).sin().powi(2)
+        + lat_a.cos() * lat_b.cos() * ((lon_b - lon_a) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METRES * h.sqrt().asin()
+}
+
+fn service_date(time: &DateTime<FixedOffset>) -> ::time::Date {
+    let local = time.naive_local();
+    ::time::Date::try_from_ymd(local.year(), local.month() as u8, local.day() as u8).unwrap()
+}
+
+pub struct TripMatcher<'a> {
+    db: &'a Database,
+    trips_by_route: HashMap<&'a str, Vec<&'a Trip>>,
+}
+
+impl<'a> TripMatcher<'a> {
+    pub fn new(db: &'a Database) -> Self {
+        let route_names: HashMap<&String, &str> = db
+            .routes
+            .iter()
+            .map(|route| (&route.id, route.short_name.as_str()))
+            .collect();
+        let mut trips_by_route: HashMap<&str, Vec<&Trip>> = HashMap::new();
+        for trip in db.trips.iter() {
+            if let Some(name) = route_names.get(&trip.route_id) {
+                trips_by_route.entry(name).or_default().push(trip);
+            }
+        }
+        Self { db, trips_by_route }
+    }
+
+    fn stop_matches(&self, stop_id: &String, location: &Location) -> bool {
+        let wanted = match &location.stop {
+            Some(wanted) => wanted,
+            None => return false,
+        };
+        let stop: &Stop = match self.db.stops.get_stop(stop_id) {
+            Some(stop) => stop,
+            None => return false,
+        };
+        stop.id == wanted.id
+            || (!stop.parent_station.is_empty() && stop.parent_station == wanted.id)
+            || distance_metres((stop.lat, stop.lon), (wanted.lat, wanted.lon))
+                < ORIGIN_RADIUS_METRES
+    }
+
+    fn destination_matches(&self, trip: &Trip, destination: &Location) -> bool {
+        let wanted = normalise_name(&destination.raw);
+        let headsign = normalise_name(&trip.headsign);
+        (!wanted.is_empty() && headsign.starts_with(&wanted))
+            || trip
+                .stops
+                .last()
+                .map(|stop| self.stop_matches(&stop.stop_id, destination))
+                .unwrap_or(false)
+    }
+
+    fn confidence(
+        &self,
+        trip: &Trip,
+        origin: &Location,
+        destination: &Location,
+    ) -> MatchConfidence {
+        let origin_matches = trip
+            .stops
+            .first()
+            .map(|stop| self.stop_matches(&stop.stop_id, origin))
+            .unwrap_or(false);
+        match (origin_matches, self.destination_matches(trip, destination)) {
+            (true, true) => MatchConfidence::High,
+            (true, false) | (false, true) => MatchConfidence::Medium,
+            (false, false) => MatchConfidence::Low,
+        }
+    }
+
+    /// Finds the timetabled trips on the cancellation's route that run that day and leave their
+    ///  first stop at the scheduled time.
+    pub fn match_trip(&self, cancellation: &Cancellations) -> Option<TripMatch> {
+        if cancellation.window().is_some() {
+            return None;
+        }
+        let time = cancellation.time();
+        let date = service_date(time);
+        let minutes = time.hour() * 60 + time.minute();
+
+        // Services after midnight are timetabled as 24:xx on the previous day.
+        let days = [(date, minutes), (date.previous_day(), minutes + 24 * 60)];
+
+        let mut candidates: Vec<(MatchConfidence, &Trip)> = self
+            .trips_by_route
+            .get(cancellation.route())?
+            .iter()
+            .filter(|trip| {
+                let departs = match trip.stops.first() {
+                    Some(stop) => stop.departure_time.minutes_since_midnight(),
+                    None => return false,
+                };
+                let service = self.db.services.get_service(&trip.service_id);
+                days.iter().any(|(date, minutes)| {
+                    departs == *minutes && service.map(|s| s.runs_on(*date)).unwrap_or(false)
+                })
+            })
+            .map(|trip| {
+                let confidence =
+                    self.confidence(trip, cancellation.origin(), cancellation.destination());
+                (confidence, *trip)
+            })
+            .collect();
+
+        let confidence = candidates.iter().map(|(c, _)| *c).max()?;
+        candidates.retain(|(c, _)| *c == confidence);
+        Some(TripMatch {
+            trip_ids: candidates
+                .into_iter()
+                .map(|(_, trip)| trip.id.clone())
+                .collect(),
+            confidence,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_matcher {
+    use super::*;
+    use crate::parser::ResolvedStop;
+    use crate::test_helpers::{
+        gtfs_calendar, gtfs_data, gtfs_route, gtfs_stop, gtfs_stop_time, gtfs_trip,
+    };
+    use crate::time::convert_time_to_instant;
+    use chrono::Utc;
+    use metlink_gtfs_lib::gtfs::data::route::RouteType;
+    use metlink_gtfs_lib::gtfs::data::{stop, GtfsData};
+
+    fn database() -> Database {
+        let located = |id: &str, name: &str, lat: f64, lon: f64| stop::Stop {
+            stop_lat: lat,
+            stop_lon: lon,
+            ..gtfs_stop(id, name)
+        };
+        let data = GtfsData {
+            calendar: vec![gtfs_calendar("WEEKDAY", false)],
+            route: vec![gtfs_route("10", "1", "", RouteType::Bus)],
+            stop: vec![
+                located("JW", "Johnsonville West - Stop A", -41.22, 174.79),
+                located("IB", "Island Bay - The Parade", -41.34, 174.77),
+                located("CP", "Churton Park - Stop A", -41.20, 174.80),
+            ],
+            stop_time: vec![
+                gtfs_stop_time("1-0803-JW", "JW", 1, "08:03:00"),
+                gtfs_stop_time("1-0803-JW", "IB", 2, "08:50:00"),
+                gtfs_stop_time("1-0803-CP", "CP", 1, "08:03:00"),
+                gtfs_stop_time("1-0803-CP", "IB", 2, "08:55:00"),
+                gtfs_stop_time("1-0813-CP", "CP", 1, "08:13:00"),
+                gtfs_stop_time("1-0813-CP", "IB", 2, "09:05:00"),
+            ],
+            trip: vec![
+                gtfs_trip("1-0803-JW", "10", "WEEKDAY", "Island Bay"),
+                gtfs_trip("1-0803-CP", "10", "WEEKDAY", "Island Bay"),
+                gtfs_trip("1-0813-CP", "10", "WEEKDAY", "Island Bay"),
+            ],
+            ..gtfs_data()
+        };
+        (&data).into()
+    }
+
+    fn cancelled(origin: Location, destination: Location, hour: u32, minute: u32) -> Cancellations {
+        // Monday 2021-02-15, NZ time
+        let tweet_time = DateTime::parse_from_rfc3339("2021-02-14T17:13:07Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        Cancellations::BusCancelled {
+            route: "1".to_string(),
+            origin,
+            destination,
+            cause: None,
+            raw_time: "".to_string(),
+            tweet_time,
+            time: convert_time_to_instant(tweet_time, hour, minute).unwrap(),
+            link: None,
+        }
+    }
+
+    fn resolved(raw: &str, lat: f64, lon: f64) -> Location {
+        Location {
+            raw: raw.to_string(),
+            stop: Some(ResolvedStop {
+                id: "".to_string(),
+                lat,
+                lon,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_match_trip() {
+        let db = database();
+        let matcher = TripMatcher::new(&db);
+        assert_eq!(
+            Some(TripMatch {
+                trip_ids: vec!["1-0803-JW".to_string()],
+                confidence: MatchConfidence::High,
+            }),
+            matcher.match_trip(&cancelled(
+                resolved("Johnsonville West", -41.221, 174.791),
+                "Island Bay".into(),
+                8,
+                3
+            ))
+        );
+    }
+
+    #[test]
+    fn test_match_trip_ambiguous() {
+        let db = database();
+        let matcher = TripMatcher::new(&db);
+        let trip_match = matcher
+            .match_trip(&cancelled("Somewhere".into(), "Island Bay".into(), 8, 3))
+            .unwrap();
+        assert!(trip_match.is_ambiguous());
+        assert_eq!(MatchConfidence::Medium, trip_match.confidence);
+    }
+
+    #[test]
+    fn test_match_trip_not_running() {
+        let db = database();
+        let matcher = TripMatcher::new(&db);
+        assert_eq!(
+            None,
+            matcher.match_trip(&cancelled(
+                "Churton Park".into(),
+                "Island Bay".into(),
+                8,
+                30
+            ))
+        );
+    }
+}
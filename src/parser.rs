@@ -1,16 +1,168 @@
+use crate::disruption::{
+    place_name, routes_from_text, window_from_text, DisruptionKind, DisruptionWindow,
+};
 use crate::{time::convert_time_to_instant, tweet_cache::TweetContent};
-use chrono::{DateTime, Duration, FixedOffset, Utc};
+use chrono::{DateTime, Duration, FixedOffset, Timelike, Utc};
 use lazy_static::lazy_static;
+use metlink_gtfs_lib::realtime::service_alerts::ServiceAlertCause;
 use regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, ops::Sub};
+use std::{fmt, ops::Sub};
 
 // Turns out am/pm is called the " delay_minutes: ()period". Thanks StackExchange
 // https://english.stackexchange.com/questions/35315/what-is-the-proper-name-for-am-and-pm#35317
 const TIME_RE: &str = r"(?P<hour>[0-9]{1,2})(?:[.:](?P<minute>[0-9]{2}))?(?P<period>am|pm|m|)";
 const BUS_NUM_RE: &str = r"Bu[sa] ?(?P<bus_num>[0-9ex]+)";
 const BUS_DEST_RE: &str = r"(?:from +)?(?P<origin>.*?) +(?:to|tp|-) +(?P<destination>.*?)";
-const TRAIN_LINE_NAMES: [&str; 5] = ["WRL", "KPL", "HVL", "JVL", "MEL"];
+// A time without the named captures, for lists like "10.00 & 10.40am"
+const FERRY_TIME_RE: &str = r"[0-9]{1,2}(?:[.:][0-9]{2})?(?:am|pm)?";
+pub const TRAIN_LINE_NAMES: [&str; 5] = ["WRL", "KPL", "HVL", "JVL", "MEL"];
+const STATION_CODE_RE: &str = r"[A-Z]{3,4}";
+const CAUSE_RE: &str = r"(?:due to|Due to) (?:an? |the )?(?P<cause>.+?) *(?:\.|,| - |(?i:updates? to follow)|Alt s|https://|$)";
+
+// Checked in order, so the more specific phrases need to come first; "track obstruction" is an
+//  obstruction, not a track fault, and "traffic accident" is an accident. Each is a regex matched
+//  against whole words, so "tree" doesn't match "street".
+const CAUSE_KEYWORDS: [(&str, CauseKind); 42] = [
+    ("previous", CauseKind::LateArrivalOfPreviousService),
+    ("late arrival", CauseKind::LateArrivalOfPreviousService),
+    ("earlier delays", CauseKind::LateArrivalOfPreviousService),
+    (r"trespass\w*", CauseKind::Trespasser),
+    (r"tresspass\w*", CauseKind::Trespasser),
+    (r"staff\w*", CauseKind::StaffShortage),
+    ("drivers?", CauseKind::StaffShortage),
+    ("bus replacements?", CauseKind::BusReplacement),
+    ("freight", CauseKind::FreightTrain),
+    ("non-metlink", CauseKind::FreightTrain),
+    (r"obstruct\w*", CauseKind::Obstruction),
+    ("trees?", CauseKind::Obstruction),
+    ("slips?", CauseKind::Obstruction),
+    (r"signal\w*", CauseKind::SignalFault),
+    (
+        "speed restrictions?",
+        CauseKind::ServiceAlert(ServiceAlertCause::Maintenance),
+    ),
+    (
+        "worksites?",
+        CauseKind::ServiceAlert(ServiceAlertCause::Maintenance),
+    ),
+    (
+        "work sites?",
+        CauseKind::ServiceAlert(ServiceAlertCause::Maintenance),
+    ),
+    (
+        "work-sites?",
+        CauseKind::ServiceAlert(ServiceAlertCause::Maintenance),
+    ),
+    (
+        "maintenance",
+        CauseKind::ServiceAlert(ServiceAlertCause::Maintenance),
+    ),
+    ("points", CauseKind::TrackFault),
+    ("tracks?", CauseKind::TrackFault),
+    (
+        "police",
+        CauseKind::ServiceAlert(ServiceAlertCause::PoliceActivity),
+    ),
+    (
+        "emergency services",
+        CauseKind::ServiceAlert(ServiceAlertCause::PoliceActivity),
+    ),
+    (
+        "medical",
+        CauseKind::ServiceAlert(ServiceAlertCause::MedicalEmergency),
+    ),
+    (
+        "accidents?",
+        CauseKind::ServiceAlert(ServiceAlertCause::Accident),
+    ),
+    (
+        "crash(?:es)?",
+        CauseKind::ServiceAlert(ServiceAlertCause::Accident),
+    ),
+    (
+        "weather",
+        CauseKind::ServiceAlert(ServiceAlertCause::Weather),
+    ),
+    (
+        "wind(?:s|y)?",
+        CauseKind::ServiceAlert(ServiceAlertCause::Weather),
+    ),
+    // "Due to adverse conditions" in the ferry tweets
+    (
+        "conditions",
+        CauseKind::ServiceAlert(ServiceAlertCause::Weather),
+    ),
+    (
+        "strikes?",
+        CauseKind::ServiceAlert(ServiceAlertCause::Strike),
+    ),
+    (
+        "industrial action",
+        CauseKind::ServiceAlert(ServiceAlertCause::Strike),
+    ),
+    (
+        r"protest\w*",
+        CauseKind::ServiceAlert(ServiceAlertCause::Demonstration),
+    ),
+    (
+        "holidays?",
+        CauseKind::ServiceAlert(ServiceAlertCause::Holiday),
+    ),
+    (
+        "roadworks",
+        CauseKind::ServiceAlert(ServiceAlertCause::Construction),
+    ),
+    (
+        "construction",
+        CauseKind::ServiceAlert(ServiceAlertCause::Construction),
+    ),
+    ("traffic", CauseKind::Traffic),
+    ("congestion", CauseKind::Traffic),
+    ("road block", CauseKind::Traffic),
+    ("blocked road", CauseKind::Traffic),
+    (
+        "mechanical",
+        CauseKind::ServiceAlert(ServiceAlertCause::TechnicalProblem),
+    ),
+    (
+        r"break\w*",
+        CauseKind::ServiceAlert(ServiceAlertCause::TechnicalProblem),
+    ),
+    (
+        "faults?|faulty",
+        CauseKind::ServiceAlert(ServiceAlertCause::TechnicalProblem),
+    ),
+];
+
+// Used to guess which bus regex a tweet that doesn't parse was closest to.
+const BUS_FAMILY_KEYWORDS: [(&str, &str); 6] = [
+    ("reinstat", "BUS_REINSTATED_RE"),
+    ("part cancel", "BUS_PART_CANCELLED_RE"),
+    ("part-cancel", "BUS_PART_CANCELLED_RE"),
+    ("delay", "BUS_DELAYED_RE"),
+    (" late", "BUS_DELAYED_LATE_RE"),
+    ("cancel", "BUS_FULL_CANCELLED_RE"),
+];
+
+const FERRY_FAMILY_KEYWORDS: [(&str, &str); 4] = [
+    ("restricted", "FERRY_RESTRICTED_RE"),
+    (" late", "FERRY_DELAYED_RE"),
+    ("delay", "FERRY_DELAYED_RE"),
+    ("cancel", "FERRY_CANCELLED_RE"),
+];
+
+// Guesses which train regex a tweet was meant for, when none of them matched.
+const TRAIN_FAMILY_KEYWORDS: [(&str, &str); 8] = [
+    ("terminat", "TRAIN_PART_CANCELLED_RE"),
+    ("replaced", "TRAIN_REPLACED_RE"),
+    ("held", "TRAIN_HELD_RE"),
+    ("departed", "TRAIN_DEPARTED_LATE_RE"),
+    (" late", "TRAIN_RUNNING_LATE_RE"),
+    ("delay", "TRAIN_RUNNING_LATE_RE"),
+    ("cancel", "TRAIN_CANCELLED_RE"),
+    ("canx", "TRAIN_CANCELLED_RE"),
+];
 
 lazy_static! {
     static ref TRAIN_LINE_NAME: String = TRAIN_LINE_NAMES.join("|");
@@ -25,15 +177,15 @@ lazy_static! {
     ))
     .unwrap();
     static ref BUS_DELAYED_RE: Regex = Regex::new(&format!(
-        "{0}: {1} {2} +(?:is|has been|will be) delayed(?: by)? (?:[0-9]+-)?(?P<delay_mins>[0-9]+) min",
+        "{0}: {1} {2} +(?:is|has been|will be) delayed(?: by)? (?:(?P<delay_from_mins>[0-9]+)-)?(?P<delay_mins>[0-9]+) min",
         BUS_NUM_RE, TIME_RE, BUS_DEST_RE
     )).unwrap();
     static ref BUS_DELAYED_LATE_RE: Regex = Regex::new(&format!(
-        "{0}: {1} {2} +(?:will) run (?:[0-9]+-)?(?P<delay_mins>[0-9]+) minutes? late\\.",
+        "{0}: {1} {2} +(?:will) run (?:(?P<delay_from_mins>[0-9]+)-)?(?P<delay_mins>[0-9]+) minutes? late\\.",
         BUS_NUM_RE, TIME_RE, BUS_DEST_RE
     )).unwrap();
     static ref BUS_DELAYED_UNDETERMINATE_RE: Regex = Regex::new(&format!(
-        "{0}: {1} {2} (has been|is) delayed(?: due to vehicle breakdown| due to road block|)\\.",
+        "{0}: {1} {2} (?:has been|is) delayed(?: due to (?P<delay_reason>vehicle breakdown|road block)|)\\.",
         BUS_NUM_RE, TIME_RE, BUS_DEST_RE
     ))
     .unwrap();
@@ -52,46 +204,462 @@ lazy_static! {
             BUS_NUM_RE, TIME_RE, r"(?:to|-) (?P<destination>.*?)"
         ))
     .unwrap();
+    static ref BUS_NUM_PREFIX_RE: Regex = Regex::new(&format!("^{}", BUS_NUM_RE)).unwrap();
+    static ref BUS_DEPARTURE_PREFIX_RE: Regex = Regex::new(&format!(
+        "^{0}:(?: *{0}:)? *(?:from )?{1}",
+        BUS_NUM_RE.replace("?P<bus_num>", ""),
+        TIME_RE
+    ))
+    .unwrap();
+    static ref SCHOOL_BUS_RE: Regex = Regex::new(&format!(
+        "^School ?(?P<bus_num>[0-9]+):(?: *{0}:)? *{1} {2} +(?:is|has been|will be) (?P<impact>(?i)cancelled|delayed|reinstated)(?: by)?(?: (?:(?P<delay_from_mins>[0-9]+)-)?(?P<delay_mins>[0-9]+) min)?",
+        BUS_NUM_RE.replace("?P<bus_num>", ""),
+        TIME_RE,
+        BUS_DEST_RE
+    ))
+    .unwrap();
+    static ref FERRY_DELAYED_RE: Regex = Regex::new(&format!(
+        "^Ferry (?P<route>[A-Z]+):.*? the {0} (?:departure|sailing) from (?P<origin>.+?) (?:will be|is) running (?:approx )?(?:(?P<delay_from_mins>[0-9]+)-)?(?P<delay_mins>[0-9]+) mins? late",
+        TIME_RE
+    ))
+    .unwrap();
+    static ref FERRY_SUSPENDED_RE: Regex = Regex::new(
+        "^Ferry (?P<route>[A-Z]+):.*?(?:(?i:all) (?:ferry )?(?:sailings|services) (?:have been |are |were )?cancelled|(?P<service>Harbour Explorer)(?: Ferry)? (?:is )?cancelled) (?P<period>today|this (?:AM|PM|morning|afternoon|evening))"
+    )
+    .unwrap();
+    // Sailings are listed as "10am/12pm", "4.30, 5.30, 6.30pm" or "10am &amp; 12pm".
+    static ref FERRY_TIMES_RE: String =
+        format!("(?:{0}(?: &amp; | & |, | and |/))*{0}", FERRY_TIME_RE);
+    static ref FERRY_CANCELLED_RE: Regex = Regex::new(&format!(
+        "^Ferry (?P<route>[A-Z]+):.*?(?P<times>{0}) sailings? (?:from (?P<origin>[^0-9]+?) )?(?:(?:is|are|have been) )?cancelled",
+        *FERRY_TIMES_RE
+    ))
+    .unwrap();
+    // Sailings from both ends, like "10am/12pm sailings from QW &amp; 10.40am/12.45pm from Days Bay"
+    static ref FERRY_CANCELLED_FROM_RE: Regex = Regex::new(&format!(
+        "^Ferry (?P<route>[A-Z]+):.*?(?P<sailings>{0} (?:sailings? )?from [^0-9&]+?(?: (?:&amp;|&|and) {0} (?:sailings? )?from [^0-9&]+?)+) (?:(?:is|are|have been) )?cancelled",
+        *FERRY_TIMES_RE
+    ))
+    .unwrap();
+    static ref FERRY_SAILINGS_FROM_RE: Regex = Regex::new(&format!(
+        "(?P<times>{0}) (?:sailings? )?from (?P<origin>[^0-9&]+?)(?: &amp;| &| and|$)",
+        *FERRY_TIMES_RE
+    ))
+    .unwrap();
+    // The list can be cut off part way through a time, which is left out.
+    static ref FERRY_RESTRICTED_RE: Regex = Regex::new(&format!(
+        "^Ferry (?P<route>[A-Z]+):.*?restricted (?:sailings|timetable)(?: on the Harbour Ferry)?(?: (?P<period>this (?:AM|PM|morning|afternoon|evening)))?,? departing (?P<origin>.+?) (?:@|at) ?(?P<times>{0})(?:[^0-9.:]|$)",
+        *FERRY_TIMES_RE
+    ))
+    .unwrap();
+    static ref FERRY_NOTICE_RE: Regex = Regex::new(
+        r"(?i)\breminder\b|\breached capacity\b|\bnot (?:stop|stopping at|go via|run to)\b|\bno sailings in or out of\b"
+    )
+    .unwrap();
+    static ref TIME_LIST_RE: Regex = Regex::new(TIME_RE).unwrap();
+    static ref DISRUPTION_RE: Regex = Regex::new(
+        r"(?i)buses replac|buses are replacing|detour|divert|diversion|cannot pass|closure|road closed"
+    )
+    .unwrap();
+    // Tweets about one service can mention a detour too.
+    static ref SINGLE_SERVICE_RE: Regex =
+        Regex::new(&format!(r"(?i)\bthe {} (?:service|svc|departure|sailing)\b", TIME_RE))
+            .unwrap();
+    static ref BETWEEN_RE: Regex =
+        Regex::new(r"\bbetween (?P<from>[A-Z][\w ]*?) and (?P<to>[A-Z][\w]*(?: [A-Z][\w]*)*)")
+            .unwrap();
+    static ref PLACE_PREFIX_RE: Regex =
+        Regex::new(r"^(?:Bus [0-9]+[ex]?: +)?(?P<place>[A-Z][A-Za-z ,]*?): ").unwrap();
     static ref TRAIN_LINE_NAMES_RE: Regex = Regex::new(&TRAIN_LINE_NAME).unwrap();
+    static ref TRAIN_SERVICE_RE: String = format!(
+        "^(?P<line>{0}):(?: +(?:{0}):)? +The {1} (?:service|svc) from (?P<origin>{2}) to (?P<destination>{2})",
+        *TRAIN_LINE_NAME, TIME_RE, STATION_CODE_RE
+    );
+    static ref TRAIN_SERVICE_PREFIX_RE: Regex = Regex::new(&TRAIN_SERVICE_RE).unwrap();
+    static ref TRAIN_PART_CANCELLED_RE: Regex = Regex::new(&format!(
+        "{0} +(?:is|has been) (?:cancelled|canx) from (?P<cancelled_from>{1})",
+        *TRAIN_SERVICE_RE, STATION_CODE_RE
+    ))
+    .unwrap();
+    static ref TRAIN_PART_CANCELLED_BETWEEN_RE: Regex = Regex::new(&format!(
+        "{0} +(?:is|has been) (?:cancelled|canx) between (?P<cancelled_from>{1}) (?:and|&amp;|&) (?P<cancelled_to>{1})",
+        *TRAIN_SERVICE_RE, STATION_CODE_RE
+    ))
+    .unwrap();
+    static ref TRAIN_TERMINATING_RE: Regex = Regex::new(&format!(
+        "{0} +(?:will (?:be )?terminat(?:e|ing)|is terminating) at (?P<cancelled_from>{1})",
+        *TRAIN_SERVICE_RE, STATION_CODE_RE
+    ))
+    .unwrap();
+    static ref TRAIN_CANCELLED_RE: Regex = Regex::new(&format!(
+        "{0} +(?:is|has been) (?:cancelled|canx)",
+        *TRAIN_SERVICE_RE
+    ))
+    .unwrap();
+    static ref TRAIN_RUNNING_LATE_RE: Regex = Regex::new(&format!(
+        "{0} +is (?:now on the move (?:and )?)?running (?:approx )?(?:(?P<delay_from_mins>[0-9]+)-)?(?P<delay_mins>[0-9]+) mins? late(?: from (?P<delayed_from>{1}))?",
+        *TRAIN_SERVICE_RE, STATION_CODE_RE
+    ))
+    .unwrap();
+    static ref TRAIN_DEPARTED_LATE_RE: Regex = Regex::new(&format!(
+        "{0} +has (?:now )?departed(?: (?P<delayed_from>{1}))?(?: running)? (?:approx )?(?:(?P<delay_from_mins>[0-9]+)-)?(?P<delay_mins>[0-9]+) mins? late",
+        *TRAIN_SERVICE_RE, STATION_CODE_RE
+    ))
+    .unwrap();
+    static ref TRAIN_HELD_RE: Regex = Regex::new(&format!(
+        "{0} +is (?:currently |still )?(?:being )?held (?:at|(?:just )?(?:north|south) of) (?P<held_at>{1})",
+        *TRAIN_SERVICE_RE, STATION_CODE_RE
+    ))
+    .unwrap();
+    static ref TRAIN_REPLACED_RE: Regex = Regex::new(&format!(
+        "{0} +(?:is|has been|will be) replaced by bus(?:es)?(?: between (?P<replaced_from>{1}) and (?P<replaced_to>{1}))?",
+        *TRAIN_SERVICE_RE, STATION_CODE_RE
+    ))
+    .unwrap();
+    static ref CAUSE_KEYWORD_RES: Vec<(Regex, CauseKind)> = CAUSE_KEYWORDS
+        .iter()
+        .map(|(keyword, kind)| (Regex::new(&format!(r"\b(?:{})\b", keyword)).unwrap(), *kind))
+        .collect();
+    static ref STATED_CAUSE_RE: Regex = Regex::new(CAUSE_RE).unwrap();
+    static ref CORRECTION_RE: Regex = Regex::new(r"\b(?:CORRECTION|Correction): +").unwrap();
+    static ref LINK_RE: Regex = Regex::new(r"https://t\.co/[A-Za-z0-9]+").unwrap();
+    static ref ON_THE_MOVE_RE: Regex = Regex::new(&format!(
+        r"\bon the move(?: from (?P<moved_from>{}))?",
+        STATION_CODE_RE
+    ))
+    .unwrap();
+    static ref MINUTES_RE: Regex = Regex::new(r"\b[0-9]+ min").unwrap();
+    static ref TRAIN_NOTICE_RE: Regex = Regex::new(
+        r"(?i)\breduced (?:seating|number of carriages|carriages)\b|\b[0-9] cars instead of\b|\b(?:are|is) (?:now )?(?:running|returning) (?:to|as) (?:normal|regular|scheduled|timetable|schedule)|\bclearance\b|\bbeen resolved\b|\bfinal update\b"
+    )
+    .unwrap();
 }
 
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct ResolvedStop {
+    pub id: String,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+/// A stop, station or place as written in the tweet; `stop` is filled in once it's been matched
+///  against the GTFS stops.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Location {
+    pub raw: String,
+    pub stop: Option<ResolvedStop>,
+}
+impl From<&str> for Location {
+    fn from(raw: &str) -> Self {
+        Self {
+            raw: raw.to_string(),
+            stop: None,
+        }
+    }
+}
+
+/// What a stated cause has been classified as. Where possible this uses the same categories as
+///  the GTFS-RT service alerts, so the two can be compared.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum CauseKind {
+    ServiceAlert(ServiceAlertCause),
+    LateArrivalOfPreviousService,
+    Trespasser,
+    StaffShortage,
+    SignalFault,
+    TrackFault,
+    Obstruction,
+    BusReplacement,
+    FreightTrain,
+    Traffic,
+}
+impl From<&str> for CauseKind {
+    fn from(raw: &str) -> Self {
+        let raw = raw.to_lowercase();
+        CAUSE_KEYWORD_RES
+            .iter()
+            .find(|(keyword, _)| keyword.is_match(&raw))
+            .map(|(_, kind)| *kind)
+            .unwrap_or(CauseKind::ServiceAlert(ServiceAlertCause::OtherCause))
+    }
+}
+
+/// The reason given in the tweet ("due to a signal fault"), and what it's been classified as.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Cause {
+    pub raw: String,
+    pub kind: CauseKind,
+}
+impl From<&str> for Cause {
+    fn from(raw: &str) -> Self {
+        Self {
+            raw: raw.to_string(),
+            kind: raw.into(),
+        }
+    }
+}
+
+/// How late a service is running, as given in the tweet.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Debug)]
+pub enum Delay {
+    Minutes(u32),
+    /// "delayed 15-20 minutes"
+    Range {
+        min: u32,
+        max: u32,
+    },
+    /// Delayed, but not by how much; `reason` is the one given when there's no number, such as
+    ///  "vehicle breakdown".
+    Unknown {
+        reason: Option<String>,
+    },
+}
+impl Delay {
+    /// The delay in minutes for adding up; ranges count as their midpoint.
+    pub fn minutes(&self) -> Option<u32> {
+        match self {
+            Delay::Minutes(minutes) => Some(*minutes),
+            Delay::Range { min, max } => Some((min + max) / 2),
+            Delay::Unknown { .. } => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SchoolRun {
+    Morning,
+    Afternoon,
+}
+
+/// What's happened to a school service; they don't get part cancelled.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Debug)]
+pub enum SchoolBusImpact {
+    Cancelled,
+    Delayed(Delay),
+    Reinstated,
+}
+
+/// A ferry route as named in the tweet; `route_id` is filled in once it's been matched against the
+///  GTFS ferry routes.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct FerryRoute {
+    pub raw: String,
+    pub route_id: Option<String>,
+}
+impl From<&str> for FerryRoute {
+    fn from(raw: &str) -> Self {
+        Self {
+            raw: raw.to_string(),
+            route_id: None,
+        }
+    }
+}
+
+/// How much of the day's sailings a ferry suspension covers.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FerryPeriod {
+    Day,
+    Morning,
+    Afternoon,
+}
+
+/// An event announced by a tweet. `link` is the t.co link the tweet came with, which usually goes
+///  to the full notice on the Metlink site.
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
 pub enum Cancellations {
     BusCancelled {
         route: String,
-        origin: String,
-        destination: String,
+        origin: Location,
+        destination: Location,
+        cause: Option<Cause>,
         raw_time: String,
         tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
         time: DateTime<FixedOffset>,
     },
     BusPartCancelled {
         route: String,
-        origin: String,
-        destination: String,
-        cancelled_from: String,
-        cancelled_to: String,
+        origin: Location,
+        destination: Location,
+        cancelled_from: Location,
+        cancelled_to: Location,
+        cause: Option<Cause>,
         raw_time: String,
         tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
         time: DateTime<FixedOffset>,
     },
     BusReinstated {
         route: String,
-        origin: String,
-        destination: String,
+        origin: Location,
+        destination: Location,
+        cause: Option<Cause>,
         raw_time: String,
         tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
         time: DateTime<FixedOffset>,
     },
     BusDelayed {
         route: String,
-        origin: String,
-        destination: String,
-        delay_minutes: String,
+        origin: Location,
+        destination: Location,
+        delay: Delay,
+        cause: Option<Cause>,
+        raw_time: String,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        time: DateTime<FixedOffset>,
+    },
+    TrainCancelled {
+        line: String,
+        origin: Location,
+        destination: Location,
+        cause: Option<Cause>,
         raw_time: String,
         tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
         time: DateTime<FixedOffset>,
     },
+    TrainPartCancelled {
+        line: String,
+        origin: Location,
+        destination: Location,
+        cancelled_from: Location,
+        cancelled_to: Location,
+        cause: Option<Cause>,
+        raw_time: String,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        time: DateTime<FixedOffset>,
+    },
+    TrainDelayed {
+        line: String,
+        origin: Location,
+        destination: Location,
+        delayed_from: Location,
+        delay: Delay,
+        cause: Option<Cause>,
+        raw_time: String,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        time: DateTime<FixedOffset>,
+    },
+    TrainHeld {
+        line: String,
+        origin: Location,
+        destination: Location,
+        held_at: Location,
+        cause: Option<Cause>,
+        raw_time: String,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        time: DateTime<FixedOffset>,
+    },
+    TrainReplaced {
+        line: String,
+        origin: Location,
+        destination: Location,
+        replaced_from: Location,
+        replaced_to: Location,
+        cause: Option<Cause>,
+        raw_time: String,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        time: DateTime<FixedOffset>,
+    },
+    /// A school service. These run once a school day, to the school in the morning and back in the
+    ///  afternoon.
+    SchoolBus {
+        route: String,
+        school: String,
+        run: SchoolRun,
+        impact: SchoolBusImpact,
+        origin: Location,
+        destination: Location,
+        cause: Option<Cause>,
+        raw_time: String,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        time: DateTime<FixedOffset>,
+    },
+    // Ferry tweets only say where a sailing leaves from, so `destination` is left empty.
+    FerryCancelled {
+        route: FerryRoute,
+        origin: Location,
+        destination: Location,
+        cause: Option<Cause>,
+        raw_time: String,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        time: DateTime<FixedOffset>,
+    },
+    FerryDelayed {
+        route: FerryRoute,
+        origin: Location,
+        destination: Location,
+        delay: Delay,
+        cause: Option<Cause>,
+        raw_time: String,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        time: DateTime<FixedOffset>,
+    },
+    /// Every sailing for the rest of the day, morning or afternoon is cancelled, usually because of
+    ///  the weather. `time` is the start of the period.
+    FerrySuspended {
+        route: FerryRoute,
+        period: FerryPeriod,
+        origin: Location,
+        destination: Location,
+        cause: Option<Cause>,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        time: DateTime<FixedOffset>,
+    },
+    /// Only the listed sailings are running for the rest of the day, morning or afternoon. `time`
+    ///  is the start of the period.
+    FerryRestricted {
+        route: FerryRoute,
+        period: FerryPeriod,
+        origin: Location,
+        destination: Location,
+        sailings: Vec<String>,
+        cause: Option<Cause>,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        time: DateTime<FixedOffset>,
+    },
+    /// A disruption to some lines or routes for a window of time, rather than to a single trip.
+    ///  `routes` is empty when the tweet doesn't say which are affected.
+    NetworkDisruption {
+        kind: DisruptionKind,
+        routes: Vec<String>,
+        origin: Location,
+        destination: Location,
+        window: DisruptionWindow,
+        planned: bool,
+        cause: Option<Cause>,
+        tweet_time: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+    },
+    /// A tweet correcting an earlier one. Deduplicating replaces the event it corrects with this
+    ///  one.
+    Correction { event: Box<Cancellations> },
 }
 
 impl Cancellations {
@@ -101,6 +669,18 @@ impl Cancellations {
             Cancellations::BusPartCancelled { tweet_time, .. } => tweet_time,
             Cancellations::BusReinstated { tweet_time, .. } => tweet_time,
             Cancellations::BusDelayed { tweet_time, .. } => tweet_time,
+            Cancellations::TrainCancelled { tweet_time, .. } => tweet_time,
+            Cancellations::TrainPartCancelled { tweet_time, .. } => tweet_time,
+            Cancellations::TrainDelayed { tweet_time, .. } => tweet_time,
+            Cancellations::TrainHeld { tweet_time, .. } => tweet_time,
+            Cancellations::TrainReplaced { tweet_time, .. } => tweet_time,
+            Cancellations::SchoolBus { tweet_time, .. } => tweet_time,
+            Cancellations::FerryCancelled { tweet_time, .. } => tweet_time,
+            Cancellations::FerryDelayed { tweet_time, .. } => tweet_time,
+            Cancellations::FerrySuspended { tweet_time, .. } => tweet_time,
+            Cancellations::FerryRestricted { tweet_time, .. } => tweet_time,
+            Cancellations::NetworkDisruption { tweet_time, .. } => tweet_time,
+            Cancellations::Correction { event } => event.tweet_time(),
         }
     }
     pub fn time(&self) -> &DateTime<FixedOffset> {
@@ -109,10 +689,380 @@ impl Cancellations {
             Cancellations::BusPartCancelled { time, .. } => time,
             Cancellations::BusReinstated { time, .. } => time,
             Cancellations::BusDelayed { time, .. } => time,
+            Cancellations::TrainCancelled { time, .. } => time,
+            Cancellations::TrainPartCancelled { time, .. } => time,
+            Cancellations::TrainDelayed { time, .. } => time,
+            Cancellations::TrainHeld { time, .. } => time,
+            Cancellations::TrainReplaced { time, .. } => time,
+            Cancellations::SchoolBus { time, .. } => time,
+            Cancellations::FerryCancelled { time, .. } => time,
+            Cancellations::FerryDelayed { time, .. } => time,
+            Cancellations::FerrySuspended { time, .. } => time,
+            Cancellations::FerryRestricted { time, .. } => time,
+            Cancellations::NetworkDisruption { window, .. } => &window.start,
+            Cancellations::Correction { event } => event.time(),
+        }
+    }
+    /// The bus route number, the train line, or the ferry route.
+    pub fn route(&self) -> &str {
+        match self {
+            Cancellations::BusCancelled { route, .. } => route,
+            Cancellations::BusPartCancelled { route, .. } => route,
+            Cancellations::BusReinstated { route, .. } => route,
+            Cancellations::BusDelayed { route, .. } => route,
+            Cancellations::TrainCancelled { line, .. } => line,
+            Cancellations::TrainPartCancelled { line, .. } => line,
+            Cancellations::TrainDelayed { line, .. } => line,
+            Cancellations::TrainHeld { line, .. } => line,
+            Cancellations::TrainReplaced { line, .. } => line,
+            Cancellations::SchoolBus { route, .. } => route,
+            Cancellations::FerryCancelled { route, .. } => &route.raw,
+            Cancellations::FerryDelayed { route, .. } => &route.raw,
+            Cancellations::FerrySuspended { route, .. } => &route.raw,
+            Cancellations::FerryRestricted { route, .. } => &route.raw,
+            Cancellations::NetworkDisruption { routes, .. } => {
+                routes.first().map(|r| r.as_str()).unwrap_or("")
+            }
+            Cancellations::Correction { event } => event.route(),
+        }
+    }
+    pub fn origin(&self) -> &Location {
+        match self {
+            Cancellations::BusCancelled { origin, .. } => origin,
+            Cancellations::BusPartCancelled { origin, .. } => origin,
+            Cancellations::BusReinstated { origin, .. } => origin,
+            Cancellations::BusDelayed { origin, .. } => origin,
+            Cancellations::TrainCancelled { origin, .. } => origin,
+            Cancellations::TrainPartCancelled { origin, .. } => origin,
+            Cancellations::TrainDelayed { origin, .. } => origin,
+            Cancellations::TrainHeld { origin, .. } => origin,
+            Cancellations::TrainReplaced { origin, .. } => origin,
+            Cancellations::SchoolBus { origin, .. } => origin,
+            Cancellations::FerryCancelled { origin, .. } => origin,
+            Cancellations::FerryDelayed { origin, .. } => origin,
+            Cancellations::FerrySuspended { origin, .. } => origin,
+            Cancellations::FerryRestricted { origin, .. } => origin,
+            Cancellations::NetworkDisruption { origin, .. } => origin,
+            Cancellations::Correction { event } => event.origin(),
+        }
+    }
+    pub fn destination(&self) -> &Location {
+        match self {
+            Cancellations::BusCancelled { destination, .. } => destination,
+            Cancellations::BusPartCancelled { destination, .. } => destination,
+            Cancellations::BusReinstated { destination, .. } => destination,
+            Cancellations::BusDelayed { destination, .. } => destination,
+            Cancellations::TrainCancelled { destination, .. } => destination,
+            Cancellations::TrainPartCancelled { destination, .. } => destination,
+            Cancellations::TrainDelayed { destination, .. } => destination,
+            Cancellations::TrainHeld { destination, .. } => destination,
+            Cancellations::TrainReplaced { destination, .. } => destination,
+            Cancellations::SchoolBus { destination, .. } => destination,
+            Cancellations::FerryCancelled { destination, .. } => destination,
+            Cancellations::FerryDelayed { destination, .. } => destination,
+            Cancellations::FerrySuspended { destination, .. } => destination,
+            Cancellations::FerryRestricted { destination, .. } => destination,
+            Cancellations::NetworkDisruption { destination, .. } => destination,
+            Cancellations::Correction { event } => event.destination(),
+        }
+    }
+    pub fn cause(&self) -> Option<&Cause> {
+        match self {
+            Cancellations::BusCancelled { cause, .. } => cause.as_ref(),
+            Cancellations::BusPartCancelled { cause, .. } => cause.as_ref(),
+            Cancellations::BusReinstated { cause, .. } => cause.as_ref(),
+            Cancellations::BusDelayed { cause, .. } => cause.as_ref(),
+            Cancellations::TrainCancelled { cause, .. } => cause.as_ref(),
+            Cancellations::TrainPartCancelled { cause, .. } => cause.as_ref(),
+            Cancellations::TrainDelayed { cause, .. } => cause.as_ref(),
+            Cancellations::TrainHeld { cause, .. } => cause.as_ref(),
+            Cancellations::TrainReplaced { cause, .. } => cause.as_ref(),
+            Cancellations::SchoolBus { cause, .. } => cause.as_ref(),
+            Cancellations::FerryCancelled { cause, .. } => cause.as_ref(),
+            Cancellations::FerryDelayed { cause, .. } => cause.as_ref(),
+            Cancellations::FerrySuspended { cause, .. } => cause.as_ref(),
+            Cancellations::FerryRestricted { cause, .. } => cause.as_ref(),
+            Cancellations::NetworkDisruption { cause, .. } => cause.as_ref(),
+            Cancellations::Correction { event } => event.cause(),
+        }
+    }
+    pub fn cause_mut(&mut self) -> &mut Option<Cause> {
+        match self {
+            Cancellations::BusCancelled { cause, .. } => cause,
+            Cancellations::BusPartCancelled { cause, .. } => cause,
+            Cancellations::BusReinstated { cause, .. } => cause,
+            Cancellations::BusDelayed { cause, .. } => cause,
+            Cancellations::TrainCancelled { cause, .. } => cause,
+            Cancellations::TrainPartCancelled { cause, .. } => cause,
+            Cancellations::TrainDelayed { cause, .. } => cause,
+            Cancellations::TrainHeld { cause, .. } => cause,
+            Cancellations::TrainReplaced { cause, .. } => cause,
+            Cancellations::SchoolBus { cause, .. } => cause,
+            Cancellations::FerryCancelled { cause, .. } => cause,
+            Cancellations::FerryDelayed { cause, .. } => cause,
+            Cancellations::FerrySuspended { cause, .. } => cause,
+            Cancellations::FerryRestricted { cause, .. } => cause,
+            Cancellations::NetworkDisruption { cause, .. } => cause,
+            Cancellations::Correction { event } => event.cause_mut(),
+        }
+    }
+    pub fn link(&self) -> Option<&str> {
+        match self {
+            Cancellations::BusCancelled { link, .. } => link.as_deref(),
+            Cancellations::BusPartCancelled { link, .. } => link.as_deref(),
+            Cancellations::BusReinstated { link, .. } => link.as_deref(),
+            Cancellations::BusDelayed { link, .. } => link.as_deref(),
+            Cancellations::TrainCancelled { link, .. } => link.as_deref(),
+            Cancellations::TrainPartCancelled { link, .. } => link.as_deref(),
+            Cancellations::TrainDelayed { link, .. } => link.as_deref(),
+            Cancellations::TrainHeld { link, .. } => link.as_deref(),
+            Cancellations::TrainReplaced { link, .. } => link.as_deref(),
+            Cancellations::SchoolBus { link, .. } => link.as_deref(),
+            Cancellations::FerryCancelled { link, .. } => link.as_deref(),
+            Cancellations::FerryDelayed { link, .. } => link.as_deref(),
+            Cancellations::FerrySuspended { link, .. } => link.as_deref(),
+            Cancellations::FerryRestricted { link, .. } => link.as_deref(),
+            Cancellations::NetworkDisruption { link, .. } => link.as_deref(),
+            Cancellations::Correction { event } => event.link(),
+        }
+    }
+    pub fn link_mut(&mut self) -> &mut Option<String> {
+        match self {
+            Cancellations::BusCancelled { link, .. } => link,
+            Cancellations::BusPartCancelled { link, .. } => link,
+            Cancellations::BusReinstated { link, .. } => link,
+            Cancellations::BusDelayed { link, .. } => link,
+            Cancellations::TrainCancelled { link, .. } => link,
+            Cancellations::TrainPartCancelled { link, .. } => link,
+            Cancellations::TrainDelayed { link, .. } => link,
+            Cancellations::TrainHeld { link, .. } => link,
+            Cancellations::TrainReplaced { link, .. } => link,
+            Cancellations::SchoolBus { link, .. } => link,
+            Cancellations::FerryCancelled { link, .. } => link,
+            Cancellations::FerryDelayed { link, .. } => link,
+            Cancellations::FerrySuspended { link, .. } => link,
+            Cancellations::FerryRestricted { link, .. } => link,
+            Cancellations::NetworkDisruption { link, .. } => link,
+            Cancellations::Correction { event } => event.link_mut(),
+        }
+    }
+    pub fn school(&self) -> Option<&str> {
+        match self {
+            Cancellations::SchoolBus { school, .. } => Some(school),
+            Cancellations::Correction { event } => event.school(),
+            _ => None,
+        }
+    }
+    pub fn delay(&self) -> Option<&Delay> {
+        match self {
+            Cancellations::BusDelayed { delay, .. } => Some(delay),
+            Cancellations::TrainDelayed { delay, .. } => Some(delay),
+            Cancellations::FerryDelayed { delay, .. } => Some(delay),
+            Cancellations::SchoolBus {
+                impact: SchoolBusImpact::Delayed(delay),
+                ..
+            } => Some(delay),
+            Cancellations::Correction { event } => event.delay(),
+            _ => None,
+        }
+    }
+    pub fn window(&self) -> Option<&DisruptionWindow> {
+        match self {
+            Cancellations::NetworkDisruption { window, .. } => Some(window),
+            Cancellations::Correction { event } => event.window(),
+            _ => None,
+        }
+    }
+    pub fn ferry_route_mut(&mut self) -> Option<&mut FerryRoute> {
+        match self {
+            Cancellations::FerryCancelled { route, .. } => Some(route),
+            Cancellations::FerryDelayed { route, .. } => Some(route),
+            Cancellations::FerrySuspended { route, .. } => Some(route),
+            Cancellations::FerryRestricted { route, .. } => Some(route),
+            Cancellations::Correction { event } => event.ferry_route_mut(),
+            _ => None,
+        }
+    }
+    pub fn locations_mut(&mut self) -> Vec<&mut Location> {
+        match self {
+            Cancellations::BusCancelled {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+            Cancellations::BusPartCancelled {
+                origin,
+                destination,
+                cancelled_from,
+                cancelled_to,
+                ..
+            } => vec![origin, destination, cancelled_from, cancelled_to],
+            Cancellations::BusReinstated {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+            Cancellations::BusDelayed {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+            Cancellations::TrainCancelled {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+            Cancellations::TrainPartCancelled {
+                origin,
+                destination,
+                cancelled_from,
+                cancelled_to,
+                ..
+            } => vec![origin, destination, cancelled_from, cancelled_to],
+            Cancellations::TrainDelayed {
+                origin,
+                destination,
+                delayed_from,
+                ..
+            } => vec![origin, destination, delayed_from],
+            Cancellations::TrainHeld {
+                origin,
+                destination,
+                held_at,
+                ..
+            } => vec![origin, destination, held_at],
+            Cancellations::TrainReplaced {
+                origin,
+                destination,
+                replaced_from,
+                replaced_to,
+                ..
+            } => vec![origin, destination, replaced_from, replaced_to],
+            Cancellations::SchoolBus {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+            Cancellations::FerryCancelled {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+            Cancellations::FerryDelayed {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+            Cancellations::FerrySuspended {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+            Cancellations::FerryRestricted {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+            Cancellations::NetworkDisruption {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+            Cancellations::Correction { event } => event.locations_mut(),
+        }
+    }
+}
+
+/// What a tweet from a line's account turned out to be.
+enum Parsed {
+    Events(Vec<Cancellations>),
+    /// A notice that isn't about a service being disrupted, like reduced seating or a held train
+    ///  being on the move.
+    NotAnEvent,
+}
+impl Parsed {
+    fn events(self) -> Vec<Cancellations> {
+        match self {
+            Parsed::Events(events) => events,
+            Parsed::NotAnEvent => vec![],
         }
     }
 }
 
+/// Why a tweet couldn't be parsed.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct ParseError {
+    /// The regex that matched, or that the tweet looks like it was meant to match.
+    pub family: Option<String>,
+    /// The part of the tweet that couldn't be parsed.
+    pub capture: Option<String>,
+    pub message: String,
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(family) = &self.family {
+            write!(f, " (closest: {})", family)?;
+        }
+        if let Some(capture) = &self.capture {
+            write!(f, " (failed: {})", capture)?;
+        }
+        Ok(())
+    }
+}
+
+/// A tweet that couldn't be parsed, as written to the diagnostics report.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct ParseDiagnostic {
+    pub tweet_id: u64,
+    pub created_at: DateTime<Utc>,
+    pub text: String,
+    #[serde(flatten)]
+    pub error: ParseError,
+}
+
+fn unparsed_bus_tweet(tweet: &TweetContent) -> ParseError {
+    let text = tweet.text.to_lowercase();
+    let family = BUS_FAMILY_KEYWORDS
+        .iter()
+        .find(|(keyword, _)| text.contains(keyword))
+        .map(|(_, family)| family.to_string());
+    // The first piece of the tweet that every bus regex needs
+    let capture = if !BUS_NUM_PREFIX_RE.is_match(&tweet.text) {
+        Some("bus_num")
+    } else if !BUS_DEPARTURE_PREFIX_RE.is_match(&tweet.text) {
+        Some("hour")
+    } else if family.is_some() {
+        Some("destination")
+    } else {
+        None
+    };
+    ParseError {
+        family,
+        capture: capture.map(|c| c.to_string()),
+        message: "Unable to detect bus impact".to_string(),
+    }
+}
+
+fn unparsed_train_tweet(tweet: &TweetContent) -> ParseError {
+    let text = tweet.text.to_lowercase();
+    let family = TRAIN_FAMILY_KEYWORDS
+        .iter()
+        .find(|(keyword, _)| text.contains(keyword))
+        .map(|(_, family)| family.to_string());
+    // Always set, so that linked tweets still get reported
+    let capture = if !TRAIN_SERVICE_PREFIX_RE.is_match(&tweet.text) {
+        "service"
+    } else {
+        "impact"
+    };
+    ParseError {
+        family,
+        capture: Some(capture.to_string()),
+        message: "Unable to detect train impact".to_string(),
+    }
+}
+
 fn do_time_from(
     time: DateTime<Utc>,
     hour: u32,
@@ -165,7 +1115,8 @@ fn do_time_from(
 fn time_from_capture(
     tweet: &TweetContent,
     capture: &Captures,
-) -> Result<(String, DateTime<FixedOffset>), String> {
+    family: &str,
+) -> Result<(String, DateTime<FixedOffset>), ParseError> {
     let hour = capture.name("hour").unwrap().as_str().parse().unwrap();
     let minute = capture
         .name("minute")
@@ -178,48 +1129,79 @@ fn time_from_capture(
         .unwrap()
         .as_str()
         .to_ascii_lowercase();
-    return do_time_from(tweet.created_at, hour, minute, &period);
+    do_time_from(tweet.created_at, hour, minute, &period).map_err(|message| ParseError {
+        family: Some(family.to_string()),
+        capture: Some("time".to_string()),
+        message,
+    })
+}
+
+fn delay_from_capture(capture: &Captures, family: &str) -> Result<Delay, ParseError> {
+    let minutes = |name: &str| -> Result<Option<u32>, ParseError> {
+        capture
+            .name(name)
+            .map(|m| {
+                m.as_str().parse().map_err(|_| ParseError {
+                    family: Some(family.to_string()),
+                    capture: Some(name.to_string()),
+                    message: format!("Delay of {:?} minutes is out of range", m.as_str()),
+                })
+            })
+            .transpose()
+    };
+    let max = minutes("delay_mins")?.unwrap();
+    Ok(match minutes("delay_from_mins")? {
+        Some(min) => Delay::Range { min, max },
+        None => Delay::Minutes(max),
+    })
 }
 
-fn parse_bus_tweet(tweet: &TweetContent) -> Result<Vec<Cancellations>, String> {
+fn parse_bus_tweet(tweet: &TweetContent) -> Result<Vec<Cancellations>, ParseError> {
+    let cause = cause_from_text(&tweet.text);
     None.or_else(|| {
         BUS_REINSTATED_RE.captures(&tweet.text).map(|capture| {
-            let (raw_time, time) = time_from_capture(tweet, &capture)?;
+            let (raw_time, time) = time_from_capture(tweet, &capture, "BUS_REINSTATED_RE")?;
             Ok(vec![Cancellations::BusReinstated {
                 route: capture.name("bus_num").unwrap().as_str().to_string(),
-                origin: capture.name("origin").unwrap().as_str().to_string(),
-                destination: capture.name("destination").unwrap().as_str().to_string(),
+                origin: capture.name("origin").unwrap().as_str().into(),
+                destination: capture.name("destination").unwrap().as_str().into(),
+                cause: cause.clone(),
                 raw_time,
                 tweet_time: tweet.created_at,
                 time,
+                link: None,
             }])
         })
     })
     .or_else(|| {
         BUS_DELAYED_RE.captures(&tweet.text).map(|capture| {
-            let (raw_time, time) = time_from_capture(tweet, &capture)?;
+            let (raw_time, time) = time_from_capture(tweet, &capture, "BUS_DELAYED_RE")?;
             Ok(vec![Cancellations::BusDelayed {
                 route: capture.name("bus_num").unwrap().as_str().to_string(),
-                origin: capture.name("origin").unwrap().as_str().to_string(),
-                destination: capture.name("destination").unwrap().as_str().to_string(),
-                delay_minutes: capture.name("delay_mins").unwrap().as_str().to_string(),
+                origin: capture.name("origin").unwrap().as_str().into(),
+                destination: capture.name("destination").unwrap().as_str().into(),
+                delay: delay_from_capture(&capture, "BUS_DELAYED_RE")?,
+                cause: cause.clone(),
                 raw_time,
                 tweet_time: tweet.created_at,
                 time,
+                link: None,
             }])
         })
     })
     .or_else(|| {
         BUS_DELAYED_LATE_RE.captures(&tweet.text).map(|capture| {
-            let (raw_time, time) = time_from_capture(tweet, &capture)?;
+            let (raw_time, time) = time_from_capture(tweet, &capture, "BUS_DELAYED_LATE_RE")?;
             Ok(vec![Cancellations::BusDelayed {
                 route: capture.name("bus_num").unwrap().as_str().to_string(),
-                origin: capture.name("origin").unwrap().as_str().to_string(),
-                destination: capture.name("destination").unwrap().as_str().to_string(),
-                delay_minutes: capture.name("delay_mins").unwrap().as_str().to_string(),
+                origin: capture.name("origin").unwrap().as_str().into(),
+                destination: capture.name("destination").unwrap().as_str().into(),
+                delay: delay_from_capture(&capture, "BUS_DELAYED_LATE_RE")?,
+                cause: cause.clone(),
                 raw_time,
                 tweet_time: tweet.created_at,
                 time,
+                link: None,
             }])
         })
     })
@@ -227,161 +1209,669 @@ fn parse_bus_tweet(tweet: &TweetContent) -> Result<Vec<Cancellations>, String> {
         BUS_DELAYED_UNDETERMINATE_RE
             .captures(&tweet.text)
             .map(|capture| {
-                let (raw_time, time) = time_from_capture(tweet, &capture)?;
-                Ok(vec![Cancellations::BusDelayed {
-                    route: capture.name("bus_num").unwrap().as_str().to_string(),
-                    origin: capture.name("origin").unwrap().as_str().to_string(),
-                    destination: capture.name("destination").unwrap().as_str().to_string(),
-                    delay_minutes: "".to_string(),
+                let (raw_time, time) =
+                    time_from_capture(tweet, &capture, "BUS_DELAYED_UNDETERMINATE_RE")?;
+                Ok(vec![Cancellations::BusDelayed {
+                    route: capture.name("bus_num").unwrap().as_str().to_string(),
+                    origin: capture.name("origin").unwrap().as_str().into(),
+                    destination: capture.name("destination").unwrap().as_str().into(),
+                    delay: Delay::Unknown {
+                        reason: capture.name("delay_reason").map(|m| m.as_str().to_string()),
+                    },
+                    cause: cause.clone(),
+                    raw_time,
+                    tweet_time: tweet.created_at,
+                    time,
+                    link: None,
+                }])
+            })
+    })
+    .or_else(|| {
+        BUS_PART_CANCELLED_BETWEEN_RE
+            .captures(&tweet.text)
+            .map(|capture| {
+                let (raw_time, time) =
+                    time_from_capture(tweet, &capture, "BUS_PART_CANCELLED_BETWEEN_RE")?;
+                Ok(vec![Cancellations::BusPartCancelled {
+                    route: capture.name("bus_num").unwrap().as_str().to_string(),
+                    origin: capture.name("origin").unwrap().as_str().into(),
+                    destination: capture.name("destination").unwrap().as_str().into(),
+                    cancelled_from: capture.name("cancelled_from").unwrap().as_str().into(),
+                    cancelled_to: capture.name("cancelled_to").unwrap().as_str().into(),
+                    cause: cause.clone(),
+                    raw_time,
+                    tweet_time: tweet.created_at,
+                    time,
+                    link: None,
+                }])
+            })
+    })
+    .or_else(|| {
+        BUS_PART_CANCELLED_BETWEEN_NO_ORIGIN_RE
+            .captures(&tweet.text)
+            .map(|capture| {
+                let cancelled_from: Location =
+                    capture.name("cancelled_from").unwrap().as_str().into();
+                let (raw_time, time) =
+                    time_from_capture(tweet, &capture, "BUS_PART_CANCELLED_BETWEEN_NO_ORIGIN_RE")?;
+                Ok(vec![Cancellations::BusPartCancelled {
+                    route: capture.name("bus_num").unwrap().as_str().to_string(),
+                    origin: cancelled_from.clone(),
+                    destination: capture.name("destination").unwrap().as_str().into(),
+                    cancelled_from,
+                    cancelled_to: capture.name("cancelled_to").unwrap().as_str().into(),
+                    cause: cause.clone(),
+                    raw_time,
+                    tweet_time: tweet.created_at,
+                    time,
+                    link: None,
+                }])
+            })
+    })
+    .or_else(|| {
+        BUS_PART_CANCELLED_RE.captures(&tweet.text).map(|capture| {
+            let destination: Location = capture.name("destination").unwrap().as_str().into();
+            let (raw_time, time) = time_from_capture(tweet, &capture, "BUS_PART_CANCELLED_RE")?;
+            Ok(vec![Cancellations::BusPartCancelled {
+                route: capture.name("bus_num").unwrap().as_str().to_string(),
+                origin: capture.name("origin").unwrap().as_str().into(),
+                destination: destination.clone(),
+                cancelled_from: capture.name("cancelled_from").unwrap().as_str().into(),
+                cancelled_to: destination,
+                cause: cause.clone(),
+                raw_time,
+                tweet_time: tweet.created_at,
+                time,
+                link: None,
+            }])
+        })
+    })
+    .or_else(|| {
+        BUS_FULL_CANCELLED_RE.captures(&tweet.text).map(|capture| {
+            let (raw_time, time) = time_from_capture(tweet, &capture, "BUS_FULL_CANCELLED_RE")?;
+            Ok(vec![Cancellations::BusCancelled {
+                route: capture.name("bus_num").unwrap().as_str().to_string(),
+                origin: capture.name("origin").unwrap().as_str().into(),
+                destination: capture.name("destination").unwrap().as_str().into(),
+                cause: cause.clone(),
+                raw_time,
+                tweet_time: tweet.created_at,
+                time,
+                link: None,
+            }])
+        })
+    })
+    .unwrap_or_else(|| {
+        if tweet.text.contains("buses cannot pass") {
+            Ok(vec![])
+        } else {
+            Err(unparsed_bus_tweet(tweet))
+        }
+    })
+}
+
+pub fn cause_from_text(text: &str) -> Option<Cause> {
+    STATED_CAUSE_RE
+        .captures(text)
+        .map(|capture| capture.name("cause").unwrap().as_str().into())
+}
+
+fn parse_school_tweet(tweet: &TweetContent) -> Result<Vec<Cancellations>, ParseError> {
+    let capture = SCHOOL_BUS_RE
+        .captures(&tweet.text)
+        .ok_or_else(|| ParseError {
+            family: Some("SCHOOL_BUS_RE".to_string()),
+            capture: None,
+            message: "Unable to detect school bus impact".to_string(),
+        })?;
+    let (raw_time, time) = time_from_capture(tweet, &capture, "SCHOOL_BUS_RE")?;
+    let impact = match capture
+        .name("impact")
+        .unwrap()
+        .as_str()
+        .to_lowercase()
+        .as_str()
+    {
+        "cancelled" => SchoolBusImpact::Cancelled,
+        "reinstated" => SchoolBusImpact::Reinstated,
+        _ if capture.name("delay_mins").is_some() => {
+            SchoolBusImpact::Delayed(delay_from_capture(&capture, "SCHOOL_BUS_RE")?)
+        }
+        _ => SchoolBusImpact::Delayed(Delay::Unknown { reason: None }),
+    };
+    let origin: Location = capture.name("origin").unwrap().as_str().into();
+    let destination: Location = capture.name("destination").unwrap().as_str().into();
+    let (run, school) = if time.hour() < 12 {
+        (SchoolRun::Morning, destination.raw.clone())
+    } else {
+        (SchoolRun::Afternoon, origin.raw.clone())
+    };
+    Ok(vec![Cancellations::SchoolBus {
+        route: capture.name("bus_num").unwrap().as_str().to_string(),
+        school,
+        run,
+        impact,
+        origin,
+        destination,
+        cause: cause_from_text(&tweet.text),
+        raw_time,
+        tweet_time: tweet.created_at,
+        time,
+        link: None,
+    }])
+}
+
+// "10.00 & 10.40am" means both are am.
+fn times_from_list(
+    tweet: &TweetContent,
+    times: &str,
+    family: &str,
+) -> Result<Vec<(String, DateTime<FixedOffset>)>, ParseError> {
+    let captures: Vec<Captures> = TIME_LIST_RE.captures_iter(times).collect();
+    let last_period = captures
+        .last()
+        .and_then(|capture| capture.name("period"))
+        .map(|m| m.as_str().to_ascii_lowercase())
+        .unwrap_or_default();
+    captures
+        .iter()
+        .map(|capture| {
+            let hour = capture.name("hour").unwrap().as_str().parse().unwrap();
+            let minute = capture
+                .name("minute")
+                .map(|m| m.as_str())
+                .unwrap_or("00")
+                .parse()
+                .unwrap();
+            let period = match capture.name("period").unwrap().as_str() {
+                "" => last_period.clone(),
+                period => period.to_ascii_lowercase(),
+            };
+            do_time_from(tweet.created_at, hour, minute, &period).map_err(|message| ParseError {
+                family: Some(family.to_string()),
+                capture: Some("time".to_string()),
+                message,
+            })
+        })
+        .collect()
+}
+
+fn ferry_period(period: Option<&str>) -> FerryPeriod {
+    match period {
+        Some("this AM") | Some("this morning") => FerryPeriod::Morning,
+        Some("this PM") | Some("this afternoon") | Some("this evening") => FerryPeriod::Afternoon,
+        _ => FerryPeriod::Day,
+    }
+}
+
+fn ferry_period_start(
+    tweet: &TweetContent,
+    period: FerryPeriod,
+    family: &str,
+) -> Result<DateTime<FixedOffset>, ParseError> {
+    let starts = if period == FerryPeriod::Afternoon {
+        12
+    } else {
+        0
+    };
+    convert_time_to_instant(tweet.created_at, starts, 0).map_err(|message| ParseError {
+        family: Some(family.to_string()),
+        capture: Some("period".to_string()),
+        message,
+    })
+}
+
+fn unparsed_ferry_tweet(tweet: &TweetContent) -> ParseError {
+    let text = tweet.text.to_lowercase();
+    ParseError {
+        family: FERRY_FAMILY_KEYWORDS
+            .iter()
+            .find(|(keyword, _)| text.contains(keyword))
+            .map(|(_, family)| family.to_string()),
+        capture: Some("impact".to_string()),
+        message: "Unable to detect ferry impact".to_string(),
+    }
+}
+
+fn parse_ferry_tweet(tweet: &TweetContent) -> Result<Parsed, ParseError> {
+    let cause = cause_from_text(&tweet.text);
+    None.or_else(|| {
+        FERRY_DELAYED_RE.captures(&tweet.text).map(|capture| {
+            let (raw_time, time) = time_from_capture(tweet, &capture, "FERRY_DELAYED_RE")?;
+            Ok(vec![Cancellations::FerryDelayed {
+                route: capture.name("route").unwrap().as_str().into(),
+                origin: capture.name("origin").unwrap().as_str().into(),
+                destination: "".into(),
+                delay: delay_from_capture(&capture, "FERRY_DELAYED_RE")?,
+                cause: cause.clone(),
+                raw_time,
+                tweet_time: tweet.created_at,
+                time,
+                link: None,
+            }])
+        })
+    })
+    .or_else(|| {
+        FERRY_SUSPENDED_RE.captures(&tweet.text).map(|capture| {
+            let period = ferry_period(capture.name("period").map(|m| m.as_str()));
+            let time = ferry_period_start(tweet, period, "FERRY_SUSPENDED_RE")?;
+            Ok(vec![Cancellations::FerrySuspended {
+                route: capture
+                    .name("service")
+                    .or_else(|| capture.name("route"))
+                    .unwrap()
+                    .as_str()
+                    .into(),
+                period,
+                origin: "".into(),
+                destination: "".into(),
+                cause: cause.clone(),
+                tweet_time: tweet.created_at,
+                time,
+                link: None,
+            }])
+        })
+    })
+    .or_else(|| {
+        FERRY_CANCELLED_RE.captures(&tweet.text).map(|capture| {
+            let times = times_from_list(
+                tweet,
+                capture.name("times").unwrap().as_str(),
+                "FERRY_CANCELLED_RE",
+            )?;
+            Ok(times
+                .into_iter()
+                .map(|(raw_time, time)| Cancellations::FerryCancelled {
+                    route: capture.name("route").unwrap().as_str().into(),
+                    origin: capture
+                        .name("origin")
+                        .map(|m| m.as_str())
+                        .unwrap_or("")
+                        .into(),
+                    destination: "".into(),
+                    cause: cause.clone(),
+                    raw_time,
+                    tweet_time: tweet.created_at,
+                    time,
+                    link: None,
+                })
+                .collect())
+        })
+    })
+    .or_else(|| {
+        FERRY_CANCELLED_FROM_RE
+            .captures(&tweet.text)
+            .map(|capture| {
+                let mut events = vec![];
+                for sailings in FERRY_SAILINGS_FROM_RE.captures_iter(&capture["sailings"]) {
+                    let times =
+                        times_from_list(tweet, &sailings["times"], "FERRY_CANCELLED_FROM_RE")?;
+                    events.extend(times.into_iter().map(|(raw_time, time)| {
+                        Cancellations::FerryCancelled {
+                            route: capture.name("route").unwrap().as_str().into(),
+                            origin: sailings.name("origin").unwrap().as_str().into(),
+                            destination: "".into(),
+                            cause: cause.clone(),
+                            raw_time,
+                            tweet_time: tweet.created_at,
+                            time,
+                            link: None,
+                        }
+                    }));
+                }
+                Ok(events)
+            })
+    })
+    .or_else(|| {
+        FERRY_RESTRICTED_RE.captures(&tweet.text).map(|capture| {
+            let period = ferry_period(capture.name("period").map(|m| m.as_str()));
+            // "this AM departing DB @ 6.50, 7.45" doesn't need to say they're in the morning
+            let times = capture.name("times").unwrap().as_str();
+            let times = match period {
+                FerryPeriod::Morning if !times.ends_with('m') => format!("{}am", times),
+                FerryPeriod::Afternoon if !times.ends_with('m') => format!("{}pm", times),
+                _ => times.to_string(),
+            };
+            let sailings = times_from_list(tweet, &times, "FERRY_RESTRICTED_RE")?;
+            Ok(vec![Cancellations::FerryRestricted {
+                route: capture.name("route").unwrap().as_str().into(),
+                period,
+                origin: capture.name("origin").unwrap().as_str().into(),
+                destination: "".into(),
+                sailings: sailings.into_iter().map(|(raw_time, _)| raw_time).collect(),
+                cause: cause.clone(),
+                tweet_time: tweet.created_at,
+                time: ferry_period_start(tweet, period, "FERRY_RESTRICTED_RE")?,
+                link: None,
+            }])
+        })
+    })
+    .map(|events| events.map(Parsed::Events))
+    .unwrap_or_else(|| {
+        // Skipped stops and notices about the boats aren't a sailing being disrupted
+        if FERRY_NOTICE_RE.is_match(&tweet.text) {
+            Ok(Parsed::NotAnEvent)
+        } else {
+            Err(unparsed_ferry_tweet(tweet))
+        }
+    })
+}
+
+fn parse_disruption_tweet(tweet: &TweetContent) -> Cancellations {
+    let text = tweet.text.to_lowercase();
+    let kind = if text.contains("buses replac") || text.contains("buses are replacing") {
+        DisruptionKind::RailReplacement
+    } else if text.contains("detour") || text.contains("divert") || text.contains("diversion") {
+        DisruptionKind::Detour
+    } else {
+        DisruptionKind::RoadClosure
+    };
+    let (origin, destination) = match BETWEEN_RE.captures(&tweet.text) {
+        Some(capture) => (
+            place_name(capture.name("from").unwrap().as_str()),
+            place_name(capture.name("to").unwrap().as_str()),
+        ),
+        None => (
+            PLACE_PREFIX_RE
+                .captures(&tweet.text)
+                .map(|capture| capture.name("place").unwrap().as_str())
+                .filter(|place| !TRAIN_LINE_NAMES_RE.is_match(place))
+                .unwrap_or("")
+                .to_string(),
+            "".to_string(),
+        ),
+    };
+    let (window, planned) = window_from_text(&tweet.text, tweet.created_at);
+    Cancellations::NetworkDisruption {
+        kind,
+        routes: routes_from_text(&tweet.text),
+        origin: origin.as_str().into(),
+        destination: destination.as_str().into(),
+        window,
+        planned,
+        cause: cause_from_text(&tweet.text),
+        tweet_time: tweet.created_at,
+        link: None,
+    }
+}
+
+fn parse_train_tweet(tweet: &TweetContent) -> Result<Parsed, ParseError> {
+    let cause = cause_from_text(&tweet.text);
+    None.or_else(|| {
+        TRAIN_PART_CANCELLED_BETWEEN_RE
+            .captures(&tweet.text)
+            .map(|capture| {
+                let (raw_time, time) =
+                    time_from_capture(tweet, &capture, "TRAIN_PART_CANCELLED_BETWEEN_RE")?;
+                Ok(vec![Cancellations::TrainPartCancelled {
+                    line: capture.name("line").unwrap().as_str().to_string(),
+                    origin: capture.name("origin").unwrap().as_str().into(),
+                    destination: capture.name("destination").unwrap().as_str().into(),
+                    cancelled_from: capture.name("cancelled_from").unwrap().as_str().into(),
+                    cancelled_to: capture.name("cancelled_to").unwrap().as_str().into(),
+                    cause: cause.clone(),
                     raw_time,
                     tweet_time: tweet.created_at,
                     time,
+                    link: None,
                 }])
             })
     })
     .or_else(|| {
-        BUS_PART_CANCELLED_BETWEEN_RE
+        TRAIN_PART_CANCELLED_RE
             .captures(&tweet.text)
+            .or_else(|| TRAIN_TERMINATING_RE.captures(&tweet.text))
             .map(|capture| {
-                let (raw_time, time) = time_from_capture(tweet, &capture)?;
-                Ok(vec![Cancellations::BusPartCancelled {
-                    route: capture.name("bus_num").unwrap().as_str().to_string(),
-                    origin: capture.name("origin").unwrap().as_str().to_string(),
-                    destination: capture.name("destination").unwrap().as_str().to_string(),
-                    cancelled_from: capture.name("cancelled_from").unwrap().as_str().to_string(),
-                    cancelled_to: capture.name("cancelled_to").unwrap().as_str().to_string(),
+                let destination: Location = capture.name("destination").unwrap().as_str().into();
+                let (raw_time, time) =
+                    time_from_capture(tweet, &capture, "TRAIN_PART_CANCELLED_RE")?;
+                Ok(vec![Cancellations::TrainPartCancelled {
+                    line: capture.name("line").unwrap().as_str().to_string(),
+                    origin: capture.name("origin").unwrap().as_str().into(),
+                    destination: destination.clone(),
+                    cancelled_from: capture.name("cancelled_from").unwrap().as_str().into(),
+                    cancelled_to: destination,
+                    cause: cause.clone(),
                     raw_time,
                     tweet_time: tweet.created_at,
                     time,
+                    link: None,
                 }])
             })
     })
     .or_else(|| {
-        BUS_PART_CANCELLED_BETWEEN_NO_ORIGIN_RE
-            .captures(&tweet.text)
-            .map(|capture| {
-                let cancelled_from = capture.name("cancelled_from").unwrap().as_str().to_string();
-                let (raw_time, time) = time_from_capture(tweet, &capture)?;
-                Ok(vec![Cancellations::BusPartCancelled {
-                    route: capture.name("bus_num").unwrap().as_str().to_string(),
-                    origin: cancelled_from.clone(),
-                    destination: capture.name("destination").unwrap().as_str().to_string(),
-                    cancelled_from,
-                    cancelled_to: capture.name("cancelled_to").unwrap().as_str().to_string(),
-                    raw_time,
-                    tweet_time: tweet.created_at,
-                    time,
-                }])
-            })
+        TRAIN_REPLACED_RE.captures(&tweet.text).map(|capture| {
+            let origin: Location = capture.name("origin").unwrap().as_str().into();
+            let destination: Location = capture.name("destination").unwrap().as_str().into();
+            let (raw_time, time) = time_from_capture(tweet, &capture, "TRAIN_REPLACED_RE")?;
+            Ok(vec![Cancellations::TrainReplaced {
+                line: capture.name("line").unwrap().as_str().to_string(),
+                replaced_from: capture
+                    .name("replaced_from")
+                    .map(|m| m.as_str().into())
+                    .unwrap_or_else(|| origin.clone()),
+                replaced_to: capture
+                    .name("replaced_to")
+                    .map(|m| m.as_str().into())
+                    .unwrap_or_else(|| destination.clone()),
+                origin,
+                destination,
+                cause: cause.clone(),
+                raw_time,
+                tweet_time: tweet.created_at,
+                time,
+                link: None,
+            }])
+        })
     })
     .or_else(|| {
-        BUS_PART_CANCELLED_RE.captures(&tweet.text).map(|capture| {
-            let destination = capture.name("destination").unwrap().as_str().to_string();
-            let (raw_time, time) = time_from_capture(tweet, &capture)?;
-            Ok(vec![Cancellations::BusPartCancelled {
-                route: capture.name("bus_num").unwrap().as_str().to_string(),
-                origin: capture.name("origin").unwrap().as_str().to_string(),
-                destination: destination.clone(),
-                cancelled_from: capture.name("cancelled_from").unwrap().as_str().to_string(),
-                cancelled_to: destination,
+        TRAIN_HELD_RE.captures(&tweet.text).map(|capture| {
+            let (raw_time, time) = time_from_capture(tweet, &capture, "TRAIN_HELD_RE")?;
+            Ok(vec![Cancellations::TrainHeld {
+                line: capture.name("line").unwrap().as_str().to_string(),
+                origin: capture.name("origin").unwrap().as_str().into(),
+                destination: capture.name("destination").unwrap().as_str().into(),
+                held_at: capture.name("held_at").unwrap().as_str().into(),
+                cause: cause.clone(),
                 raw_time,
                 tweet_time: tweet.created_at,
                 time,
+                link: None,
             }])
         })
     })
     .or_else(|| {
-        BUS_FULL_CANCELLED_RE.captures(&tweet.text).map(|capture| {
-            let (raw_time, time) = time_from_capture(tweet, &capture)?;
-            Ok(vec![Cancellations::BusCancelled {
-                route: capture.name("bus_num").unwrap().as_str().to_string(),
-                origin: capture.name("origin").unwrap().as_str().to_string(),
-                destination: capture.name("destination").unwrap().as_str().to_string(),
+        TRAIN_RUNNING_LATE_RE
+            .captures(&tweet.text)
+            .map(|capture| (capture, "TRAIN_RUNNING_LATE_RE"))
+            .or_else(|| {
+                TRAIN_DEPARTED_LATE_RE
+                    .captures(&tweet.text)
+                    .map(|capture| (capture, "TRAIN_DEPARTED_LATE_RE"))
+            })
+            .map(|(capture, family)| {
+                let origin: Location = capture.name("origin").unwrap().as_str().into();
+                let (raw_time, time) = time_from_capture(tweet, &capture, family)?;
+                Ok(vec![Cancellations::TrainDelayed {
+                    line: capture.name("line").unwrap().as_str().to_string(),
+                    delayed_from: capture
+                        .name("delayed_from")
+                        .map(|m| m.as_str().into())
+                        .unwrap_or_else(|| origin.clone()),
+                    origin,
+                    destination: capture.name("destination").unwrap().as_str().into(),
+                    delay: delay_from_capture(&capture, family)?,
+                    cause: cause.clone(),
+                    raw_time,
+                    tweet_time: tweet.created_at,
+                    time,
+                    link: None,
+                }])
+            })
+    })
+    .or_else(|| {
+        TRAIN_CANCELLED_RE.captures(&tweet.text).map(|capture| {
+            let (raw_time, time) = time_from_capture(tweet, &capture, "TRAIN_CANCELLED_RE")?;
+            Ok(vec![Cancellations::TrainCancelled {
+                line: capture.name("line").unwrap().as_str().to_string(),
+                origin: capture.name("origin").unwrap().as_str().into(),
+                destination: capture.name("destination").unwrap().as_str().into(),
+                cause: cause.clone(),
                 raw_time,
                 tweet_time: tweet.created_at,
                 time,
+                link: None,
             }])
         })
     })
+    .map(|events| events.map(Parsed::Events))
     .unwrap_or_else(|| {
-        if tweet.text.contains("https://t.co/") || tweet.text.contains("buses cannot pass") {
-            Ok(vec![])
+        // A held train on the move without a delay is picked up by `parse_thread`
+        if TRAIN_NOTICE_RE.is_match(&tweet.text)
+            || (ON_THE_MOVE_RE.is_match(&tweet.text) && !MINUTES_RE.is_match(&tweet.text))
+        {
+            Ok(Parsed::NotAnEvent)
         } else {
-            Err("Unable to detect bus impact".to_string())
+            Err(unparsed_train_tweet(tweet))
         }
     })
 }
 
-lazy_static! {
-    static ref IGNORED_TWEET_IDS: HashSet<u64> = {
-        let mut h = HashSet::new();
-        h.extend(&[
-            1313942434309525504,
-            1314353142742482944,
-            1314733655579922435,
-            1314737174181367812,
-            1314737926467579907,
-            1315364812663083010,
-            1318041702188216321,
-            1318391755377496065,
-            1319510878396370946,
-            1322661891441745921,
-            1325978495604830208,
-            1330659892303052801,
-            1331688367751237632,
-            1332174572502716417,
-            1333449475386294272,
-            1334628241722626050,
-            1335667339166167046,
-            1335745856847286275,
-            1337504190365462528,
-            1339338275341791233,
-            1339766601579548672,
-            1340450608818601984,
-            1346375145888206849,
-            1346657252422270976,
-            1346992964510248961,
-            1348859010426892290,
-            1349203333723033603,
-            1350225276978909184,
-            1351244750372921345,
-            1353179739381395456,
-            1354564608745381890,
-            1354966519957000195,
-            1354966526365892612,
-            1356690879805681664,
-            1356836623581806593,
-            1357189987502944257,1365177291186204675, 
-            1357200754935758849,
-            1358956553324228610,
-            1360050266813329410,
-            1360053285831364608,1365393717859786752,1362453135260672002,
-            1360870398640807937,
-            1362241741579898883,
-            1362497545927159809,
-            1363612979686645761,
-            1363667353708359686,
-            1364304051546939396,1365394465016344581,
-            1365055494612779015,
-        ]);
-        h
-    };
+/// The t.co link in a tweet, which usually goes to the full notice on the Metlink site.
+fn link_from_text(text: &str) -> Option<String> {
+    LINK_RE.find(text).map(|link| link.as_str().to_string())
 }
 
-pub fn parse_tweet(tweet: &TweetContent) -> Result<Vec<Cancellations>, String> {
-    if IGNORED_TWEET_IDS.contains(&tweet.id) {
-        Ok(vec![])
-    } else if tweet.text.contains("https://t.co/") {
-        Ok(vec![])
-    } else if TRAIN_LINE_NAMES_RE.is_match_at(&tweet.text, 0) || tweet.text.starts_with("Trains") {
-        // Don't care about trains
-        Ok(vec![])
-    } else if tweet.text.starts_with("Ferry WHF:") {
+/// A held train that's started moving again, but without saying how late it is.
+fn moved_on(held: &Cancellations, tweet: &TweetContent) -> Option<Cancellations> {
+    let capture = ON_THE_MOVE_RE.captures(&tweet.text)?;
+    match held {
+        Cancellations::TrainHeld {
+            line,
+            origin,
+            destination,
+            held_at,
+            cause,
+            raw_time,
+            time,
+            ..
+        } => Some(Cancellations::TrainDelayed {
+            line: line.clone(),
+            origin: origin.clone(),
+            destination: destination.clone(),
+            delayed_from: capture
+                .name("moved_from")
+                .map(|m| m.as_str().into())
+                .unwrap_or_else(|| held_at.clone()),
+            delay: Delay::Unknown { reason: None },
+            cause: cause.clone(),
+            raw_time: raw_time.clone(),
+            tweet_time: tweet.created_at,
+            time: *time,
+            link: None,
+        }),
+        _ => None,
+    }
+}
+
+/// Parses a reply chain from `TweetCache::threads`, with one result per tweet. Follow-ups tend to
+///  leave out what the tweet before them said, so they take its cause if they're about the same
+///  service, and an "on the move" reply to a held train is a delay from where it was held.
+pub fn parse_thread<F>(
+    thread: &[&TweetContent],
+    parse: F,
+) -> Vec<Result<Vec<Cancellations>, ParseError>>
+where
+    F: Fn(&TweetContent) -> Result<Vec<Cancellations>, ParseError>,
+{
+    let mut previous: Option<Cancellations> = None;
+    thread
+        .iter()
+        .map(|tweet| {
+            let parsed = parse(tweet).map(|events| match &previous {
+                Some(held @ Cancellations::TrainHeld { .. }) if events.is_empty() => {
+                    moved_on(held, tweet).into_iter().collect()
+                }
+                Some(previous) => events
+                    .into_iter()
+                    .map(|mut event| {
+                        if event.cause().is_none()
+                            && event.route() == previous.route()
+                            && event.time() == previous.time()
+                        {
+                            *event.cause_mut() = previous.cause().cloned();
+                        }
+                        event
+                    })
+                    .collect(),
+                None => events,
+            });
+            if let Some(last) = parsed.as_ref().ok().and_then(|events| events.last()) {
+                previous = Some(last.clone());
+            }
+            parsed
+        })
+        .collect()
+}
+
+pub fn parse_tweet(tweet: &TweetContent) -> Result<Vec<Cancellations>, ParseError> {
+    if let Some(expanded_text) = &tweet.expanded_text {
+        parse_tweet(&TweetContent {
+            text: expanded_text.clone(),
+            expanded_text: None,
+            ..tweet.clone()
+        })
+    } else if LINK_RE.is_match(&tweet.text) {
+        let unlinked = TweetContent {
+            text: LINK_RE.replace_all(&tweet.text, "").trim_end().to_string(),
+            ..tweet.clone()
+        };
+        // Most linked tweets are general notices. They're only worth reporting if they got far
+        //  enough into a regex to say what's missing.
+        let events =
+            parse_tweet(&unlinked).or_else(|error| match (&error.family, &error.capture) {
+                (None, None) => Ok(vec![]),
+                _ => Err(error),
+            })?;
+        Ok(events
+            .into_iter()
+            .map(|mut event| {
+                *event.link_mut() = link_from_text(&tweet.text);
+                event
+            })
+            .collect())
+    } else if CORRECTION_RE.is_match(&tweet.text) {
+        let corrected = TweetContent {
+            text: CORRECTION_RE.replace(&tweet.text, "").to_string(),
+            ..tweet.clone()
+        };
+        Ok(parse_tweet(&corrected)?
+            .into_iter()
+            .map(|event| Cancellations::Correction {
+                event: Box::new(event),
+            })
+            .collect())
+    } else if DISRUPTION_RE.is_match(&tweet.text)
+        && !SINGLE_SERVICE_RE.is_match(&tweet.text)
+        && !BUS_DEPARTURE_PREFIX_RE.is_match(&tweet.text)
+    {
+        Ok(vec![parse_disruption_tweet(tweet)])
+    } else if TRAIN_LINE_NAMES_RE.is_match_at(&tweet.text, 0) {
+        Ok(parse_train_tweet(tweet)?.events())
+    } else if tweet.text.starts_with("Ferry") {
+        Ok(parse_ferry_tweet(tweet)?.events())
+    } else if tweet.text.starts_with("Trains") {
         Ok(vec![])
-    } else if tweet.text.starts_with("Bus") || tweet.text.starts_with("School") {
+    } else if tweet.text.starts_with("School") {
+        Ok(parse_school_tweet(tweet)?)
+    } else if tweet.text.starts_with("Bus") {
         Ok(parse_bus_tweet(tweet)?)
     } else {
-        Err("Not able to detect tweet type.".to_string())
+        Err(ParseError {
+            family: None,
+            capture: None,
+            message: "Not able to detect tweet type.".to_string(),
+        })
     }
 }
 
@@ -406,11 +1896,7 @@ mod test_parser {
         expected: Vec<Cancellations>,
     ) {
         let text = text.to_string();
-        let tweet = TweetContent {
-            id: 1353447509805342721,
-            created_at,
-            text: text.clone(),
-        };
+        let tweet = TweetContent::new(1353447509805342721, created_at, &text);
         assert_eq!((&text, parse_tweet(&tweet)), (&text, Ok(expected)));
     }
 
@@ -433,11 +1919,13 @@ mod test_parser {
                 &"Bus 3: Bus 3: 10:30am Wellington Station to Lyall Bay is cancelled. Please check RTI for next available bus.",
                 vec![Cancellations::BusCancelled {
                     route: "3".to_string(),
-                    origin: "Wellington Station".to_string(),
-                    destination: "Lyall Bay".to_string(),
+                    origin: "Wellington Station".into(),
+                    destination: "Lyall Bay".into(),
+                    cause: None,
                     raw_time: "10:30 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 10, 30).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 10, 30).unwrap(),
+                    link: None,
                 }]
             );
         }
@@ -449,11 +1937,13 @@ mod test_parser {
                 &"Bus 27: Bus 27: 5:23pm Kingston - Wellington Stn has been cancelled. Please check RTI for next available service.",
                 vec![Cancellations::BusCancelled {
                     route: "27".to_string(),
-                    origin: "Kingston".to_string(),
-                    destination: "Wellington Stn".to_string(),
+                    origin: "Kingston".into(),
+                    destination: "Wellington Stn".into(),
+                    cause: None,
                     raw_time: "5:23 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 17, 23).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 17, 23).unwrap(),
+                    link: None,
                 }]
             );
         }
@@ -465,11 +1955,13 @@ mod test_parser {
                 &"Bus 3: Bus 3: 6pm Tirangi Road to Wellington Station has been cancelled.",
                 vec![Cancellations::BusCancelled {
                     route: "3".to_string(),
-                    origin: "Tirangi Road".to_string(),
-                    destination: "Wellington Station".to_string(),
+                    origin: "Tirangi Road".into(),
+                    destination: "Wellington Station".into(),
+                    cause: None,
                     raw_time: "6:00 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
                     time: convert_time_to_instant(*SAMPLE_TIME, 18, 00).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -481,11 +1973,13 @@ mod test_parser {
                 &"Bus 83: Bus 83: 8:35pm Courtenay Place tp Eastbourne is cancelled. Check RTI for next service.",
                 vec![Cancellations::BusCancelled {
                     route: "83".to_string(),
-                    origin: "Courtenay Place".to_string(),
-                    destination: "Eastbourne".to_string(),
+                    origin: "Courtenay Place".into(),
+                    destination: "Eastbourne".into(),
+                    cause: None,
                     raw_time: "8:35 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 20, 35).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 20, 35).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -498,11 +1992,13 @@ mod test_parser {
                 &"Bus 14: Bus 14: 1:30pm Wilton to Kilbirnie was cancelled. Please check RTI for next available bus.",
                 vec![Cancellations::BusCancelled {
                     route: "14".to_string(),
-                    origin: "Wilton".to_string(),
-                    destination: "Kilbirnie".to_string(),
+                    origin: "Wilton".into(),
+                    destination: "Kilbirnie".into(),
+                    cause: None,
                     raw_time: "1:30 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 13, 30).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 13, 30).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -515,11 +2011,13 @@ mod test_parser {
                 &"Bus 3: Bus 3: 3:40pm Tirangi Road to Wellington Station cancelled. Please check RTI for next available bus.",
                 vec![Cancellations::BusCancelled {
                     route: "3".to_string(),
-                    origin: "Tirangi Road".to_string(),
-                    destination: "Wellington Station".to_string(),
+                    origin: "Tirangi Road".into(),
+                    destination: "Wellington Station".into(),
+                    cause: None,
                     raw_time: "3:40 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 40).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 40).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -532,11 +2030,13 @@ mod test_parser {
                 &"Bus 12: Bus 12: 12:48pm Strathmore to Kilbirnie  is cancelled. Please check RTI for next service.",
                 vec![Cancellations::BusCancelled {
                     route: "12".to_string(),
-                    origin: "Strathmore".to_string(),
-                    destination: "Kilbirnie".to_string(),
+                    origin: "Strathmore".into(),
+                    destination: "Kilbirnie".into(),
+                    cause: None,
                     raw_time: "12:48 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 12, 48).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 12, 48).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -552,11 +2052,13 @@ mod test_parser {
                 &"Bus 3: Bus 3: 5:10 Lyall Bay - Wellington Stn has been cancelled. Please check RTI for next available service.",
                 vec![Cancellations::BusCancelled {
                     route: "3".to_string(),
-                    origin: "Lyall Bay".to_string(),
-                    destination: "Wellington Stn".to_string(),
+                    origin: "Lyall Bay".into(),
+                    destination: "Wellington Stn".into(),
+                    cause: None,
                     raw_time: "5:10 pm".to_string(),
                     tweet_time,
-                    time: convert_time_to_instant(tweet_time, 17, 10).unwrap()
+                    time: convert_time_to_instant(tweet_time, 17, 10).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -569,11 +2071,13 @@ mod test_parser {
                 &"Bus 17: Bua 17: 8:20pm Wellington Station to Kowhai Park is cancelled. Please check RTI for next service.",
                 vec![Cancellations::BusCancelled {
                     route: "17".to_string(),
-                    origin: "Wellington Station".to_string(),
-                    destination: "Kowhai Park".to_string(),
+                    origin: "Wellington Station".into(),
+                    destination: "Kowhai Park".into(),
+                    cause: None,
                     raw_time: "8:20 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 20, 20).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 20, 20).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -586,11 +2090,13 @@ mod test_parser {
                 &"Bus 22: Bus 22 8pm: Wellington Station to Mairangi is cancelled. Please check RTI for next service.",
                 vec![Cancellations::BusCancelled {
                     route: "22".to_string(),
-                    origin: "Wellington Station".to_string(),
-                    destination: "Mairangi".to_string(),
+                    origin: "Wellington Station".into(),
+                    destination: "Mairangi".into(),
+                    cause: None,
                     raw_time: "8:00 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 20, 00).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 20, 00).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -602,11 +2108,13 @@ mod test_parser {
                 &"Bus 3: Bus 3 3:50pm Wellington Station to Tirangi Road is cancelled. Check RTI to find next available bus",
                 vec![Cancellations::BusCancelled {
                     route: "3".to_string(),
-                    origin: "Wellington Station".to_string(),
-                    destination: "Tirangi Road".to_string(),
+                    origin: "Wellington Station".into(),
+                    destination: "Tirangi Road".into(),
+                    cause: None,
                     raw_time: "3:50 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 50).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 50).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -619,11 +2127,13 @@ mod test_parser {
                 &"Bus 2: Bus 2 3:57pm Miramar to Karori is cancelled. Check RTI to find next available bus",
                 vec![Cancellations::BusCancelled {
                     route: "2".to_string(),
-                    origin: "Miramar".to_string(),
-                    destination: "Karori".to_string(),
+                    origin: "Miramar".into(),
+                    destination: "Karori".into(),
+                    cause: None,
                     raw_time: "3:57 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 57).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 57).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -635,11 +2145,13 @@ mod test_parser {
                 &"Bus 3: Bus 3 3:50pm Wellington Station to Tirangi Road is cancelled. Check RTI to find next available bus",
                 vec![Cancellations:: BusCancelled {
                     route: "3".to_string(),
-                    origin: "Wellington Station".to_string(),
-                    destination: "Tirangi Road".to_string(),
+                    origin: "Wellington Station".into(),
+                    destination: "Tirangi Road".into(),
+                    cause: None,
                     raw_time: "3:50 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 50).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 50).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -652,11 +2164,13 @@ mod test_parser {
                 &"Bus 1: 6:36pm Grenada Village to Island Bay is cancelled. Check RTI to find next available bus",
                 vec![Cancellations:: BusCancelled {
                     route: "1".to_string(),
-                    origin: "Grenada Village".to_string(),
-                    destination: "Island Bay".to_string(),
+                    origin: "Grenada Village".into(),
+                    destination: "Island Bay".into(),
+                    cause: None,
                     raw_time: "6:36 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 18, 36).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 18, 36).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -669,11 +2183,13 @@ mod test_parser {
                 &"Bus 27: Bus 27: 5:23pm Kingston - Wellington Stn has been REINSTATED.",
                 vec![Cancellations::BusReinstated {
                     route: "27".to_string(),
-                    origin: "Kingston".to_string(),
-                    destination: "Wellington Stn".to_string(),
+                    origin: "Kingston".into(),
+                    destination: "Wellington Stn".into(),
+                    cause: None,
                     raw_time: "5:23 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
                     time: convert_time_to_instant(*SAMPLE_TIME, 17, 23).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -684,11 +2200,13 @@ mod test_parser {
                 &"Bus 14: Bus 14: 7:43am Kilbirnie to Wilton has been reinstated and will now run.",
                 vec![Cancellations::BusReinstated {
                     route: "14".to_string(),
-                    origin: "Kilbirnie".to_string(),
-                    destination: "Wilton".to_string(),
+                    origin: "Kilbirnie".into(),
+                    destination: "Wilton".into(),
+                    cause: None,
                     raw_time: "7:43 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
                     time: convert_time_to_instant(*SAMPLE_TIME, 7, 43).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -698,11 +2216,13 @@ mod test_parser {
                 &"Bus 1: Bus 1: 8:13am Churton Park to Island Bay that was cancelled will now run.",
                 vec![Cancellations::BusReinstated {
                     route: "1".to_string(),
-                    origin: "Churton Park".to_string(),
-                    destination: "Island Bay".to_string(),
+                    origin: "Churton Park".into(),
+                    destination: "Island Bay".into(),
+                    cause: None,
                     raw_time: "8:13 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
                     time: convert_time_to_instant(*SAMPLE_TIME, 8, 13).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -714,11 +2234,13 @@ mod test_parser {
                 &"Bus 160: Bus 160: 9:00am from Lower Hutt to Wainuiomata is REINSTATED.",
                 vec![Cancellations::BusReinstated {
                     route: "160".to_string(),
-                    origin: "Lower Hutt".to_string(),
-                    destination: "Wainuiomata".to_string(),
+                    origin: "Lower Hutt".into(),
+                    destination: "Wainuiomata".into(),
+                    cause: None,
                     raw_time: "9:00 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
                     time: convert_time_to_instant(*SAMPLE_TIME, 9, 00).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -731,11 +2253,13 @@ mod test_parser {
                 &"Bus 160: Bus 160: 8:23am from Wainuiomata to Lower Hutt is reinstated.",
                 vec![Cancellations::BusReinstated {
                     route: "160".to_string(),
-                    origin: "Wainuiomata".to_string(),
-                    destination: "Lower Hutt".to_string(),
+                    origin: "Wainuiomata".into(),
+                    destination: "Lower Hutt".into(),
+                    cause: None,
                     raw_time: "8:23 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
                     time: convert_time_to_instant(*SAMPLE_TIME, 8, 23).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -748,12 +2272,14 @@ mod test_parser {
                 &"Bus 29: Bus 29: 7:00pm Brooklyn to Wellington Station is delayed by 20 minutes. Please check RTI for next available bus.",
                 vec![Cancellations::BusDelayed {
                     route: "29".to_string(),
-                    origin: "Brooklyn".to_string(),
-                    destination: "Wellington Station".to_string(),
-                    delay_minutes: "20".to_string(),
+                    origin: "Brooklyn".into(),
+                    destination: "Wellington Station".into(),
+                    delay: Delay::Minutes(20),
+                    cause: None,
                     raw_time: "7:00 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 19, 00).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 19, 00).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -765,12 +2291,14 @@ mod test_parser {
                 &"Bus 17: Bus 17: 5:03pm Wellington Station - Kowhai Park has been delayed 20 minutes due to mechanical issues. Please check RTI for updates.",
                 vec![Cancellations::BusDelayed {
                     route: "17".to_string(),
-                    origin: "Wellington Station".to_string(),
-                    destination: "Kowhai Park".to_string(),
-                    delay_minutes: "20".to_string(),
+                    origin: "Wellington Station".into(),
+                    destination: "Kowhai Park".into(),
+                    delay: Delay::Minutes(20),
+                    cause: Some("mechanical issues".into()),
                     raw_time: "5:03 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 17, 03).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 17, 03).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -783,12 +2311,14 @@ mod test_parser {
                 &"Bus 220: Bus 220: 7:10am Titahi Bay to Ascot Park will be delayed 20 minutes. Sorry for the inconvenience!",
                 vec![Cancellations::BusDelayed {
                     route: "220".to_string(),
-                    origin: "Titahi Bay".to_string(),
-                    destination: "Ascot Park".to_string(),
-                    delay_minutes: "20".to_string(),
+                    origin: "Titahi Bay".into(),
+                    destination: "Ascot Park".into(),
+                    delay: Delay::Minutes(20),
+                    cause: None,
                     raw_time: "7:10 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 7, 10).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 7, 10).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -801,12 +2331,14 @@ mod test_parser {
                 &"Bus 130: Bus 130: 11:00am from Naenae to Petone is delayed 15-20 minutes due to mechanical issues. Check RTI for updates.",
                 vec![Cancellations::BusDelayed {
                     route: "130".to_string(),
-                    origin: "Naenae".to_string(),
-                    destination: "Petone".to_string(),
-                    delay_minutes: "20".to_string(),
+                    origin: "Naenae".into(),
+                    destination: "Petone".into(),
+                    delay: Delay::Range { min: 15, max: 20 },
+                    cause: Some("mechanical issues".into()),
                     raw_time: "11:00 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 11, 00).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 11, 00).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -819,12 +2351,16 @@ mod test_parser {
                 &"Bus 130: Bus 130: 10:15am from Petone to Naenae is delayed due to vehicle breakdown. Check RTI for updates.",
                 vec![Cancellations::BusDelayed {
                     route: "130".to_string(),
-                    origin: "Petone".to_string(),
-                    destination: "Naenae".to_string(),
-                    delay_minutes: "".to_string(),
+                    origin: "Petone".into(),
+                    destination: "Naenae".into(),
+                    delay: Delay::Unknown {
+                        reason: Some("vehicle breakdown".to_string()),
+                    },
+                    cause: Some("vehicle breakdown".into()),
                     raw_time: "10:15 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 10, 15).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 10, 15).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -837,12 +2373,14 @@ mod test_parser {
                 &"Bus 25: Bus 25: 7:05am Khandallah to Highbury will run 15 minutes late.",
                 vec![Cancellations::BusDelayed {
                     route: "25".to_string(),
-                    origin: "Khandallah".to_string(),
-                    destination: "Highbury".to_string(),
-                    delay_minutes: "15".to_string(),
+                    origin: "Khandallah".into(),
+                    destination: "Highbury".into(),
+                    delay: Delay::Minutes(15),
+                    cause: None,
                     raw_time: "7:05 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
                     time: convert_time_to_instant(*SAMPLE_TIME, 7, 05).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -855,12 +2393,14 @@ mod test_parser {
                 &"Bus 220: 1.14pm Ascot Park to Titahi Bay is delayed. Please check RTI for updates on this service.",
                 vec![Cancellations::BusDelayed {
                     route: "220".to_string(),
-                    origin: "Ascot Park".to_string(),
-                    destination: "Titahi Bay".to_string(),
-                    delay_minutes: "".to_string(),
+                    origin: "Ascot Park".into(),
+                    destination: "Titahi Bay".into(),
+                    delay: Delay::Unknown { reason: None },
+                    cause: None,
                     raw_time: "1:14 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
                     time: convert_time_to_instant(*SAMPLE_TIME, 13, 14).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -873,12 +2413,219 @@ mod test_parser {
                 &"Bus 24: Bus 24: 9:10am Miramar - Johnsonville has been delayed due to road block. Please check RTI for updates.",
                 vec![Cancellations::BusDelayed {
                     route: "24".to_string(),
-                    origin: "Miramar".to_string(),
-                    destination: "Johnsonville".to_string(),
-                    delay_minutes: "".to_string(),
+                    origin: "Miramar".into(),
+                    destination: "Johnsonville".into(),
+                    delay: Delay::Unknown {
+                        reason: Some("road block".to_string()),
+                    },
+                    cause: Some("road block".into()),
                     raw_time: "9:10 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
                     time: convert_time_to_instant(*SAMPLE_TIME, 9, 10).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_cancelled_school_bus() {
+            println!("{}", SCHOOL_BUS_RE.as_str());
+
+            parse_tweet_str(
+                &"School 313: Bus 313: 7:48am Greytown to Wairarapa College is cancelled.",
+                vec![Cancellations::SchoolBus {
+                    route: "313".to_string(),
+                    school: "Wairarapa College".to_string(),
+                    run: SchoolRun::Morning,
+                    impact: SchoolBusImpact::Cancelled,
+                    origin: "Greytown".into(),
+                    destination: "Wairarapa College".into(),
+                    cause: None,
+                    raw_time: "7:48 am".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 7, 48).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_delayed_school_bus() {
+            parse_tweet_str(
+                &"School 911: 3:17pm St Orans College to Upper Hutt is delayed 20 minutes due to unplanned roadworks.",
+                vec![Cancellations::SchoolBus {
+                    route: "911".to_string(),
+                    school: "St Orans College".to_string(),
+                    run: SchoolRun::Afternoon,
+                    impact: SchoolBusImpact::Delayed(Delay::Minutes(20)),
+                    origin: "St Orans College".into(),
+                    destination: "Upper Hutt".into(),
+                    cause: Some("unplanned roadworks".into()),
+                    raw_time: "3:17 pm".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 17).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_delayed_ferry() {
+            println!("{}", FERRY_DELAYED_RE.as_str());
+            let tweet_time = DateTime::parse_from_rfc3339("2021-01-17T18:00:21Z")
+                .unwrap()
+                .with_timezone(&Utc);
+
+            parse_tweet_time_str(
+                tweet_time,
+                &"Ferry WHF: Due to an unexpected issue, the 7.15am departure from Days Bay will be running approx 10 mins late. Apologies for any inconven...",
+                vec![Cancellations::FerryDelayed {
+                    route: "WHF".into(),
+                    origin: "Days Bay".into(),
+                    destination: "".into(),
+                    delay: Delay::Minutes(10),
+                    cause: Some("unexpected issue".into()),
+                    raw_time: "7:15 am".to_string(),
+                    tweet_time,
+                    time: convert_time_to_instant(tweet_time, 7, 15).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_cancelled_ferry() {
+            println!("{}", FERRY_CANCELLED_RE.as_str());
+            let tweet_time = DateTime::parse_from_rfc3339("2021-01-19T21:03:07Z")
+                .unwrap()
+                .with_timezone(&Utc);
+            let cancelled = |raw_time: &str, hour, minute| Cancellations::FerryCancelled {
+                route: "WHF".into(),
+                origin: "".into(),
+                destination: "".into(),
+                cause: Some("mechanical issue".into()),
+                raw_time: raw_time.to_string(),
+                tweet_time,
+                time: convert_time_to_instant(tweet_time, hour, minute).unwrap(),
+                link: None,
+            };
+
+            parse_tweet_time_str(
+                tweet_time,
+                &"Ferry WHF: Harbour ferry 10.00 &amp; 10.40am sailings cancelled today (20 January) due to a mechanical issue. Updates to follow about PM sail...",
+                vec![cancelled("10:00 am", 10, 0), cancelled("10:40 am", 10, 40)],
+            );
+
+            let tweet_time = DateTime::parse_from_rfc3339("2021-02-15T20:31:09Z")
+                .unwrap()
+                .with_timezone(&Utc);
+            let cancelled_from =
+                |origin: &str, raw_time: &str, hour, minute| Cancellations::FerryCancelled {
+                    route: "WHF".into(),
+                    origin: origin.into(),
+                    destination: "".into(),
+                    cause: Some("adverse conditions".into()),
+                    raw_time: raw_time.to_string(),
+                    tweet_time,
+                    time: convert_time_to_instant(tweet_time, hour, minute).unwrap(),
+                    link: None,
+                };
+            parse_tweet_time_str(
+                tweet_time,
+                &"Ferry WHF: Due to adverse conditions, today's 10am/12pm sailings from QW &amp; 10.40am/12.45pm from Days Bay have been cancelled on Harbour F...",
+                vec![
+                    cancelled_from("QW", "10:00 am", 10, 0),
+                    cancelled_from("QW", "12:00 pm", 12, 0),
+                    cancelled_from("Days Bay", "10:40 am", 10, 40),
+                    cancelled_from("Days Bay", "12:45 pm", 12, 45),
+                ],
+            );
+        }
+
+        #[test]
+        fn test_restricted_ferry() {
+            let tweet_time = DateTime::parse_from_rfc3339("2021-02-15T16:56:04Z")
+                .unwrap()
+                .with_timezone(&Utc);
+            parse_tweet_time_str(
+                tweet_time,
+                &"Ferry WHF: Due to adverse weather conditions, Harbour Ferry will run to restricted timetable this AM departing Days Bay @ 6.50, 7.45, 8.4...",
+                vec![Cancellations::FerryRestricted {
+                    route: "WHF".into(),
+                    period: FerryPeriod::Morning,
+                    origin: "Days Bay".into(),
+                    destination: "".into(),
+                    sailings: vec!["6:50 am".to_string(), "7:45 am".to_string()],
+                    cause: Some("adverse weather conditions".into()),
+                    tweet_time,
+                    time: convert_time_to_instant(tweet_time, 0, 0).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_suspended_ferry() {
+            println!("{}", FERRY_SUSPENDED_RE.as_str());
+            let tweet_time = DateTime::parse_from_rfc3339("2021-01-23T21:30:11Z")
+                .unwrap()
+                .with_timezone(&Utc);
+
+            parse_tweet_time_str(
+                tweet_time,
+                &"Ferry WHF: Harbour Explorer Ferry is cancelled today due to forecasted weather conditions. All other scheduled services will run as usual.",
+                vec![Cancellations::FerrySuspended {
+                    route: "Harbour Explorer".into(),
+                    period: FerryPeriod::Day,
+                    origin: "".into(),
+                    destination: "".into(),
+                    cause: Some("forecasted weather conditions".into()),
+                    tweet_time,
+                    time: convert_time_to_instant(tweet_time, 0, 0).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_ferry_notice() {
+            parse_tweet_str(
+                &"Ferry WHF: Reminder: Face coverings still required on Wellington Harbour Ferry until further notice.",
+                vec![],
+            );
+            let tweet = TweetContent::new(
+                0,
+                *SAMPLE_TIME,
+                "Ferry WHF: Due to a mechanical issue, sailings from Days Bay will be replaced by shuttles",
+            );
+            assert_eq!(
+                Some("impact".to_string()),
+                parse_tweet(&tweet).unwrap_err().capture
+            );
+        }
+
+        #[test]
+        fn test_network_disruption() {
+            let tweet_time = DateTime::parse_from_rfc3339("2021-01-28T03:40:02Z")
+                .unwrap()
+                .with_timezone(&Utc);
+
+            parse_tweet_time_str(
+                tweet_time,
+                &"Bus 25: Highbury: Aro Street and Durham Street, buses cannot pass due to burst watermain.",
+                vec![Cancellations::NetworkDisruption {
+                    kind: DisruptionKind::RoadClosure,
+                    routes: vec!["25".to_string()],
+                    origin: "Highbury".into(),
+                    destination: "".into(),
+                    window: DisruptionWindow {
+                        start: tweet_time.into(),
+                        end: None,
+                    },
+                    planned: false,
+                    cause: Some("burst watermain".into()),
+                    tweet_time,
+                    link: None,
                 }],
             );
         }
@@ -891,13 +2638,15 @@ mod test_parser {
                 &"Bus 2: Bus 2: 6:15pm Karori - Miramar has been part cancelled from Rongotai. Please check RTI for next available service.",
                 vec![Cancellations::BusPartCancelled {
                     route: "2".to_string(),
-                    origin: "Karori".to_string(),
-                    destination: "Miramar".to_string(),
-                    cancelled_from: "Rongotai".to_string(),
-                    cancelled_to: "Miramar".to_string(),
+                    origin: "Karori".into(),
+                    destination: "Miramar".into(),
+                    cancelled_from: "Rongotai".into(),
+                    cancelled_to: "Miramar".into(),
+                    cause: None,
                     raw_time: "6:15 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 18, 15).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 18, 15).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -910,13 +2659,15 @@ mod test_parser {
                 &"Bus 2: Bus 2: 2:50pm Karori - Miramar is part cancelled between Kilbrinie and Miramar. Please check RTI for next service.",
                 vec![Cancellations::BusPartCancelled {
                     route: "2".to_string(),
-                    origin: "Karori".to_string(),
-                    destination: "Miramar".to_string(),
-                    cancelled_from: "Kilbrinie".to_string(),
-                    cancelled_to: "Miramar".to_string(),
+                    origin: "Karori".into(),
+                    destination: "Miramar".into(),
+                    cancelled_from: "Kilbrinie".into(),
+                    cancelled_to: "Miramar".into(),
+                    cause: None,
                     raw_time: "2:50 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 14, 50).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 14, 50).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -928,13 +2679,15 @@ mod test_parser {
                 &"Bus 29: Bus 29: 6.20pm from Wellington Stn to Island Bay is part-cancelled between Wgtn Stn and Courtenay Pl. Check RTI to find next service.",
                 vec![Cancellations::BusPartCancelled {
                     route: "29".to_string(),
-                    origin: "Wellington Stn".to_string(),
-                    destination: "Island Bay".to_string(),
-                    cancelled_from: "Wgtn Stn".to_string(),
-                    cancelled_to: "Courtenay Pl".to_string(),
+                    origin: "Wellington Stn".into(),
+                    destination: "Island Bay".into(),
+                    cancelled_from: "Wgtn Stn".into(),
+                    cancelled_to: "Courtenay Pl".into(),
+                    cause: None,
                     raw_time: "6:20 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 18, 20).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 18, 20).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -946,13 +2699,15 @@ mod test_parser {
                 &"Bus 220: Bus 220: 9:11am Titahi Bay to Ascot Park will be part cancelled between Titahi Bay and Porirua Station. Please check RTI",
                 vec![Cancellations::BusPartCancelled {
                     route: "220".to_string(),
-                    origin: "Titahi Bay".to_string(),
-                    destination: "Ascot Park".to_string(),
-                    cancelled_from: "Titahi Bay".to_string(),
-                    cancelled_to: "Porirua Station".to_string(),
+                    origin: "Titahi Bay".into(),
+                    destination: "Ascot Park".into(),
+                    cancelled_from: "Titahi Bay".into(),
+                    cancelled_to: "Porirua Station".into(),
+                    cause: None,
                     raw_time: "9:11 am".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 9, 11).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 9, 11).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -964,13 +2719,15 @@ mod test_parser {
                 &"Bus 110: Bus 110: 5pm Emerald Hill to Petone. Is part-cancelled from Emerald hill to Upper Hutt.",
                 vec![Cancellations::BusPartCancelled {
                     route: "110".to_string(),
-                    origin: "Emerald Hill".to_string(),
-                    destination: "Petone".to_string(),
-                    cancelled_from: "Emerald hill".to_string(),
-                    cancelled_to: "Upper Hutt".to_string(),
+                    origin: "Emerald Hill".into(),
+                    destination: "Petone".into(),
+                    cancelled_from: "Emerald hill".into(),
+                    cancelled_to: "Upper Hutt".into(),
+                    cause: None,
                     raw_time: "5:00 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 17, 00).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 17, 00).unwrap(),
+                    link: None,
                 }],
             );
         }
@@ -982,15 +2739,387 @@ mod test_parser {
                 & "Bus 2: Bus 2: 6:46pm Seatoun Park to Kilbirnie part-cancelled from Seatount to Rongotai Rd.",
                 vec![Cancellations::BusPartCancelled {
                     route: "2".to_string(),
-                    origin: "Seatoun Park".to_string(),
-                    destination: "Kilbirnie".to_string(),
-                    cancelled_from: "Seatount".to_string(),
-                    cancelled_to: "Rongotai Rd".to_string(),
+                    origin: "Seatoun Park".into(),
+                    destination: "Kilbirnie".into(),
+                    cancelled_from: "Seatount".into(),
+                    cancelled_to: "Rongotai Rd".into(),
+                    cause: None,
                     raw_time: "6:46 pm".to_string(),
                     tweet_time: *SAMPLE_TIME,
-                    time: convert_time_to_instant(*SAMPLE_TIME, 18, 46).unwrap()
+                    time: convert_time_to_instant(*SAMPLE_TIME, 18, 46).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_cancelled_train() {
+            println!("{}", TRAIN_CANCELLED_RE.as_str());
+
+            parse_tweet_str(
+                &"HVL: The 3:29pm service from WELL to TAIT is cancelled due to staffing issues. Alt svc departs WELL at 3:49pm and 4:09pm",
+                vec![Cancellations::TrainCancelled {
+                    line: "HVL".to_string(),
+                    origin: "WELL".into(),
+                    destination: "TAIT".into(),
+                    cause: Some("staffing issues".into()),
+                    raw_time: "3:29 pm".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 29).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_part_cancelled_train() {
+            println!("{}", TRAIN_PART_CANCELLED_BETWEEN_RE.as_str());
+
+            parse_tweet_str(
+                &"KPL: The 5:41pm svc from WAIK to WELL is canx between WAIK and PARA due to an earlier signal fault on HVL, Alt svc departs WAIK at 6:05pm",
+                vec![Cancellations::TrainPartCancelled {
+                    line: "KPL".to_string(),
+                    origin: "WAIK".into(),
+                    destination: "WELL".into(),
+                    cancelled_from: "WAIK".into(),
+                    cancelled_to: "PARA".into(),
+                    cause: Some("earlier signal fault on HVL".into()),
+                    raw_time: "5:41 pm".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 17, 41).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_part_cancelled_train_alt() {
+            println!("{}", TRAIN_TERMINATING_RE.as_str());
+
+            parse_tweet_str(
+                &"KPL: KPL: The 5:18pm svc from WELL to WAIK will terminate at PARA due to the delays caused by the earlier mechanical issue",
+                vec![Cancellations::TrainPartCancelled {
+                    line: "KPL".to_string(),
+                    origin: "WELL".into(),
+                    destination: "WAIK".into(),
+                    cancelled_from: "PARA".into(),
+                    cancelled_to: "WAIK".into(),
+                    cause: Some("delays caused by the earlier mechanical issue".into()),
+                    raw_time: "5:18 pm".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 17, 18).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_delayed_train() {
+            println!("{}", TRAIN_RUNNING_LATE_RE.as_str());
+
+            parse_tweet_str(
+                &"HVL: The 5:35pm service from WELL to TAIT is running approx 15 mins late from WOBU due to a signal fault",
+                vec![Cancellations::TrainDelayed {
+                    line: "HVL".to_string(),
+                    origin: "WELL".into(),
+                    destination: "TAIT".into(),
+                    delayed_from: "WOBU".into(),
+                    delay: Delay::Minutes(15),
+                    cause: Some("signal fault".into()),
+                    raw_time: "5:35 pm".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 17, 35).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_delayed_train_alt() {
+            println!("{}", TRAIN_DEPARTED_LATE_RE.as_str());
+
+            parse_tweet_str(
+                &"WRL: The 12:45pm svc from WELL to MAST has departed 33 mins late due to an issue with the loco engine, apologies for the inconvenience",
+                vec![Cancellations::TrainDelayed {
+                    line: "WRL".to_string(),
+                    origin: "WELL".into(),
+                    destination: "MAST".into(),
+                    delayed_from: "WELL".into(),
+                    delay: Delay::Minutes(33),
+                    cause: Some("issue with the loco engine".into()),
+                    raw_time: "12:45 pm".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 12, 45).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_held_train() {
+            println!("{}", TRAIN_HELD_RE.as_str());
+
+            parse_tweet_str(
+                &"WRL: WRL: The 3:38pm svc from MAST to WELL is currently held at FEAT due to a worksite, update to follow",
+                vec![Cancellations::TrainHeld {
+                    line: "WRL".to_string(),
+                    origin: "MAST".into(),
+                    destination: "WELL".into(),
+                    held_at: "FEAT".into(),
+                    cause: Some("worksite".into()),
+                    raw_time: "3:38 pm".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 38).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_replaced_train() {
+            println!("{}", TRAIN_REPLACED_RE.as_str());
+
+            parse_tweet_str(
+                &"HVL: HVL: The 7:00pm svc from UPPE to WELL is replaced by bus between UPPE and TAIT, from TAIT to WELL is a train svc due to the track issue",
+                vec![Cancellations::TrainReplaced {
+                    line: "HVL".to_string(),
+                    origin: "UPPE".into(),
+                    destination: "WELL".into(),
+                    replaced_from: "UPPE".into(),
+                    replaced_to: "TAIT".into(),
+                    cause: Some("track issue".into()),
+                    raw_time: "7:00 pm".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 19, 00).unwrap(),
+                    link: None,
+                }],
+            );
+        }
+
+        #[test]
+        fn test_correction() {
+            parse_tweet_str(
+                &"HVL: CORRECTION: The 7.30pm svc from UPPE to WELL is held at EPUN due to a tresspasser on the tracks. Updates to follow.",
+                vec![Cancellations::Correction {
+                    event: Box::new(Cancellations::TrainHeld {
+                        line: "HVL".to_string(),
+                        origin: "UPPE".into(),
+                        destination: "WELL".into(),
+                        held_at: "EPUN".into(),
+                        cause: Some("tresspasser on the tracks".into()),
+                        raw_time: "7:30 pm".to_string(),
+                        tweet_time: *SAMPLE_TIME,
+                        time: convert_time_to_instant(*SAMPLE_TIME, 19, 30).unwrap(),
+                        link: None,
+                    }),
+                }],
+            );
+        }
+
+        #[test]
+        fn test_unparsed_bus() {
+            let tweet = TweetContent::new(
+                1365394465016344581,
+                *SAMPLE_TIME,
+                "Bus 145: 9.33am Belmont to Lower Hutt",
+            );
+            let error = parse_tweet(&tweet).unwrap_err();
+            assert_eq!(None, error.family);
+            assert_eq!(None, error.capture);
+
+            let tweet = TweetContent {
+                text: "Bus 2: Seatoun Village to Karori is cancelled.".to_string(),
+                ..tweet
+            };
+            let error = parse_tweet(&tweet).unwrap_err();
+            assert_eq!(Some("BUS_FULL_CANCELLED_RE".to_string()), error.family);
+            assert_eq!(Some("hour".to_string()), error.capture);
+
+            let tweet = TweetContent {
+                text: "Bus 120: Bus 6.58pm from Lower Hutt to Stokes Valley is cancelled."
+                    .to_string(),
+                ..tweet
+            };
+            let error = parse_tweet(&tweet).unwrap_err();
+            assert_eq!(Some("hour".to_string()), error.capture);
+        }
+
+        #[test]
+        fn test_linked_tweet() {
+            let text = "WRL: The 3:38pm svc from MAST to WELL is replaced by buses between FEAT and WELL due to an issue with a freight train https://t.co/Vg6Fa60KIm";
+            parse_tweet_str(
+                &text,
+                vec![Cancellations::TrainReplaced {
+                    line: "WRL".to_string(),
+                    origin: "MAST".into(),
+                    destination: "WELL".into(),
+                    replaced_from: "FEAT".into(),
+                    replaced_to: "WELL".into(),
+                    cause: Some("issue with a freight train".into()),
+                    raw_time: "3:38 pm".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 15, 38).unwrap(),
+                    link: Some("https://t.co/Vg6Fa60KIm".to_string()),
                 }],
             );
+            parse_tweet_str(
+                &"Paraparaumu Ticket Office Closed https://t.co/KLvs995RGZ",
+                vec![],
+            );
+        }
+
+        #[test]
+        fn test_thread() {
+            let tweeted_at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+            let held = TweetContent::new(
+                1314495834554208256,
+                tweeted_at("2020-10-09T09:20:03Z"),
+                "KPL: The 9:14pm svc from WELL to WAIK is held south of PARA due to a broken down freight service. Updates to follow.",
+            );
+            let moving = TweetContent {
+                in_reply_to_status_id: Some(held.id),
+                ..TweetContent::new(
+                    1314496834554208256,
+                    tweeted_at("2020-10-09T09:24:03Z"),
+                    "KPL: The 9:14pm svc from WELL to WAIK is now on the move from PARA",
+                )
+            };
+            let late = TweetContent {
+                in_reply_to_status_id: Some(moving.id),
+                ..TweetContent::new(
+                    1314498352097103877,
+                    tweeted_at("2020-10-09T09:30:03Z"),
+                    "KPL: The 9:14pm svc from WELL to WAIK is now on the move and running 21 mins late from PARA.",
+                )
+            };
+            let parsed = parse_thread(&[&held, &moving, &late], parse_tweet);
+            let time = convert_time_to_instant(held.created_at, 21, 14).unwrap();
+            let delayed = |tweet_time, delay| Cancellations::TrainDelayed {
+                line: "KPL".to_string(),
+                origin: "WELL".into(),
+                destination: "WAIK".into(),
+                delayed_from: "PARA".into(),
+                delay,
+                cause: Some("broken down freight service".into()),
+                raw_time: "9:14 pm".to_string(),
+                tweet_time,
+                time,
+                link: None,
+            };
+            assert_eq!(
+                vec![
+                    parse_tweet(&held),
+                    Ok(vec![delayed(
+                        moving.created_at,
+                        Delay::Unknown { reason: None }
+                    )]),
+                    Ok(vec![delayed(late.created_at, Delay::Minutes(21))]),
+                ],
+                parsed
+            );
+        }
+
+        #[test]
+        fn test_expanded_tweet() {
+            let tweet = TweetContent {
+                expanded_text: Some(
+                    "Bus 3: 10:30am Wellington Station to Lyall Bay is cancelled.".to_string(),
+                ),
+                ..TweetContent::new(
+                    1353447509805342721,
+                    *SAMPLE_TIME,
+                    "Bus 3: 10:30am Wellington Station to Lyall B... https://t.co/yE6bv2YCKv",
+                )
+            };
+            assert_eq!(
+                Ok(vec![Cancellations::BusCancelled {
+                    route: "3".to_string(),
+                    origin: "Wellington Station".into(),
+                    destination: "Lyall Bay".into(),
+                    cause: None,
+                    raw_time: "10:30 am".to_string(),
+                    tweet_time: *SAMPLE_TIME,
+                    time: convert_time_to_instant(*SAMPLE_TIME, 10, 30).unwrap(),
+                    link: None,
+                }]),
+                parse_tweet(&tweet)
+            );
+        }
+
+        #[test]
+        fn test_train_notice() {
+            parse_tweet_str(
+                &"HVL: The 7:40am service from UPPE to WELL will run with 4 cars instead of the usual 6. Alt services depart UPPE at 7:16am and 8:00am.",
+                vec![],
+            );
+            parse_tweet_str(
+                &"JVL: Services are now on the move after the earlier emergency services incident. Please expect some delays.",
+                vec![],
+            );
+        }
+
+        #[test]
+        fn test_train_unparsed() {
+            let tweet = TweetContent::new(
+                0,
+                *SAMPLE_TIME,
+                "HVL: The 5:43pm svc from WELL to UPPE and the 6:30pm svc from UPPE to WELL are cancelled due to a mechanical fault. https://t.co/ZDtilBg0On",
+            );
+            assert_eq!(
+                Err(ParseError {
+                    family: Some("TRAIN_CANCELLED_RE".to_string()),
+                    capture: Some("impact".to_string()),
+                    message: "Unable to detect train impact".to_string(),
+                }),
+                parse_tweet(&tweet)
+            );
+            let tweet = TweetContent::new(
+                0,
+                *SAMPLE_TIME,
+                "HVL: Northbound services are currently suspended between Wellington and Petone due to a track fault",
+            );
+            assert_eq!(
+                Some("service".to_string()),
+                parse_tweet(&tweet).unwrap_err().capture
+            );
+        }
+
+        #[test]
+        fn test_cause_kind() {
+            assert_eq!(
+                CauseKind::LateArrivalOfPreviousService,
+                "late arrival of the previous service".into()
+            );
+            assert_eq!(CauseKind::Trespasser, "tresspasser on the tracks".into());
+            assert_eq!(CauseKind::Obstruction, "track obstruction".into());
+            assert_eq!(CauseKind::SignalFault, "signal fault".into());
+            assert_eq!(
+                CauseKind::ServiceAlert(ServiceAlertCause::Accident),
+                "traffic accident at Horokiwi".into()
+            );
+            assert_eq!(
+                CauseKind::ServiceAlert(ServiceAlertCause::TechnicalProblem),
+                "Mechanical issue".into()
+            );
+            assert_eq!(
+                CauseKind::ServiceAlert(ServiceAlertCause::OtherCause),
+                "operational process".into()
+            );
+            // Only whole words, so these are nothing to do with trees
+            assert_eq!(
+                CauseKind::ServiceAlert(ServiceAlertCause::Accident),
+                "traffic accident on Champion Street".into()
+            );
+            assert_eq!(
+                CauseKind::ServiceAlert(ServiceAlertCause::PoliceActivity),
+                "emergency services incident on Elizabeth Street".into()
+            );
+            assert_eq!(
+                CauseKind::ServiceAlert(ServiceAlertCause::OtherCause),
+                "road closure on McColl street".into()
+            );
+            assert_eq!(CauseKind::Obstruction, "a fallen tree".into());
+            assert_eq!(CauseKind::SignalFault, "signalling issue".into());
         }
     }
 }
@@ -0,0 +1,218 @@
+use crate::parser::{Cancellations, Location, SchoolBusImpact};
+use crate::resolver::normalise_name;
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TripStatus {
+    Cancelled,
+    PartCancelled,
+    Replaced,
+    Delayed,
+    Held,
+    Reinstated,
+    /// Not a single trip, see `Cancellations::NetworkDisruption` and
+    ///  `Cancellations::FerryRestricted`.
+    Disrupted,
+}
+impl From<&Cancellations> for TripStatus {
+    fn from(cancellation: &Cancellations) -> Self {
+        match cancellation {
+            Cancellations::BusCancelled { .. } => TripStatus::Cancelled,
+            Cancellations::BusPartCancelled { .. } => TripStatus::PartCancelled,
+            Cancellations::BusReinstated { .. } => TripStatus::Reinstated,
+            Cancellations::BusDelayed { .. } => TripStatus::Delayed,
+            Cancellations::TrainCancelled { .. } => TripStatus::Cancelled,
+            Cancellations::TrainPartCancelled { .. } => TripStatus::PartCancelled,
+            Cancellations::TrainDelayed { .. } => TripStatus::Delayed,
+            Cancellations::TrainHeld { .. } => TripStatus::Held,
+            Cancellations::TrainReplaced { .. } => TripStatus::Replaced,
+            Cancellations::NetworkDisruption { .. } => TripStatus::Disrupted,
+            Cancellations::FerryCancelled { .. } => TripStatus::Cancelled,
+            Cancellations::FerryDelayed { .. } => TripStatus::Delayed,
+            Cancellations::FerrySuspended { .. } => TripStatus::Cancelled,
+            Cancellations::FerryRestricted { .. } => TripStatus::Disrupted,
+            Cancellations::SchoolBus { impact, .. } => match impact {
+                SchoolBusImpact::Cancelled => TripStatus::Cancelled,
+                SchoolBusImpact::Delayed(_) => TripStatus::Delayed,
+                SchoolBusImpact::Reinstated => TripStatus::Reinstated,
+            },
+            Cancellations::Correction { event } => TripStatus::from(event.as_ref()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct StatusChange {
+    /// Index into the cancellations that were reconciled.
+    pub event: usize,
+    pub tweet_time: DateTime<Utc>,
+    pub status: TripStatus,
+    /// For a reinstatement, the earlier cancellation that it reverses.
+    pub reverses: Option<usize>,
+}
+
+/// Everything tweeted about a single trip, in the order it was tweeted.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct TripTimeline {
+    pub route: String,
+    pub origin: String,
+    pub destination: String,
+    pub time: DateTime<FixedOffset>,
+    pub changes: Vec<StatusChange>,
+}
+
+impl TripTimeline {
+    pub fn effective_status(&self) -> Option<TripStatus> {
+        self.changes.last().map(|change| change.status)
+    }
+
+    pub fn reversed(&self) -> impl Iterator<Item = usize> + '_ {
+        self.changes.iter().filter_map(|change| change.reverses)
+    }
+}
+
+// Prefer the GTFS stop so "Wgtn Stn" and "Wellington Station" are the same endpoint.
+pub fn location_key(location: &Location) -> String {
+    location
+        .stop
+        .as_ref()
+        .map(|stop| stop.id.clone())
+        .unwrap_or_else(|| normalise_name(&location.raw))
+}
+
+/// Groups a week's events by trip (route, scheduled time and endpoints), and links each
+///  reinstatement to the cancellation it reverses.
+pub fn reconcile(cancellations: &[Cancellations]) -> Vec<TripTimeline> {
+    let mut order: Vec<usize> = (0..cancellations.len()).collect();
+    order.sort_by_key(|&idx| *cancellations[idx].tweet_time());
+
+    let mut timelines: Vec<TripTimeline> = vec![];
+    let mut trips: HashMap<(String, DateTime<FixedOffset>, String, String), usize> = HashMap::new();
+    for idx in order {
+        let cancellation = &cancellations[idx];
+        // Disruptions cover a window of time, not a single trip.
+        if cancellation.window().is_some() {
+            continue;
+        }
+        let origin = location_key(cancellation.origin());
+        let destination = location_key(cancellation.destination());
+        let key = (
+            cancellation.route().to_string(),
+            *cancellation.time(),
+            origin.clone(),
+            destination.clone(),
+        );
+        let trip = *trips.entry(key).or_insert_with(|| {
+            timelines.push(TripTimeline {
+                route: cancellation.route().to_string(),
+                origin,
+                destination,
+                time: *cancellation.time(),
+                changes: vec![],
+            });
+            timelines.len() - 1
+        });
+        let timeline = &mut timelines[trip];
+
+        let status = TripStatus::from(cancellation);
+        let reverses = if status == TripStatus::Reinstated {
+            // The latest cancellation that hasn't already been reinstated
+            timeline
+                .changes
+                .iter()
+                .rev()
+                .take_while(|change| change.status != TripStatus::Reinstated)
+                .find(|change| {
+                    matches!(
+                        change.status,
+                        TripStatus::Cancelled | TripStatus::PartCancelled
+                    )
+                })
+                .map(|change| change.event)
+        } else {
+            None
+        };
+        timeline.changes.push(StatusChange {
+            event: idx,
+            tweet_time: *cancellation.tweet_time(),
+            status,
+            reverses,
+        });
+    }
+    timelines.sort_by(|a, b| (a.time, &a.route).cmp(&(b.time, &b.route)));
+    timelines
+}
+
+#[cfg(test)]
+mod test_reconcile {
+    use super::*;
+    use crate::test_helpers::bus_event;
+
+    #[test]
+    fn test_reconcile() {
+        let cancellations = vec![
+            bus_event(
+                TripStatus::Reinstated,
+                "Johnsonville West",
+                "2021-02-14T18:45:00Z",
+            ),
+            bus_event(
+                TripStatus::Cancelled,
+                "Johnsonville West",
+                "2021-02-14T18:30:00Z",
+            ),
+            bus_event(
+                TripStatus::Cancelled,
+                "Churton Park",
+                "2021-02-14T18:31:00Z",
+            ),
+        ];
+        let timelines = reconcile(&cancellations);
+        assert_eq!(2, timelines.len());
+
+        let reinstated = timelines
+            .iter()
+            .find(|t| t.origin == "johnsonville west")
+            .unwrap();
+        assert_eq!(Some(TripStatus::Reinstated), reinstated.effective_status());
+        assert_eq!(vec![1], reinstated.reversed().collect::<Vec<_>>());
+
+        let cancelled = timelines
+            .iter()
+            .find(|t| t.origin == "churton park")
+            .unwrap();
+        assert_eq!(Some(TripStatus::Cancelled), cancelled.effective_status());
+        assert_eq!(0, cancelled.reversed().count());
+    }
+
+    #[test]
+    fn test_reconcile_cancelled_again() {
+        let cancellations = vec![
+            bus_event(
+                TripStatus::PartCancelled,
+                "Johnsonville West",
+                "2021-02-14T18:30:00Z",
+            ),
+            bus_event(
+                TripStatus::Reinstated,
+                "Johnsonville West",
+                "2021-02-14T18:45:00Z",
+            ),
+            bus_event(
+                TripStatus::Cancelled,
+                "Johnsonville West",
+                "2021-02-14T18:50:00Z",
+            ),
+            bus_event(
+                TripStatus::Reinstated,
+                "Johnsonville West",
+                "2021-02-14T18:55:00Z",
+            ),
+        ];
+        let timelines = reconcile(&cancellations);
+        assert_eq!(1, timelines.len());
+        assert_eq!(vec![0, 2], timelines[0].reversed().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,320 @@
+use crate::report::ReportRange;
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Pacific::Auckland;
+use metlink_gtfs_lib::client::reqwest_client;
+use metlink_gtfs_lib::realtime::replay::{Replay, ReplayEvent, StateChange, TripKey};
+use metlink_gtfs_lib::realtime::service_alerts::ServiceAlertRealtimeApi;
+use metlink_gtfs_lib::realtime::trip_updates::TripUpdateRealtimeApi;
+use metlink_gtfs_lib::realtime::vehicle_positions::VehiclePositionsRealtimeApi;
+use std::fs::File;
+use std::path::PathBuf;
+use time::OffsetDateTime;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const TIMELINE_FILE: &str = "realtime-replay.json";
+
+fn describe_trip(trip: &TripKey) -> String {
+    match &trip.start_date {
+        Some(date) => format!("{} on {}", trip.trip_id, date),
+        None => trip.trip_id.clone(),
+    }
+}
+
+fn describe_delay(delay: Option<i32>) -> String {
+    match delay {
+        Some(delay) => format!("{}m", delay / 60),
+        None => "unknown".to_string(),
+    }
+}
+
+/// One line for the change, in Wellington time.
+fn describe(event: &ReplayEvent) -> String {
+    let time = Utc
+        .timestamp_opt(event.time.unix_timestamp(), 0)
+        .single()
+        .map(|time| time.with_timezone(&Auckland).format("%F %T").to_string())
+        .unwrap_or_else(|| event.time.unix_timestamp().to_string());
+    let change = match &event.change {
+        StateChange::AlertAdded(entity) => {
+            format!("alert {} added: {}", entity.id, entity.alert.header())
+        }
+        StateChange::AlertRemoved { id } => format!("alert {} removed", id),
+        StateChange::TripCancelled { trip } => format!("{} cancelled", describe_trip(trip)),
+        StateChange::TripReinstated { trip } => format!("{} reinstated", describe_trip(trip)),
+        StateChange::DelayChanged { trip, from, to } => format!(
+            "{} delay {} -> {}",
+            describe_trip(trip),
+            describe_delay(*from),
+            describe_delay(*to)
+        ),
+        StateChange::VehicleAssigned { trip, vehicle_id } => {
+            format!("{} running as vehicle {}", describe_trip(trip), vehicle_id)
+        }
+    };
+    format!("{} {}", time, change)
+}
+
+fn to_offset(time: DateTime<Utc>) -> OffsetDateTime {
+    OffsetDateTime::from_unix_timestamp(time.timestamp())
+}
+
+/// Replays the downloaded realtime snapshots for the range, printing each change and writing the
+///  timeline out. Takes the same `--from`, `--to` as the report.
+pub async fn run(args: &[String]) -> Result<()> {
+    let range = ReportRange::from_args(args, Utc::now())?;
+    let (start, end) = range.utc_bounds();
+    let cache_dir = PathBuf::from("./.cache");
+    let mut replay = Replay::open(
+        &ServiceAlertRealtimeApi::new(&cache_dir, reqwest_client()?),
+        &TripUpdateRealtimeApi::new(&cache_dir, reqwest_client()?),
+        &VehiclePositionsRealtimeApi::new(&cache_dir, reqwest_client()?),
+    )
+    .await?
+    .between(to_offset(start), to_offset(end));
+    let snapshots = replay.remaining();
+
+    let mut timeline = vec![];
+    while let Some((time, changes)) = replay.next_snapshot().await {
+        for change in changes {
+            let event = ReplayEvent { time, change };
+            println!("{}", describe(&event));
+            timeline.push(event);
+        }
+    }
+    serde_json::to_writer_pretty(File::create(TIMELINE_FILE)?, &timeline)?;
+    println!(
+        "{} changes from {} snapshots between {} and {}, see {}",
+        timeline.len(),
+        snapshots,
+        range.start,
+        range.end,
+        TIMELINE_FILE
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_replay {
+    use super::*;
+    use crate::test_helpers::TempDir;
+    use metlink_gtfs_lib::realtime::archive::compact;
+    use metlink_gtfs_lib::realtime::replay::{ReplayState, Snapshot};
+    use metlink_gtfs_lib::realtime::utils::CachedRealtimeApi;
+    use time::Duration;
+
+    fn alerts(ids: &[&str]) -> Snapshot {
+        let entities: Vec<String> = ids
+            .iter()
+            .map(|id| {
+                format!(
+                    r#"{{
+                      "id": "{}",
+                      "timestamp": "2021-02-16T19:55:00+1300",
+                      "alert": {{
+                        "active_period": [],
+                        "effect": "REDUCED_SERVICE",
+                        "cause": "TECHNICAL_PROBLEM",
+                        "description_text": {{ "translation": [] }},
+                        "header_text": {{ "translation": [{{ "language": "en", "text": "HVL: Delays" }}] }},
+                        "informed_entity": [{{ "route_id": "HVL", "route_type": 2 }}],
+                        "severity_level": "WARNING"
+                      }}
+                    }}"#,
+                    id
+                )
+            })
+            .collect();
+        Snapshot::ServiceAlerts(
+            serde_json::from_str(&format!(
+                r#"{{
+                  "header": {{ "gtfs_realtime_version": "2.0", "incrementality": 0, "timestamp": 1613458500 }},
+                  "entity": [{}]
+                }}"#,
+                entities.join(",")
+            ))
+            .unwrap(),
+        )
+    }
+
+    fn trip_update_json(timestamp: i64, schedule_relationship: &str, delay: i32) -> String {
+        format!(
+            r#"{{
+              "header": {{ "gtfs_realtime_version": "2.0", "incrementality": 0, "timestamp": {} }},
+              "entity": [
+                {{
+                  "id": "1",
+                  "trip_update": {{
+                    "trip": {{ "trip_id": "HVL-1930", "start_date": "20210216", "schedule_relationship": "{}" }},
+                    "stop_time_update": {{ "stop_sequence": 4, "arrival": {{ "delay": {} }} }}
+                  }}
+                }}
+              ]
+            }}"#,
+            timestamp, schedule_relationship, delay
+        )
+    }
+
+    fn trip_update(schedule_relationship: &str, delay: i32) -> Snapshot {
+        Snapshot::TripUpdates(
+            serde_json::from_str(&trip_update_json(1613458500, schedule_relationship, delay))
+                .unwrap(),
+        )
+    }
+
+    fn vehicle_position(vehicle_id: &str) -> Snapshot {
+        Snapshot::VehiclePositions(
+            serde_json::from_str(&format!(
+                r#"{{
+                  "header": {{ "gtfs_realtime_version": "2.0", "incrementality": 0, "timestamp": 1613458500 }},
+                  "entity": [
+                    {{
+                      "id": "1",
+                      "vehicle": {{
+                        "trip": {{ "trip_id": "HVL-1930", "start_date": "20210216" }},
+                        "vehicle": {{ "id": "{}" }},
+                        "position": {{ "latitude": -41.2, "longitude": 174.8 }}
+                      }}
+                    }}
+                  ]
+                }}"#,
+                vehicle_id
+            ))
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_apply() {
+        let trip = TripKey {
+            trip_id: "HVL-1930".to_string(),
+            start_date: Some("20210216".to_string()),
+        };
+        let mut state = ReplayState::default();
+
+        let changes = state.apply(alerts(&["a", "b"]));
+        assert_eq!(2, changes.len());
+        let changes = state.apply(alerts(&["b"]));
+        assert!(matches!(&changes[..], [StateChange::AlertRemoved { id }] if id == "a"));
+        assert_eq!(1, state.alerts().count());
+
+        // On time when it's first seen, so there's nothing to say
+        assert!(state.apply(trip_update("SCHEDULED", 40)).is_empty());
+        let changes = state.apply(trip_update("SCHEDULED", 130));
+        assert!(matches!(
+            &changes[..],
+            [StateChange::DelayChanged {
+                from: Some(40),
+                to: Some(130),
+                ..
+            }]
+        ));
+        // Only a change of a whole minute counts
+        assert!(state.apply(trip_update("SCHEDULED", 170)).is_empty());
+        let changes = state.apply(trip_update("SCHEDULED", 400));
+        assert!(matches!(
+            &changes[..],
+            [StateChange::DelayChanged {
+                from: Some(170),
+                to: Some(400),
+                ..
+            }]
+        ));
+
+        let changes = state.apply(trip_update("CANCELED", 0));
+        assert!(matches!(&changes[..], [StateChange::TripCancelled { trip: t }] if *t == trip));
+        let changes = state.apply(trip_update("SCHEDULED", 400));
+        assert!(matches!(&changes[..], [StateChange::TripReinstated { .. }]));
+
+        assert_eq!(1, state.apply(vehicle_position("4251")).len());
+        assert!(state.apply(vehicle_position("4251")).is_empty());
+        let (_, trip_state) = state.trips().next().unwrap();
+        assert_eq!(Some("4251".to_string()), trip_state.vehicle_id);
+        assert_eq!(1, state.vehicles().count());
+
+        let event = ReplayEvent {
+            time: OffsetDateTime::from_unix_timestamp(1613458500),
+            change: StateChange::DelayChanged {
+                trip,
+                from: Some(170),
+                to: Some(400),
+            },
+        };
+        assert_eq!(
+            "2021-02-16 19:55:00 HVL-1930 on 20210216 delay 2m -> 6m",
+            describe(&event)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compacted() {
+        let dir = TempDir::new("realtime-archive");
+        let dir = &dir.0;
+        let service_alerts = ServiceAlertRealtimeApi::new(dir, reqwest_client().unwrap());
+        let trip_updates = TripUpdateRealtimeApi::new(dir, reqwest_client().unwrap());
+        let vehicle_positions = VehiclePositionsRealtimeApi::new(dir, reqwest_client().unwrap());
+        let folder = trip_updates.cache_folder();
+        std::fs::create_dir_all(&folder).unwrap();
+        let download = |name: &str, json: String| {
+            std::fs::write(folder.join(format!("{}.json", name)), json).unwrap()
+        };
+        download(
+            "2021-02-16 06.55.00+0000",
+            trip_update_json(1613458500, "SCHEDULED", 130),
+        );
+        download(
+            "2021-02-16 07.00.00+0000",
+            trip_update_json(1613458800, "SCHEDULED", 130),
+        );
+        download(
+            "2021-02-16 07.05.00+0000",
+            trip_update_json(1613459100, "CANCELED", 0),
+        );
+        download(
+            "2021-02-17 07.00.00+0000",
+            trip_update_json(1613545200, "SCHEDULED", 400),
+        );
+
+        // 2021-02-17 11:00 UTC, so that day's download is left as it is
+        let now = OffsetDateTime::from_unix_timestamp(1613559600);
+        let compaction = compact(&trip_updates, now, Some(Duration::days(30)))
+            .await
+            .unwrap();
+        assert_eq!(3, compaction.archived);
+        assert_eq!(1, compaction.repeated);
+        assert_eq!(0, compaction.expired);
+        assert!(folder.join("2021-02-16.jsonl.gz").exists());
+        assert_eq!(2, std::fs::read_dir(&folder).unwrap().count());
+
+        let mut replay = Replay::open(&service_alerts, &trip_updates, &vehicle_positions)
+            .await
+            .unwrap();
+        assert_eq!(4, replay.remaining());
+        let changes: Vec<StateChange> = replay
+            .timeline()
+            .await
+            .into_iter()
+            .map(|event| event.change)
+            .collect();
+        assert!(matches!(
+            &changes[..],
+            [
+                StateChange::DelayChanged { to: Some(130), .. },
+                StateChange::TripCancelled { .. },
+                StateChange::TripReinstated { .. },
+                StateChange::DelayChanged { to: Some(400), .. },
+            ]
+        ));
+
+        let compaction = compact(
+            &trip_updates,
+            now + Duration::days(60),
+            Some(Duration::days(30)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, compaction.archived);
+        assert_eq!(2, compaction.expired);
+        assert_eq!(0, std::fs::read_dir(&folder).unwrap().count());
+    }
+}
@@ -0,0 +1,252 @@
+use crate::parser::Cancellations;
+use crate::summary::{summarize, CancellationSummary};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Pacific::Auckland;
+use std::cmp::{max, min};
+use std::str::FromStr;
+
+const USAGE: &str = "Usage: [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--bucket day|week|month]";
+const DEFAULT_WEEKS: i64 = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bucket {
+    Day,
+    /// An ISO week, starting on Monday.
+    Week,
+    Month,
+}
+
+impl Bucket {
+    /// The first day of the bucket that `date` falls in.
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Bucket::Day => date,
+            Bucket::Week => date - Duration::days(date.weekday().num_days_from_monday().into()),
+            Bucket::Month => NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap(),
+        }
+    }
+
+    /// The first day of the bucket after the one that starts on `start`.
+    fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Bucket::Day => start + Duration::days(1),
+            Bucket::Week => start + Duration::weeks(1),
+            Bucket::Month if start.month() == 12 => {
+                NaiveDate::from_ymd_opt(start.year() + 1, 1, 1).unwrap()
+            }
+            Bucket::Month => NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1).unwrap(),
+        }
+    }
+}
+
+impl FromStr for Bucket {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Bucket::Day),
+            "week" => Ok(Bucket::Week),
+            "month" => Ok(Bucket::Month),
+            _ => Err(format!("Unknown bucket {:?}. {}", s, USAGE)),
+        }
+    }
+}
+
+/// The local date in Wellington, which is what a day of service means.
+pub fn local_date<Tz: TimeZone>(time: &DateTime<Tz>) -> NaiveDate {
+    time.with_timezone(&Auckland).naive_local().date()
+}
+
+/// The days to report on, from `start` up to but not including `end`, in Wellington dates.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ReportRange {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub bucket: Bucket,
+}
+
+impl ReportRange {
+    /// The four full weeks before this one.
+    pub fn default_at(now: DateTime<Utc>) -> Self {
+        let end = Bucket::Week.start_of(local_date(&now));
+        ReportRange {
+            start: end - Duration::weeks(DEFAULT_WEEKS),
+            end,
+            bucket: Bucket::Week,
+        }
+    }
+
+    /// Reads `--from`, `--to` and `--bucket`, falling back to the default for any not given.
+    pub fn from_args(args: &[String], now: DateTime<Utc>) -> Result<Self, String> {
+        let mut range = Self::default_at(now);
+        let mut from = None;
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {}. {}", flag, USAGE))?;
+            let date = || {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .map_err(|e| format!("Invalid date {:?}: {}. {}", value, e, USAGE))
+            };
+            match flag.as_str() {
+                "--from" => from = Some(date()?),
+                "--to" => range.end = date()?,
+                "--bucket" => range.bucket = value.parse()?,
+                _ => return Err(format!("Unknown argument {:?}. {}", flag, USAGE)),
+            }
+        }
+        range.start = from.unwrap_or(range.end - Duration::weeks(DEFAULT_WEEKS));
+        if range.start >= range.end {
+            return Err(format!(
+                "--from {} must be before --to {}",
+                range.start, range.end
+            ));
+        }
+        Ok(range)
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date < self.end
+    }
+
+    /// When the range starts and ends, as instants. Midnight is taken in Wellington, going with
+    ///  the earlier time if daylight saving makes it ambiguous.
+    pub fn utc_bounds(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        let midnight = |date: NaiveDate| {
+            Auckland
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+                .earliest()
+                .expect("Wellington's daylight saving never skips midnight")
+                .with_timezone(&Utc)
+        };
+        (midnight(self.start), midnight(self.end))
+    }
+
+    /// Splits the range on bucket boundaries. The first and last buckets are cut short if the
+    ///  range doesn't start or end on a boundary.
+    pub fn buckets(&self) -> Vec<(NaiveDate, NaiveDate)> {
+        let mut buckets = vec![];
+        let mut start = self.bucket.start_of(self.start);
+        while start < self.end {
+            let next = self.bucket.next(start);
+            buckets.push((max(start, self.start), min(next, self.end)));
+            start = next;
+        }
+        buckets
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BucketSummary {
+    pub start: NaiveDate,
+    /// The day after the last one in the bucket.
+    pub end: NaiveDate,
+    pub summary: CancellationSummary,
+}
+
+/// Summarises the events in each bucket, going by the day the service was scheduled for.
+pub fn report(cancellations: &[Cancellations], range: &ReportRange) -> Vec<BucketSummary> {
+    range
+        .buckets()
+        .into_iter()
+        .map(|(start, end)| {
+            let in_bucket: Vec<Cancellations> = cancellations
+                .iter()
+                .filter(|c| {
+                    let date = local_date(c.time());
+                    start <= date && date < end
+                })
+                .cloned()
+                .collect();
+            BucketSummary {
+                start,
+                end,
+                summary: summarize(&in_bucket),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test_report {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_default_range() {
+        // Monday 2021-02-15 in Wellington, but still Sunday in UTC.
+        let now = DateTime::parse_from_rfc3339("2021-02-14T17:13:07Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            ReportRange {
+                start: date("2021-01-18"),
+                end: date("2021-02-15"),
+                bucket: Bucket::Week,
+            },
+            ReportRange::from_args(&[], now).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_month_buckets() {
+        let now = Utc::now();
+        let range = ReportRange::from_args(
+            &args(&[
+                "--from",
+                "2020-11-15",
+                "--to",
+                "2021-02-01",
+                "--bucket",
+                "month",
+            ]),
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                (date("2020-11-15"), date("2020-12-01")),
+                (date("2020-12-01"), date("2021-01-01")),
+                (date("2021-01-01"), date("2021-02-01")),
+            ],
+            range.buckets()
+        );
+    }
+
+    #[test]
+    fn test_week_buckets() {
+        let range = ReportRange {
+            start: date("2021-02-10"),
+            end: date("2021-02-23"),
+            bucket: Bucket::Week,
+        };
+        assert_eq!(
+            vec![
+                (date("2021-02-10"), date("2021-02-15")),
+                (date("2021-02-15"), date("2021-02-22")),
+                (date("2021-02-22"), date("2021-02-23")),
+            ],
+            range.buckets()
+        );
+    }
+
+    #[test]
+    fn test_invalid_args() {
+        let now = Utc::now();
+        assert!(ReportRange::from_args(&args(&["--bucket", "year"]), now).is_err());
+        assert!(ReportRange::from_args(&args(&["--from"]), now).is_err());
+        assert!(ReportRange::from_args(
+            &args(&["--from", "2021-02-01", "--to", "2021-01-01"]),
+            now
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,431 @@
+use crate::parser::{Cancellations, Location, ResolvedStop};
+use metlink_gtfs_lib::db::routes::{Route, RouteDb};
+use metlink_gtfs_lib::db::stops::{Stop, StopDb};
+use metlink_gtfs_lib::gtfs::data::route::RouteType;
+use metlink_gtfs_lib::gtfs::data::stop::StopLocationType;
+use std::collections::HashMap;
+
+// Metlink's four letter station codes, as used in the rail tweets.
+const RAIL_STATION_CODES: [(&str, &str); 48] = [
+    ("WELL", "Wellington"),
+    // Kapiti Line
+    ("TAKA", "Takapu Road"),
+    ("REDW", "Redwood"),
+    ("TAWA", "Tawa"),
+    ("LIND", "Linden"),
+    ("KENE", "Kenepuru"),
+    ("PORI", "Porirua"),
+    ("PARE", "Paremata"),
+    ("MANA", "Mana"),
+    ("PLIM", "Plimmerton"),
+    ("PUKE", "Pukerua Bay"),
+    ("PAEK", "Paekakariki"),
+    ("PARA", "Paraparaumu"),
+    ("WAIK", "Waikanae"),
+    // Hutt Valley & Melling Lines
+    ("NGAU", "Ngauranga"),
+    ("PETO", "Petone"),
+    ("WEST", "Western Hutt"),
+    ("MELL", "Melling"),
+    ("AVA", "Ava"),
+    ("WOBU", "Woburn"),
+    ("WATE", "Waterloo"),
+    ("EPUN", "Epuni"),
+    ("NAEN", "Naenae"),
+    ("WING", "Wingate"),
+    ("TAIT", "Taita"),
+    ("POMA", "Pomare"),
+    ("MANO", "Manor Park"),
+    ("SILV", "Silverstream"),
+    ("HERE", "Heretaunga"),
+    ("TREN", "Trentham"),
+    ("WALL", "Wallaceville"),
+    ("UPPE", "Upper Hutt"),
+    // Wairarapa Line
+    ("MAYM", "Maymorn"),
+    ("FEAT", "Featherston"),
+    ("WOOD", "Woodside"),
+    ("MATA", "Matarawa"),
+    ("CART", "Carterton"),
+    ("SOLW", "Solway"),
+    ("RENA", "Renall Street"),
+    ("MAST", "Masterton"),
+    // Johnsonville Line
+    ("CROF", "Crofton Downs"),
+    ("NGAI", "Ngaio"),
+    ("AWAR", "Awarua Street"),
+    ("SIML", "Simla Crescent"),
+    ("BOXH", "Box Hill"),
+    ("KHAN", "Khandallah"),
+    ("RARO", "Raroa"),
+    ("JOHN", "Johnsonville"),
+];
+
+// Shorthand that shows up in the bus tweets.
+const NAME_ABBREVIATIONS: [(&str, &str); 12] = [
+    ("stn", "station"),
+    ("wgtn", "wellington"),
+    ("pl", "place"),
+    ("rd", "road"),
+    ("st", "street"),
+    ("ave", "avenue"),
+    ("cres", "crescent"),
+    ("tce", "terrace"),
+    ("hts", "heights"),
+    ("nth", "north"),
+    ("sth", "south"),
+    ("hosp", "hospital"),
+];
+
+// Words that mean the regex captured the start of the rest of the sentence, like "Island Bay is now".
+const TRAILING_WORDS: [&str; 5] = ["is", "has", "was", "will", "that"];
+
+const FUZZY_MATCH_THRESHOLD: f64 = 0.9;
+
+pub fn station_name(code: &str) -> Option<&'static str> {
+    RAIL_STATION_CODES
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, name)| *name)
+}
+
+pub fn normalise_name(name: &str) -> String {
+    let folded: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'ā' => 'a',
+            'ē' => 'e',
+            'ī' => 'i',
+            'ō' => 'o',
+            'ū' => 'u',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect();
+    folded
+        .split_whitespace()
+        .skip_while(|word| *word == "from")
+        .take_while(|word| !TRAILING_WORDS.contains(word))
+        .map(|word| {
+            NAME_ABBREVIATIONS
+                .iter()
+                .find(|(short, _)| *short == word)
+                .map(|(_, long)| *long)
+                .unwrap_or(word)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Prefer the parent station over the individual platforms and entrances.
+fn location_type_rank(stop: &Stop) -> u8 {
+    match stop.location_type {
+        StopLocationType::Station => 0,
+        StopLocationType::StopOrPlatform => 1,
+        _ => 2,
+    }
+}
+
+pub struct StopResolver<'a> {
+    stops: &'a StopDb,
+    names: Vec<(String, &'a Stop)>,
+}
+
+impl<'a> StopResolver<'a> {
+    pub fn new(stops: &'a StopDb) -> Self {
+        Self {
+            stops,
+            names: stops
+                .iter()
+                .map(|stop| (normalise_name(&stop.name), stop))
+                .collect(),
+        }
+    }
+
+    pub fn resolve(&self, raw: &str) -> Option<&'a Stop> {
+        self.resolve_station_code(raw.trim())
+            .or_else(|| self.resolve_name(raw))
+    }
+
+    fn resolve_station_code(&self, code: &str) -> Option<&'a Stop> {
+        let name = station_name(code)?;
+        self.stops
+            .iter()
+            .filter(|stop| {
+                stop.code.eq_ignore_ascii_case(code) || stop.id.eq_ignore_ascii_case(code)
+            })
+            .min_by_key(|stop| location_type_rank(stop))
+            .map(|stop| self.parent_station(stop))
+            .or_else(|| self.resolve_name(&format!("{} Station", name)))
+    }
+
+    fn resolve_name(&self, raw: &str) -> Option<&'a Stop> {
+        let wanted = normalise_name(raw);
+        if wanted.is_empty() {
+            return None;
+        }
+        let prefix = format!("{} ", wanted);
+
+        self.names
+            .iter()
+            .filter(|(name, _)| *name == wanted)
+            .map(|(_, stop)| *stop)
+            .min_by_key(|stop| location_type_rank(stop))
+            .or_else(|| {
+                // Bus tweets tend to name the suburb, and the stops are named "<suburb> - Stop A"
+                self.names
+                    .iter()
+                    .filter(|(name, _)| name.starts_with(&prefix))
+                    .min_by_key(|(name, stop)| (location_type_rank(stop), name.len()))
+                    .map(|(_, stop)| *stop)
+            })
+            .or_else(|| {
+                // Typos like "Kilbrinie"; only compare as many words as we're looking for.
+                let word_count = wanted.split(' ').count();
+                self.names
+                    .iter()
+                    .map(|(name, stop)| {
+                        let head = name
+                            .split(' ')
+                            .take(word_count)
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        (strsim::jaro_winkler(&head, &wanted), stop)
+                    })
+                    .filter(|(score, _)| *score >= FUZZY_MATCH_THRESHOLD)
+                    .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap())
+                    .map(|(_, stop)| *stop)
+            })
+            .map(|stop| self.parent_station(stop))
+    }
+
+    fn parent_station(&self, stop: &'a Stop) -> &'a Stop {
+        if stop.parent_station.is_empty() {
+            stop
+        } else {
+            self.stops.get_stop(&stop.parent_station).unwrap_or(stop)
+        }
+    }
+
+    pub fn resolve_all<'c>(&self, cancellations: impl IntoIterator<Item = &'c mut Cancellations>) {
+        let mut resolved: HashMap<String, Option<ResolvedStop>> = HashMap::new();
+        for cancellation in cancellations {
+            for location in cancellation.locations_mut() {
+                let Location { raw, stop } = location;
+                *stop = resolved
+                    .entry(raw.clone())
+                    .or_insert_with(|| {
+                        self.resolve(raw).map(|s| ResolvedStop {
+                            id: s.id.clone(),
+                            lat: s.lat,
+                            lon: s.lon,
+                        })
+                    })
+                    .clone();
+            }
+        }
+    }
+}
+
+/// Matches the ferry routes in the tweets ("WHF", "Harbour Explorer") against the GTFS ferry
+///  routes, by short or long name.
+pub struct FerryRouteResolver<'a> {
+    routes: Vec<&'a Route>,
+}
+
+impl<'a> FerryRouteResolver<'a> {
+    pub fn new(routes: &'a RouteDb) -> Self {
+        Self {
+            routes: routes
+                .iter()
+                .filter(|route| matches!(route.route_type, RouteType::Ferry))
+                .collect(),
+        }
+    }
+
+    pub fn resolve(&self, raw: &str) -> Option<&'a Route> {
+        let wanted = normalise_name(raw);
+        if wanted.is_empty() {
+            return None;
+        }
+        self.routes
+            .iter()
+            .find(|route| route.short_name.eq_ignore_ascii_case(raw.trim()))
+            .or_else(|| {
+                self.routes
+                    .iter()
+                    .find(|route| normalise_name(&route.long_name).contains(&wanted))
+            })
+            .copied()
+    }
+
+    pub fn resolve_all<'c>(&self, cancellations: impl IntoIterator<Item = &'c mut Cancellations>) {
+        for cancellation in cancellations {
+            if let Some(route) = cancellation.ferry_route_mut() {
+                route.route_id = self.resolve(&route.raw).map(|r| r.id.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_resolver {
+    use super::*;
+    use crate::test_helpers::{gtfs_data, gtfs_route, gtfs_stop};
+    use metlink_gtfs_lib::gtfs::data::{stop, GtfsData};
+
+    fn station_stop(
+        id: &str,
+        name: &str,
+        location_type: StopLocationType,
+        parent: &str,
+    ) -> stop::Stop {
+        stop::Stop {
+            location_type,
+            parent_station: parent.to_string(),
+            ..gtfs_stop(id, name)
+        }
+    }
+
+    fn stop_db() -> StopDb {
+        let data = GtfsData {
+            stop: vec![
+                station_stop("WELL", "Wellington Station", StopLocationType::Station, ""),
+                station_stop(
+                    "WELL1",
+                    "Wellington Station - Platform 1",
+                    StopLocationType::StopOrPlatform,
+                    "WELL",
+                ),
+                station_stop(
+                    "UPPE1",
+                    "Upper Hutt Station - Platform 1",
+                    StopLocationType::StopOrPlatform,
+                    "UPPE",
+                ),
+                station_stop("UPPE", "Upper Hutt Station", StopLocationType::Station, ""),
+                station_stop(
+                    "7001",
+                    "Island Bay - The Parade (near 156)",
+                    StopLocationType::StopOrPlatform,
+                    "",
+                ),
+                station_stop(
+                    "5000",
+                    "Courtenay Place - Stop B",
+                    StopLocationType::StopOrPlatform,
+                    "",
+                ),
+                station_stop(
+                    "4001",
+                    "Kilbirnie - Stop A",
+                    StopLocationType::StopOrPlatform,
+                    "",
+                ),
+            ],
+            ..gtfs_data()
+        };
+        (&data).into()
+    }
+
+    #[test]
+    fn test_station_code() {
+        let db = stop_db();
+        let resolver = StopResolver::new(&db);
+        assert_eq!(
+            Some("WELL"),
+            resolver.resolve("WELL").map(|s| s.id.as_str())
+        );
+        assert_eq!(
+            Some("UPPE"),
+            resolver.resolve("UPPE").map(|s| s.id.as_str())
+        );
+    }
+
+    #[test]
+    fn test_stop_name() {
+        let db = stop_db();
+        let resolver = StopResolver::new(&db);
+        assert_eq!(
+            Some("WELL"),
+            resolver.resolve("Wellington Stn").map(|s| s.id.as_str())
+        );
+        assert_eq!(
+            Some("WELL"),
+            resolver.resolve("Wgtn Stn").map(|s| s.id.as_str())
+        );
+        assert_eq!(
+            Some("5000"),
+            resolver.resolve("Courtenay Pl").map(|s| s.id.as_str())
+        );
+        assert_eq!(
+            Some("7001"),
+            resolver.resolve("Island Bay is now").map(|s| s.id.as_str())
+        );
+        assert_eq!(
+            None,
+            resolver.resolve("Johnsonville West").map(|s| s.id.as_str())
+        );
+    }
+
+    #[test]
+    fn test_stop_name_fuzzy() {
+        let db = stop_db();
+        let resolver = StopResolver::new(&db);
+        assert_eq!(
+            Some("4001"),
+            resolver.resolve("Kilbrinie").map(|s| s.id.as_str())
+        );
+    }
+
+    #[test]
+    fn test_resolve_all() {
+        let db = stop_db();
+        let resolver = StopResolver::new(&db);
+        let mut cancellations = vec![Cancellations::TrainCancelled {
+            line: "HVL".to_string(),
+            origin: "WELL".into(),
+            destination: "UPPE".into(),
+            cause: None,
+            raw_time: "5:00 pm".to_string(),
+            tweet_time: chrono::Utc::now(),
+            time: chrono::Utc::now().into(),
+            link: None,
+        }];
+        resolver.resolve_all(&mut cancellations);
+        match &cancellations[0] {
+            Cancellations::TrainCancelled {
+                origin,
+                destination,
+                ..
+            } => {
+                assert_eq!(Some("WELL"), origin.stop.as_ref().map(|s| s.id.as_str()));
+                assert_eq!(
+                    Some("UPPE"),
+                    destination.stop.as_ref().map(|s| s.id.as_str())
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_ferry_route() {
+        let data = GtfsData {
+            route: vec![
+                gtfs_route("1", "WHF", "Queens Wharf - Days Bay", RouteType::Ferry),
+                gtfs_route("2", "WHF", "Wellington - Hutt Valley Line", RouteType::Rail),
+            ],
+            ..gtfs_data()
+        };
+        let db: RouteDb = (&data).into();
+        let resolver = FerryRouteResolver::new(&db);
+        assert_eq!(Some("1"), resolver.resolve("WHF").map(|r| r.id.as_str()));
+        assert_eq!(
+            Some("1"),
+            resolver.resolve("Days Bay").map(|r| r.id.as_str())
+        );
+        assert!(resolver.resolve("Harbour Explorer").is_none());
+    }
+}
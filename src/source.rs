@@ -0,0 +1,306 @@
+use crate::feed::{FeedFile, OutboxFile};
+use crate::tweet_cache::{TweetCache, TweetContent, TweetEntities, TweetUrl};
+use chrono::{DateTime, Utc};
+use egg_mode::tweet::{user_timeline, Timeline, Tweet};
+use egg_mode::Token;
+use futures::future::{ready, FutureExt, LocalBoxFuture};
+use serde::Deserialize;
+use std::fs::{read_to_string, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const TWITTER_CREDS_FILE: &str = "./twitter-creds.json";
+const SCREEN_NAME: &str = "metlinkwgtn";
+const USAGE: &str = "Usage: import <tweets.js|tweets.jsonl|outbox.json|feed.(rss|atom|xml)>";
+
+/// Somewhere tweets can be loaded from into the cache.
+pub trait TweetSource {
+    /// Adds this source's tweets to the cache. Returns how many were new.
+    fn load_into<'a>(&'a self, cache: &'a mut TweetCache) -> LocalBoxFuture<'a, Result<usize>>;
+}
+
+/// Fixtures, and anything else that's already in memory.
+impl TweetSource for Vec<TweetContent> {
+    fn load_into<'a>(&'a self, cache: &'a mut TweetCache) -> LocalBoxFuture<'a, Result<usize>> {
+        ready(Ok(cache.add_all(self.iter().cloned()))).boxed_local()
+    }
+}
+
+#[derive(Deserialize)]
+struct TwitterTokenInfo {
+    api_key: String,
+    api_secret: String,
+    access_token: String,
+    access_secret: String,
+}
+
+/// The live timeline. This only goes back 3,200 tweets, so older ones need an archive.
+pub struct TwitterApi {
+    token: Token,
+}
+
+impl TwitterApi {
+    pub fn from_creds_file() -> Result<Self> {
+        let file = File::open(TWITTER_CREDS_FILE)?;
+        let reader = BufReader::new(file);
+        let creds: TwitterTokenInfo = serde_json::from_reader(reader)?;
+
+        let con_token = egg_mode::KeyPair::new(creds.api_key, creds.api_secret);
+        let access_token = egg_mode::KeyPair::new(creds.access_token, creds.access_secret);
+        Ok(TwitterApi {
+            token: egg_mode::Token::Access {
+                consumer: con_token,
+                access: access_token,
+            },
+        })
+    }
+
+    async fn load_timeline(&self, cache: &mut TweetCache) -> Result<usize> {
+        let back_to = cache.latest_id();
+        // egg-mode sends `tweet_mode=extended` with every timeline request, so each tweet's text
+        //  is its `full_text` instead of the first 140 characters.
+        let mut timeline: Timeline =
+            user_timeline(SCREEN_NAME, false, false, &self.token).with_page_size(200);
+        let mut added = 0;
+        loop {
+            println!("Fetching tweets {:?} to {:?}", timeline.min_id, back_to);
+            let (inner_timeline, feed_) = timeline.older(back_to).await?;
+            let feed: &Vec<Tweet> = &feed_;
+            if feed.is_empty() {
+                return Ok(added);
+            }
+            added += cache.add_all(feed.iter().map(TweetContent::from));
+            // Each page is written as it comes in, so hitting the rate limit doesn't lose it.
+            cache.write()?;
+            timeline = inner_timeline;
+        }
+    }
+}
+
+impl TweetSource for TwitterApi {
+    fn load_into<'a>(&'a self, cache: &'a mut TweetCache) -> LocalBoxFuture<'a, Result<usize>> {
+        self.load_timeline(cache).boxed_local()
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ArchiveHashtag {
+    text: String,
+}
+
+#[derive(Deserialize, Default)]
+struct ArchiveUrl {
+    url: String,
+    expanded_url: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ArchiveMention {
+    screen_name: String,
+}
+
+#[derive(Deserialize, Default)]
+struct ArchiveEntities {
+    #[serde(default)]
+    hashtags: Vec<ArchiveHashtag>,
+    #[serde(default)]
+    urls: Vec<ArchiveUrl>,
+    #[serde(default)]
+    user_mentions: Vec<ArchiveMention>,
+}
+
+/// A tweet as it's written in a data archive, where the numbers are all strings.
+#[derive(Deserialize)]
+struct ArchiveTweet {
+    id_str: String,
+    created_at: String,
+    full_text: String,
+    #[serde(default)]
+    in_reply_to_status_id_str: Option<String>,
+    #[serde(default)]
+    display_text_range: Option<(String, String)>,
+    #[serde(default)]
+    entities: ArchiveEntities,
+}
+
+// Newer archives wrap each tweet in an object of its own.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ArchiveEntry {
+    Wrapped { tweet: ArchiveTweet },
+    Bare(ArchiveTweet),
+}
+
+impl ArchiveTweet {
+    fn into_content(self) -> Result<TweetContent> {
+        let created_at = DateTime::parse_from_str(&self.created_at, "%a %b %d %H:%M:%S %z %Y")?
+            .with_timezone(&Utc);
+        let display_text_range = match self.display_text_range {
+            Some((start, end)) => Some((start.parse()?, end.parse()?)),
+            None => None,
+        };
+        let in_reply_to_status_id = match self.in_reply_to_status_id_str {
+            Some(id) => Some(id.parse()?),
+            None => None,
+        };
+        Ok(TweetContent::from_full_text(
+            self.id_str.parse()?,
+            created_at,
+            &self.full_text,
+            display_text_range,
+            in_reply_to_status_id,
+            TweetEntities {
+                hashtags: self.entities.hashtags.into_iter().map(|h| h.text).collect(),
+                urls: self
+                    .entities
+                    .urls
+                    .into_iter()
+                    .map(|u| TweetUrl {
+                        url: u.url,
+                        expanded_url: u.expanded_url,
+                    })
+                    .collect(),
+                user_mentions: self
+                    .entities
+                    .user_mentions
+                    .into_iter()
+                    .map(|m| m.screen_name)
+                    .collect(),
+            },
+        ))
+    }
+}
+
+/// Reads the `tweets.js` from a Twitter data archive. It's a JSON array assigned to a variable
+///  (`window.YTD.tweets.part0 = [...]`).
+pub fn parse_archive(contents: &str) -> Result<Vec<TweetContent>> {
+    let start = contents.find('[').ok_or("No tweets in the archive")?;
+    let entries: Vec<ArchiveEntry> = serde_json::from_str(&contents[start..])?;
+    entries
+        .into_iter()
+        .map(|entry| match entry {
+            ArchiveEntry::Wrapped { tweet } => tweet.into_content(),
+            ArchiveEntry::Bare(tweet) => tweet.into_content(),
+        })
+        .collect()
+}
+
+/// Reads one cached tweet per line, skipping blank lines. Tweets without entities get what can
+///  be picked out of their text.
+pub fn parse_jsonl(contents: &str) -> Result<Vec<TweetContent>> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut tweet: TweetContent = serde_json::from_str(line)?;
+            if tweet.entities == TweetEntities::default() {
+                tweet.entities = TweetEntities::from_text(&tweet.text);
+            }
+            Ok(tweet)
+        })
+        .collect()
+}
+
+/// A `tweets.js` from a Twitter data archive.
+pub struct ArchiveFile {
+    pub path: PathBuf,
+}
+
+impl TweetSource for ArchiveFile {
+    fn load_into<'a>(&'a self, cache: &'a mut TweetCache) -> LocalBoxFuture<'a, Result<usize>> {
+        let tweets = read_to_string(&self.path)
+            .map_err(|e| e.into())
+            .and_then(|contents| parse_archive(&contents));
+        ready(tweets.map(|tweets| cache.add_all(tweets))).boxed_local()
+    }
+}
+
+/// Tweets in the cache's own format, one per line.
+pub struct JsonlFile {
+    pub path: PathBuf,
+}
+
+impl TweetSource for JsonlFile {
+    fn load_into<'a>(&'a self, cache: &'a mut TweetCache) -> LocalBoxFuture<'a, Result<usize>> {
+        let tweets = read_to_string(&self.path)
+            .map_err(|e| e.into())
+            .and_then(|contents| parse_jsonl(&contents));
+        ready(tweets.map(|tweets| cache.add_all(tweets))).boxed_local()
+    }
+}
+
+/// `import <file>` adds the tweets from an archive's `tweets.js` or a `.jsonl` file to the cache,
+///  along with posts from a saved ActivityPub outbox or RSS/Atom feed.
+pub async fn run(args: &[String]) -> Result<()> {
+    let path = PathBuf::from(args.first().ok_or(USAGE)?);
+    let source: Box<dyn TweetSource> = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("js") => Box::new(ArchiveFile { path }),
+        Some("jsonl") => Box::new(JsonlFile { path }),
+        Some("json") => Box::new(OutboxFile { path }),
+        Some("rss") | Some("atom") | Some("xml") => Box::new(FeedFile { path }),
+        _ => return Err(USAGE.into()),
+    };
+    let mut cache = TweetCache::read()?;
+    let added = source.load_into(&mut cache).await?;
+    cache.write()?;
+    println!("Added {} tweets, {} cached", added, cache.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_source {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_parse_archive() {
+        let tweets = parse_archive(
+            r#"window.YTD.tweets.part0 = [ {
+              "tweet" : {
+                "id_str" : "1314498352097103877",
+                "created_at" : "Fri Oct 09 09:30:03 +0000 2020",
+                "full_text" : "@metlinkwgtn KPL: The 9:14pm svc from WELL to WAIK is now on the move and running 21 mins late from PARA.",
+                "display_text_range" : [ "13", "105" ],
+                "in_reply_to_status_id_str" : "1314495834554208256",
+                "entities" : {
+                  "hashtags" : [ ],
+                  "urls" : [ ],
+                  "user_mentions" : [ { "screen_name" : "metlinkwgtn", "id_str" : "1" } ]
+                }
+              }
+            } ]"#,
+        )
+        .unwrap();
+        assert_eq!(1, tweets.len());
+        let tweet = &tweets[0];
+        assert_eq!(1314498352097103877, tweet.id);
+        assert_eq!(
+            "KPL: The 9:14pm svc from WELL to WAIK is now on the move and running 21 mins late from PARA.",
+            tweet.text
+        );
+        assert!(tweet.full_text.is_some());
+        assert_eq!(Some(1314495834554208256), tweet.in_reply_to_status_id);
+        assert_eq!(
+            vec!["metlinkwgtn".to_string()],
+            tweet.entities.user_mentions
+        );
+    }
+
+    #[test]
+    fn test_sources_into_cache() {
+        let jsonl = parse_jsonl(
+            r#"{"id": 2, "created_at": "2021-01-14T05:18:11Z", "text": "Bus 3: Delays https://t.co/EidCAPongw"}
+
+{"id": 1, "created_at": "2021-01-14T04:06:10Z", "text": "WRL: Updates to follow"}"#,
+        )
+        .unwrap();
+        assert_eq!(1, jsonl[0].entities.urls.len());
+
+        let mut cache = TweetCache::default();
+        assert_eq!(2, block_on(jsonl.load_into(&mut cache)).unwrap());
+        assert_eq!(0, block_on(jsonl.load_into(&mut cache)).unwrap());
+        assert_eq!(vec![1, 2], cache.tweets().map(|t| t.id).collect::<Vec<_>>());
+    }
+}
@@ -1,15 +1,75 @@
+use crate::disruption::DisruptionKind;
+use crate::parser::{CauseKind, Delay};
+use crate::reconcile::{reconcile, TripStatus};
+use crate::resolver::normalise_name;
 use crate::Cancellations;
 use chrono::DateTime;
 use chrono::FixedOffset;
-use chrono::{Duration, NaiveTime};
+use chrono::{Datelike, Duration, NaiveTime, Timelike};
 use std::cmp::max;
 use std::cmp::min;
+use std::collections::{BTreeMap, HashSet};
 use std::convert::TryInto;
 use std::iter::FromIterator;
 
-#[derive(Debug, PartialEq, PartialOrd, Copy, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct CancellationSummary {
-    stats: CancellationStats, // Map<String, >
+    stats: CancellationStats,
+    train_stats: CancellationStats,
+    // Cancelled sailings, and suspensions of every sailing for part of a day.
+    ferry_stats: CancellationStats,
+    // What the trips that didn't run as planned were disrupted by, leaving out reinstatements,
+    //  school runs and network disruptions. `None` counts the tweets that didn't give a reason.
+    causes: BTreeMap<Option<CauseKind>, usize>,
+    delays: DelayStats,
+    delays_by_route: BTreeMap<String, DelayStats>,
+
+    // School services are kept out of the rest of the stats, which are for the public network.
+    school_runs: EventStats,
+    by_school: BTreeMap<String, EventStats>,
+
+    // Disruptions to whole lines or areas, which are also kept out of the per-trip stats.
+    planned_disruptions: DisruptionStats,
+    unplanned_disruptions: DisruptionStats,
+
+    by_route: BTreeMap<String, EventStats>,
+    // Keyed by route, origin and destination.
+    by_route_direction: BTreeMap<(String, String, String), EventStats>,
+    // Keyed by the hour the service was scheduled to leave.
+    by_hour: BTreeMap<u32, EventStats>,
+    // Keyed by days since Monday.
+    by_weekday: BTreeMap<u32, EventStats>,
+}
+
+/// Stats for each kind of event. Held trains count as delayed, and trains replaced by buses count
+///  as part cancelled.
+#[derive(Debug, PartialEq, PartialOrd, Copy, Clone)]
+pub struct EventStats {
+    cancelled: CancellationStats,
+    part_cancelled: CancellationStats,
+    delayed: CancellationStats,
+    reinstated: CancellationStats,
+}
+impl<'a> FromIterator<&'a Cancellations> for EventStats {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: std::iter::IntoIterator<Item = &'a Cancellations>,
+    {
+        let events: Vec<&Cancellations> = iter.into_iter().collect();
+        let with_status = |statuses: &[TripStatus]| -> CancellationStats {
+            events
+                .iter()
+                .copied()
+                .filter(|c| statuses.contains(&TripStatus::from(*c)))
+                .collect()
+        };
+        EventStats {
+            cancelled: with_status(&[TripStatus::Cancelled]),
+            part_cancelled: with_status(&[TripStatus::PartCancelled, TripStatus::Replaced]),
+            delayed: with_status(&[TripStatus::Delayed, TripStatus::Held]),
+            reinstated: with_status(&[TripStatus::Reinstated]),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Copy, Clone)]
@@ -104,14 +164,249 @@ impl<'a> FromIterator<&'a Duration> for CancellationNoticeStats {
     }
 }
 
+/// How late the delayed services ran, from the tweets that said by how much.
+#[derive(Debug, PartialEq, PartialOrd, Copy, Clone)]
+pub struct DelayStats {
+    count: usize,
+    // Delayed, but the tweet didn't say by how much.
+    unknown: usize,
+    total_minutes: u32,
+    p50: Option<u32>,
+    p90: Option<u32>,
+}
+impl<'a> FromIterator<&'a Delay> for DelayStats {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: std::iter::IntoIterator<Item = &'a Delay>,
+    {
+        let mut unknown = 0;
+        let mut minutes = vec![];
+        for delay in iter {
+            match delay.minutes() {
+                Some(m) => minutes.push(m),
+                None => unknown += 1,
+            }
+        }
+        minutes.sort_unstable();
+
+        DelayStats {
+            count: minutes.len(),
+            unknown,
+            total_minutes: minutes.iter().sum(),
+            p50: percentile(&minutes, 50),
+            p90: percentile(&minutes, 90),
+        }
+    }
+}
+
+// Nearest-rank percentile of an already sorted list.
+fn percentile(sorted: &[u32], percent: usize) -> Option<u32> {
+    if sorted.is_empty() {
+        return None;
+    }
+    Some(sorted[(percent * sorted.len()).saturating_sub(1) / 100])
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DisruptionStats {
+    count: usize,
+    by_kind: BTreeMap<DisruptionKind, usize>,
+    // Disruptions without a known end aren't in the total.
+    open_ended: usize,
+    total_duration: Duration,
+}
+impl<'a> FromIterator<&'a Cancellations> for DisruptionStats {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: std::iter::IntoIterator<Item = &'a Cancellations>,
+    {
+        let mut stats = DisruptionStats {
+            count: 0,
+            by_kind: BTreeMap::new(),
+            open_ended: 0,
+            total_duration: Duration::zero(),
+        };
+        for event in iter {
+            if let Cancellations::NetworkDisruption { kind, window, .. } = event {
+                stats.count += 1;
+                *stats.by_kind.entry(*kind).or_insert(0) += 1;
+                match window.duration() {
+                    Some(duration) => stats.total_duration = stats.total_duration + duration,
+                    None => stats.open_ended += 1,
+                }
+            }
+        }
+        stats
+    }
+}
+
+/// Drops the cancellations that were later reinstated, so only services that didn't run are
+///  counted.
+fn effective_events(cancellations: &[Cancellations]) -> Vec<&Cancellations> {
+    let reversed: HashSet<usize> = reconcile(cancellations)
+        .iter()
+        .flat_map(|timeline| timeline.reversed())
+        .collect();
+    cancellations
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !reversed.contains(idx))
+        .map(|(_, event)| event)
+        .collect()
+}
+
+fn group_by<K, F>(events: &[&Cancellations], key: F) -> BTreeMap<K, EventStats>
+where
+    K: Ord,
+    F: Fn(&Cancellations) -> K,
+{
+    let mut groups: BTreeMap<K, Vec<&Cancellations>> = BTreeMap::new();
+    for event in events {
+        groups.entry(key(event)).or_default().push(event);
+    }
+    groups
+        .into_iter()
+        .map(|(k, group)| (k, group.into_iter().collect()))
+        .collect()
+}
+
 pub fn summarize(cancellations: &[Cancellations]) -> CancellationSummary {
-    return CancellationSummary {
-        stats: cancellations
+    let (disruptions, events): (Vec<&Cancellations>, Vec<&Cancellations>) =
+        effective_events(cancellations)
+            .into_iter()
+            .partition(|c| c.window().is_some());
+    let (school_runs, events): (Vec<&Cancellations>, Vec<&Cancellations>) =
+        events.into_iter().partition(|c| c.school().is_some());
+    let mut delays_by_route: BTreeMap<String, Vec<&Delay>> = BTreeMap::new();
+    for event in &events {
+        if let Some(delay) = event.delay() {
+            delays_by_route
+                .entry(event.route().to_string())
+                .or_default()
+                .push(delay);
+        }
+    }
+    CancellationSummary {
+        stats: events
+            .iter()
+            .copied()
+            .filter(|c| matches!(c, Cancellations::BusCancelled { .. }))
+            .collect(),
+        train_stats: events
+            .iter()
+            .copied()
+            .filter(|c| matches!(c, Cancellations::TrainCancelled { .. }))
+            .collect(),
+        ferry_stats: events
             .iter()
-            .filter(|c| match c {
-                Cancellations::BusCancelled { .. } => true,
-                _ => false,
+            .copied()
+            .filter(|c| {
+                matches!(
+                    c,
+                    Cancellations::FerryCancelled { .. } | Cancellations::FerrySuspended { .. }
+                )
             })
             .collect(),
-    };
+        causes: events
+            .iter()
+            .filter(|c| TripStatus::from(**c) != TripStatus::Reinstated)
+            .fold(BTreeMap::new(), |mut causes, c| {
+                *causes.entry(c.cause().map(|cause| cause.kind)).or_insert(0) += 1;
+                causes
+            }),
+        delays: events.iter().filter_map(|c| c.delay()).collect(),
+        delays_by_route: delays_by_route
+            .into_iter()
+            .map(|(route, delays)| (route, delays.into_iter().collect()))
+            .collect(),
+        school_runs: school_runs.iter().copied().collect(),
+        by_school: group_by(&school_runs, |c| c.school().unwrap_or_default().to_string()),
+        planned_disruptions: disruptions
+            .iter()
+            .copied()
+            .filter(|c| matches!(c, Cancellations::NetworkDisruption { planned: true, .. }))
+            .collect(),
+        unplanned_disruptions: disruptions
+            .iter()
+            .copied()
+            .filter(|c| matches!(c, Cancellations::NetworkDisruption { planned: false, .. }))
+            .collect(),
+        by_route: group_by(&events, |c| c.route().to_string()),
+        by_route_direction: group_by(&events, |c| {
+            (
+                c.route().to_string(),
+                normalise_name(&c.origin().raw),
+                normalise_name(&c.destination().raw),
+            )
+        }),
+        by_hour: group_by(&events, |c| c.time().hour()),
+        by_weekday: group_by(&events, |c| c.time().weekday().num_days_from_monday()),
+    }
+}
+
+#[cfg(test)]
+mod test_summary {
+    use super::*;
+    use crate::reconcile::TripStatus;
+    use crate::test_helpers::bus_event;
+
+    #[test]
+    fn test_effective_events() {
+        let cancellations = vec![
+            bus_event(
+                TripStatus::Cancelled,
+                "Johnsonville",
+                "2021-02-14T18:30:00Z",
+            ),
+            bus_event(
+                TripStatus::Reinstated,
+                "Johnsonville",
+                "2021-02-14T18:45:00Z",
+            ),
+            bus_event(
+                TripStatus::Cancelled,
+                "Johnsonville",
+                "2021-02-14T18:50:00Z",
+            ),
+        ];
+        let events = effective_events(&cancellations);
+        assert_eq!(vec![&cancellations[1], &cancellations[2]], events);
+
+        let summary = summarize(&cancellations);
+        assert_eq!(1, summary.stats.count);
+        assert_eq!(Some(&1), summary.causes.get(&None));
+        assert_eq!(1, summary.by_route["1"].cancelled.count);
+        assert_eq!(1, summary.by_route["1"].reinstated.count);
+        assert_eq!(1, summary.by_hour[&8].cancelled.count);
+        // Monday
+        assert_eq!(1, summary.by_weekday[&0].cancelled.count);
+    }
+
+    #[test]
+    fn test_delay_stats() {
+        let delays = [
+            Delay::Minutes(5),
+            Delay::Range { min: 15, max: 20 },
+            Delay::Minutes(10),
+            Delay::Unknown {
+                reason: Some("road block".to_string()),
+            },
+            Delay::Minutes(30),
+        ];
+        let stats: DelayStats = delays.iter().collect();
+        assert_eq!(
+            DelayStats {
+                count: 4,
+                unknown: 1,
+                total_minutes: 62,
+                p50: Some(10),
+                p90: Some(30),
+            },
+            stats
+        );
+        assert_eq!(
+            None,
+            Vec::<&Delay>::new().into_iter().collect::<DelayStats>().p50
+        );
+    }
 }
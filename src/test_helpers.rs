@@ -0,0 +1,163 @@
+//! Builders for the timetables and tweeted events the tests are set up with, and somewhere for
+//! them to write files. Only what a test cares about is passed in; everything else is left empty.
+
+use crate::parser::Cancellations;
+use crate::reconcile::TripStatus;
+use crate::time::convert_time_to_instant;
+use chrono::{DateTime, Utc};
+use metlink_gtfs_lib::gtfs::data::route::RouteType;
+use metlink_gtfs_lib::gtfs::data::{calendar, route, stop, trip, GtfsData};
+use std::path::PathBuf;
+
+/// A folder of its own for a test, deleted when it's done even if the test fails.
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A GTFS feed with nothing in it, to fill in with `..gtfs_data()`.
+pub fn gtfs_data() -> GtfsData {
+    GtfsData {
+        agency: vec![],
+        calendar: vec![],
+        calendar_date: vec![],
+        feed_info: vec![],
+        route: vec![],
+        stop: vec![],
+        stop_time: vec![],
+        trip: vec![],
+    }
+}
+
+/// A service that runs every weekday, and the weekend too if `weekends`, in early 2021.
+pub fn gtfs_calendar(service_id: &str, weekends: bool) -> calendar::Calendar {
+    calendar::Calendar {
+        service_id: service_id.to_string(),
+        monday: true,
+        tuesday: true,
+        wednesday: true,
+        thursday: true,
+        friday: true,
+        saturday: weekends,
+        sunday: weekends,
+        start_date: ::time::Date::try_from_ymd(2021, 1, 1).unwrap(),
+        end_date: ::time::Date::try_from_ymd(2021, 3, 31).unwrap(),
+    }
+}
+
+pub fn gtfs_route(
+    id: &str,
+    short_name: &str,
+    long_name: &str,
+    route_type: RouteType,
+) -> route::Route {
+    route::Route {
+        route_id: id.to_string(),
+        agency_id: "".to_string(),
+        route_short_name: short_name.to_string(),
+        route_long_name: long_name.to_string(),
+        route_desc: "".to_string(),
+        route_type,
+        route_url: "".to_string(),
+        route_color: "".to_string(),
+        route_text_color: "".to_string(),
+    }
+}
+
+/// A stop in Wellington, that isn't part of a station.
+pub fn gtfs_stop(id: &str, name: &str) -> stop::Stop {
+    stop::Stop {
+        stop_id: id.to_string(),
+        stop_code: id.to_string(),
+        stop_name: name.to_string(),
+        stop_desc: "".to_string(),
+        stop_lat: -41.2,
+        stop_lon: 174.8,
+        zone_id: "".to_string(),
+        stop_url: "".to_string(),
+        location_type: stop::StopLocationType::StopOrPlatform,
+        parent_station: "".to_string(),
+        stop_timezone: "".to_string(),
+    }
+}
+
+pub fn gtfs_trip(id: &str, route_id: &str, service_id: &str, headsign: &str) -> trip::Trip {
+    trip::Trip {
+        route_id: route_id.to_string(),
+        service_id: service_id.to_string(),
+        trip_id: id.to_string(),
+        trip_headsign: headsign.to_string(),
+        direction_id: false,
+        block_id: "".to_string(),
+        shape_id: "".to_string(),
+    }
+}
+
+pub fn gtfs_stop_time(trip_id: &str, stop_id: &str, sequence: u16, time: &str) -> stop::StopTime {
+    stop::StopTime {
+        trip_id: trip_id.to_string(),
+        arrival_time: time.parse().unwrap(),
+        departure_time: time.parse().unwrap(),
+        stop_id: stop_id.to_string(),
+        stop_sequence: sequence,
+        pickup_type: stop::PickupDropoffType::Regular,
+        drop_off_type: stop::PickupDropoffType::Regular,
+        shape_dist_traveled: None,
+        stop_headsign: "".to_string(),
+        timepoint: true,
+    }
+}
+
+/// The 8:03am route 1 bus from `origin` to Island Bay, on the day it's tweeted about.
+pub fn bus_event(status: TripStatus, origin: &str, tweeted_at: &str) -> Cancellations {
+    let tweet_time = DateTime::parse_from_rfc3339(tweeted_at)
+        .unwrap()
+        .with_timezone(&Utc);
+    let time = convert_time_to_instant(tweet_time, 8, 3).unwrap();
+    match status {
+        TripStatus::Cancelled => Cancellations::BusCancelled {
+            route: "1".to_string(),
+            origin: origin.into(),
+            destination: "Island Bay".into(),
+            cause: None,
+            raw_time: "8:03 am".to_string(),
+            tweet_time,
+            time,
+            link: None,
+        },
+        TripStatus::PartCancelled => Cancellations::BusPartCancelled {
+            route: "1".to_string(),
+            origin: origin.into(),
+            destination: "Island Bay".into(),
+            cancelled_from: origin.into(),
+            cancelled_to: "Courtenay Place".into(),
+            cause: None,
+            raw_time: "8:03 am".to_string(),
+            tweet_time,
+            time,
+            link: None,
+        },
+        TripStatus::Reinstated => Cancellations::BusReinstated {
+            route: "1".to_string(),
+            origin: origin.into(),
+            destination: "Island Bay".into(),
+            cause: None,
+            raw_time: "8:03 am".to_string(),
+            tweet_time,
+            time,
+            link: None,
+        },
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,178 @@
+use crate::matcher::distance_metres;
+use crate::report::local_date;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use metlink_gtfs_lib::realtime::vehicle_positions::{OccupancyStatus, VehiclePositionRoot};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// GPS drifts a little even for a bus parked at the depot, so moving less than this doesn't count.
+const MOVING_METRES: f64 = 200.0;
+
+/// Where a vehicle was at one point in a trip.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct TrackPoint {
+    pub time: DateTime<Utc>,
+    pub vehicle_id: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    pub bearing: Option<f64>,
+    pub occupancy: Option<OccupancyStatus>,
+}
+
+/// Every position reported for a trip on one day, in order.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct TripTrack {
+    pub trip_id: String,
+    pub service_date: NaiveDate,
+    pub points: Vec<TrackPoint>,
+}
+
+impl TripTrack {
+    /// How far the vehicle got from where it was first seen on the trip.
+    pub fn max_distance_metres(&self) -> f64 {
+        let first = match self.points.first() {
+            Some(first) => (first.lat, first.lon),
+            None => return 0.0,
+        };
+        self.points
+            .iter()
+            .map(|p| distance_metres(first, (p.lat, p.lon)))
+            .fold(0.0, f64::max)
+    }
+
+    pub fn moved(&self) -> bool {
+        self.max_distance_metres() >= MOVING_METRES
+    }
+}
+
+/// The vehicle positions snapshots, stitched together into a track per trip.
+#[derive(Default, Debug)]
+pub struct TripTracks {
+    tracks: BTreeMap<(String, NaiveDate), TripTrack>,
+}
+
+impl TripTracks {
+    /// Snapshots overlap, so a position that hasn't been updated since the last one is only kept
+    ///  once. Positions without a trip or a location are skipped.
+    pub fn from_snapshots(snapshots: &[VehiclePositionRoot]) -> Self {
+        let mut tracks: BTreeMap<(String, NaiveDate), TripTrack> = BTreeMap::new();
+        for snapshot in snapshots {
+            for vehicle in snapshot.positions() {
+                let (trip, position) = match (&vehicle.trip, &vehicle.position) {
+                    (Some(trip), Some(position)) => (trip, position),
+                    _ => continue,
+                };
+                let trip_id = match &trip.trip_id {
+                    Some(trip_id) => trip_id,
+                    None => continue,
+                };
+                let reported_at = vehicle.timestamp.unwrap_or(snapshot.header.timestamp);
+                let time = match Utc.timestamp_opt(reported_at.unix_timestamp(), 0).single() {
+                    Some(time) => time,
+                    None => continue,
+                };
+                let service_date = trip
+                    .start_date
+                    .as_ref()
+                    .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+                    .unwrap_or_else(|| local_date(&time));
+                tracks
+                    .entry((trip_id.clone(), service_date))
+                    .or_insert_with(|| TripTrack {
+                        trip_id: trip_id.clone(),
+                        service_date,
+                        points: vec![],
+                    })
+                    .points
+                    .push(TrackPoint {
+                        time,
+                        vehicle_id: vehicle.vehicle_id().map(|id| id.to_string()),
+                        lat: position.latitude,
+                        lon: position.longitude,
+                        bearing: position.bearing,
+                        occupancy: vehicle.occupancy_status,
+                    });
+            }
+        }
+        for track in tracks.values_mut() {
+            track
+                .points
+                .sort_by(|a, b| (a.time, &a.vehicle_id).cmp(&(b.time, &b.vehicle_id)));
+            track
+                .points
+                .dedup_by(|a, b| a.time == b.time && a.vehicle_id == b.vehicle_id);
+        }
+        TripTracks { tracks }
+    }
+
+    pub fn get(&self, trip_id: &str, service_date: NaiveDate) -> Option<&TripTrack> {
+        self.tracks.get(&(trip_id.to_string(), service_date))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+}
+
+#[cfg(test)]
+mod test_tracks {
+    use super::*;
+
+    #[test]
+    fn test_from_snapshots() {
+        let snapshot = |timestamp: i64, lat: f64, vehicle_timestamp: i64| -> VehiclePositionRoot {
+            serde_json::from_str(&format!(
+                r#"{{
+                  "header": {{ "gtfs_realtime_version": "2.0", "incrementality": 0, "timestamp": {} }},
+                  "entity": [
+                    {{
+                      "id": "1",
+                      "vehicle": {{
+                        "trip": {{ "trip_id": "1-0803-JW", "start_date": "20210215" }},
+                        "vehicle": {{ "id": "2051" }},
+                        "position": {{ "latitude": {}, "longitude": 174.79, "bearing": 180 }},
+                        "timestamp": {},
+                        "occupancy_status": 1
+                      }}
+                    }},
+                    {{
+                      "id": "2",
+                      "vehicle": {{
+                        "trip": {{ "trip_id": "2-0810-KH", "start_date": "20210215" }},
+                        "vehicle": {{ "id": "3120" }},
+                        "position": {{ "latitude": -41.3, "longitude": 174.78 }},
+                        "occupancy_status": "FULL"
+                      }}
+                    }},
+                    {{ "id": "3", "vehicle": {{ "vehicle": {{ "id": "4001" }} }} }}
+                  ]
+                }}"#,
+                timestamp, lat, vehicle_timestamp
+            ))
+            .unwrap()
+        };
+        let date = NaiveDate::from_ymd_opt(2021, 2, 15).unwrap();
+        let tracks = TripTracks::from_snapshots(&[
+            snapshot(1613329500, -41.22, 1613329490),
+            // Not updated since the last snapshot
+            snapshot(1613329800, -41.22, 1613329490),
+            snapshot(1613330100, -41.25, 1613330095),
+        ]);
+
+        assert_eq!(2, tracks.len());
+        let moving = tracks.get("1-0803-JW", date).unwrap();
+        assert_eq!(2, moving.points.len());
+        assert_eq!(Some("2051".to_string()), moving.points[1].vehicle_id);
+        assert_eq!(
+            Some(OccupancyStatus::ManySeatsAvailable),
+            moving.points[0].occupancy
+        );
+        assert!(moving.moved());
+
+        let parked = tracks.get("2-0810-KH", date).unwrap();
+        assert_eq!(3, parked.points.len());
+        assert_eq!(Some(OccupancyStatus::Full), parked.points[0].occupancy);
+        assert!(!parked.moved());
+        assert_eq!(None, tracks.get("1-0803-JW", date.succ_opt().unwrap()));
+    }
+}
@@ -0,0 +1,178 @@
+use crate::parser::{parse_tweet, Cancellations, ParseError};
+use crate::tweet_cache::{TweetCache, TweetContent};
+use serde::{Deserialize, Serialize};
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::{collections::BTreeMap, fs::File};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const TRIAGE_FILE: &str = "./twitter-triage.json";
+const USAGE: &str = "Usage: triage [<tweet id> (ignore|needs-regex|manual <events.json>) [note]]\n       triage <tweet id> expand <full text>";
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub enum Disposition {
+    /// Not about a specific service, so there's nothing to parse.
+    Ignore,
+    /// Parsed by hand, for one-off wording that isn't worth a regex.
+    Manual(Vec<Cancellations>),
+    /// Should be parsed, but isn't yet. Stops the tweet being reported as broken in the meantime.
+    NeedsRegex,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct TriageEntry {
+    pub disposition: Disposition,
+    #[serde(default)]
+    pub note: String,
+}
+
+/// Tweets that don't parse, and what to do about them.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct Triage {
+    pub tweets: BTreeMap<u64, TriageEntry>,
+}
+
+impl Triage {
+    pub fn read() -> Result<Self> {
+        match File::open(TRIAGE_FILE) {
+            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+            Err(_) => Ok(Triage::default()),
+        }
+    }
+    pub fn write(&self) -> Result<()> {
+        let file = File::create(TRIAGE_FILE)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+    pub fn record(&mut self, id: u64, disposition: Disposition, note: String) {
+        self.tweets.insert(id, TriageEntry { disposition, note });
+    }
+
+    pub fn parse_tweet(
+        &self,
+        tweet: &TweetContent,
+    ) -> std::result::Result<Vec<Cancellations>, ParseError> {
+        match self.tweets.get(&tweet.id).map(|entry| &entry.disposition) {
+            Some(Disposition::Ignore) => Ok(vec![]),
+            Some(Disposition::Manual(cancellations)) => Ok(cancellations.clone()),
+            // Once the regex has been written, the parsed result wins.
+            Some(Disposition::NeedsRegex) => Ok(parse_tweet(tweet).unwrap_or_default()),
+            None => parse_tweet(tweet),
+        }
+    }
+}
+
+fn list_unparsed(cache: &TweetCache, triage: &Triage) {
+    let mut count = 0;
+    for tweet in cache.tweets() {
+        if let Err(err) = triage.parse_tweet(tweet) {
+            count += 1;
+            println!(
+                "{} ({}): {}\n    {}",
+                tweet.id, tweet.created_at, err, tweet.text
+            );
+        }
+    }
+    let waiting = triage
+        .tweets
+        .iter()
+        .filter(|(_, entry)| entry.disposition == Disposition::NeedsRegex)
+        .filter(|(id, _)| {
+            cache
+                .get(**id)
+                .map(|tweet| parse_tweet(tweet).is_err())
+                .unwrap_or(false)
+        })
+        .count();
+    println!(
+        "{} tweets need triage, {} are waiting on a regex",
+        count, waiting
+    );
+}
+
+/// Records the whole text of a tweet that was cut short when it was posted, so the API's
+///  `full_text` doesn't have it either. It's parsed in place of the tweet.
+fn expand(mut cache: TweetCache, id: u64, text: String) -> Result<()> {
+    if text.is_empty() {
+        return Err(USAGE.into());
+    }
+    if !cache.update(id, |tweet| tweet.expanded_text = Some(text)) {
+        return Err(format!("{} isn't in the tweet cache", id).into());
+    }
+    cache.write()?;
+    Ok(())
+}
+
+/// `triage` lists the tweets that don't parse; `triage <tweet id> <disposition> [note]` records
+///  what to do with one, and `triage <tweet id> expand <full text>` fills in a truncated one.
+pub fn run(args: &[String]) -> Result<()> {
+    let cache = TweetCache::read()?;
+    let mut triage = Triage::read()?;
+    if args.is_empty() {
+        list_unparsed(&cache, &triage);
+        return Ok(());
+    }
+
+    let id: u64 = args[0]
+        .parse()
+        .map_err(|_| format!("Invalid tweet id {:?}. {}", args[0], USAGE))?;
+    if args.get(1).map(|s| s.as_str()) == Some("expand") {
+        return expand(cache, id, args[2..].join(" "));
+    }
+    if cache.get(id).is_none() {
+        println!("Warning: {} isn't in the tweet cache", id);
+    }
+    let (disposition, note) = match args.get(1).map(|s| s.as_str()) {
+        Some("ignore") => (Disposition::Ignore, &args[2..]),
+        Some("needs-regex") => (Disposition::NeedsRegex, &args[2..]),
+        Some("manual") => {
+            let path = args.get(2).ok_or(USAGE)?;
+            let cancellations: Vec<Cancellations> =
+                serde_json::from_reader(BufReader::new(File::open(path)?))?;
+            (Disposition::Manual(cancellations), &args[3..])
+        }
+        _ => return Err(USAGE.into()),
+    };
+    triage.record(id, disposition, note.join(" "));
+    triage.write()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_triage {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    fn tweet(id: u64, text: &str) -> TweetContent {
+        TweetContent::new(
+            id,
+            DateTime::parse_from_rfc3339("2021-01-24T21:00:06Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            text,
+        )
+    }
+
+    #[test]
+    fn test_parse_tweet() {
+        let mut triage = Triage::default();
+        let unparsed = tweet(1, "Bus 14: 9.00am Kilbirnie to Wilton");
+        assert!(triage.parse_tweet(&unparsed).is_err());
+
+        triage.record(1, Disposition::NeedsRegex, "".to_string());
+        assert_eq!(Ok(vec![]), triage.parse_tweet(&unparsed));
+
+        triage.record(1, Disposition::Ignore, "No status given".to_string());
+        assert_eq!(Ok(vec![]), triage.parse_tweet(&unparsed));
+
+        let cancelled = tweet(
+            2,
+            "Bus 3: 10:30am Wellington Station to Lyall Bay is cancelled.",
+        );
+        let manual = parse_tweet(&cancelled).unwrap();
+        triage.record(1, Disposition::Manual(manual.clone()), "".to_string());
+        assert_eq!(Ok(manual), triage.parse_tweet(&unparsed));
+    }
+}
@@ -0,0 +1,658 @@
+use crate::parser::{Cancellations, Delay, Location, ResolvedStop};
+use crate::report::local_date;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Offset, TimeZone, Utc};
+use chrono_tz::Pacific::Auckland;
+use metlink_gtfs_lib::datatypes::time::Time;
+use metlink_gtfs_lib::db::trips::Trip;
+use metlink_gtfs_lib::db::Database;
+use metlink_gtfs_lib::gtfs::data::route::RouteType;
+use metlink_gtfs_lib::realtime::trip_updates::{
+    StopScheduleRelationship, StopTimeUpdate, TripScheduleRelationship, TripUpdate, TripUpdateRoot,
+};
+use std::collections::BTreeMap;
+
+// Metlink don't tweet about services running a few minutes late, so neither does this.
+const MIN_DELAY_MINUTES: u32 = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Mode {
+    Bus,
+    Train,
+}
+
+impl Mode {
+    // Ferries and the cable car don't get tweets of this kind, so they're left out.
+    fn of(route_type: RouteType) -> Option<Self> {
+        match route_type {
+            RouteType::Bus | RouteType::Trolleybus => Some(Mode::Bus),
+            RouteType::Rail | RouteType::Subway => Some(Mode::Train),
+            _ => None,
+        }
+    }
+}
+
+/// Turns GTFS-RT trip updates into the same events the tweets are parsed into, so cancellations
+///  can come straight from the realtime feed.
+pub struct TripUpdateDetector<'a> {
+    db: &'a Database,
+}
+
+impl<'a> TripUpdateDetector<'a> {
+    pub fn new(db: &'a Database) -> Self {
+        Self { db }
+    }
+
+    fn location(&self, stop_id: &str) -> Location {
+        match self.db.stops.get_stop(&stop_id.to_string()) {
+            Some(stop) => Location {
+                raw: stop.name.clone(),
+                stop: Some(ResolvedStop {
+                    id: stop.id.clone(),
+                    lat: stop.lat,
+                    lon: stop.lon,
+                }),
+            },
+            None => stop_id.into(),
+        }
+    }
+
+    /// Realtime stop updates give the stop id, the stop sequence or both.
+    fn stop_id<'b>(trip: &'b Trip, update: &'b StopTimeUpdate) -> Option<&'b str> {
+        update.stop_id.as_deref().or_else(|| {
+            trip.stops
+                .iter()
+                .find(|stop| Some(stop.stop_sequence) == update.stop_sequence)
+                .map(|stop| stop.stop_id.as_str())
+        })
+    }
+
+    /// The day the trip runs on, which it keeps even once it's past midnight.
+    fn service_date(update: &TripUpdate, reported_at: DateTime<Utc>) -> Option<NaiveDate> {
+        match &update.trip.start_date {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y%m%d").ok(),
+            None => Some(local_date(&reported_at)),
+        }
+    }
+
+    /// When the trip was timetabled to leave, from the update if it says and the timetable if
+    ///  not. Times past midnight count on from the service date.
+    fn scheduled_time(
+        trip: &Trip,
+        update: &TripUpdate,
+        reported_at: DateTime<Utc>,
+    ) -> Option<(String, DateTime<FixedOffset>)> {
+        let date = Self::service_date(update, reported_at)?;
+        let minutes = match &update.trip.start_time {
+            Some(time) => time.parse::<Time>().ok()?.minutes_since_midnight(),
+            None => trip.stops.first()?.departure_time.minutes_since_midnight(),
+        };
+        // GTFS times count from noon less 12 hours, which isn't midnight on the days the clocks
+        //  change.
+        let noon = Auckland
+            .from_local_datetime(&date.and_hms_opt(12, 0, 0)?)
+            .single()?;
+        let time = noon - Duration::hours(12) + Duration::minutes(minutes.into());
+        Some((
+            format!("{:02}:{:02}", minutes / 60, minutes % 60),
+            time.with_timezone(&time.offset().fix()),
+        ))
+    }
+
+    /// The events for one trip update. Trips that aren't in the timetable, like added ones, have
+    ///  nothing to compare against and are skipped.
+    pub fn detect(&self, update: &TripUpdate, reported_at: DateTime<Utc>) -> Vec<Cancellations> {
+        let trip = match update
+            .trip
+            .trip_id
+            .as_ref()
+            .and_then(|id| self.db.trips.get_trip(id))
+        {
+            Some(trip) => trip,
+            None => return vec![],
+        };
+        let (route, mode) = match self
+            .db
+            .routes
+            .get_route(&trip.route_id)
+            .and_then(|route| Some((route.short_name.clone(), Mode::of(route.route_type)?)))
+        {
+            Some(route) => route,
+            None => return vec![],
+        };
+        let (raw_time, time) = match Self::scheduled_time(trip, update, reported_at) {
+            Some(time) => time,
+            None => return vec![],
+        };
+        let (origin, destination) = match (trip.stops.first(), trip.stops.last()) {
+            (Some(first), Some(last)) => {
+                (self.location(&first.stop_id), self.location(&last.stop_id))
+            }
+            _ => return vec![],
+        };
+        let tweet_time = update.timestamp.map_or(reported_at, |timestamp| {
+            Utc.timestamp_opt(timestamp.unix_timestamp(), 0)
+                .single()
+                .unwrap_or(reported_at)
+        });
+
+        let mut events = vec![];
+        match update.trip.schedule_relationship {
+            TripScheduleRelationship::Canceled | TripScheduleRelationship::Deleted => {
+                events.push(match mode {
+                    Mode::Bus => Cancellations::BusCancelled {
+                        route,
+                        origin,
+                        destination,
+                        cause: None,
+                        raw_time,
+                        tweet_time,
+                        time,
+                        link: None,
+                    },
+                    Mode::Train => Cancellations::TrainCancelled {
+                        line: route,
+                        origin,
+                        destination,
+                        cause: None,
+                        raw_time,
+                        tweet_time,
+                        time,
+                        link: None,
+                    },
+                });
+                return events;
+            }
+            TripScheduleRelationship::Scheduled | TripScheduleRelationship::Replacement => {}
+            _ => return events,
+        }
+
+        let skipped: Vec<&str> = update
+            .stop_time_update
+            .iter()
+            .filter(|stop| stop.schedule_relationship == StopScheduleRelationship::Skipped)
+            .filter_map(|stop| Self::stop_id(trip, stop))
+            .collect();
+        if let (Some(from), Some(to)) = (skipped.first(), skipped.last()) {
+            let (cancelled_from, cancelled_to) = (self.location(from), self.location(to));
+            events.push(match mode {
+                Mode::Bus => Cancellations::BusPartCancelled {
+                    route: route.clone(),
+                    origin: origin.clone(),
+                    destination: destination.clone(),
+                    cancelled_from,
+                    cancelled_to,
+                    cause: None,
+                    raw_time: raw_time.clone(),
+                    tweet_time,
+                    time,
+                    link: None,
+                },
+                Mode::Train => Cancellations::TrainPartCancelled {
+                    line: route.clone(),
+                    origin: origin.clone(),
+                    destination: destination.clone(),
+                    cancelled_from,
+                    cancelled_to,
+                    cause: None,
+                    raw_time: raw_time.clone(),
+                    tweet_time,
+                    time,
+                    link: None,
+                },
+            });
+        }
+
+        // Where it's furthest behind, or the trip's own delay if none of the stops say.
+        let late = update
+            .stop_time_update
+            .iter()
+            .filter(|stop| stop.schedule_relationship == StopScheduleRelationship::Scheduled)
+            .filter_map(|stop| Some((stop.delay()?, Self::stop_id(trip, stop))))
+            .max_by_key(|(delay, _)| *delay)
+            .or_else(|| update.delay.map(|delay| (delay, None)));
+        if let Some((seconds, stop_id)) = late {
+            let minutes = (seconds.max(0) as u32) / 60;
+            if minutes >= MIN_DELAY_MINUTES {
+                let delay = Delay::Minutes(minutes);
+                events.push(match mode {
+                    Mode::Bus => Cancellations::BusDelayed {
+                        route,
+                        origin,
+                        destination,
+                        delay,
+                        cause: None,
+                        raw_time,
+                        tweet_time,
+                        time,
+                        link: None,
+                    },
+                    Mode::Train => Cancellations::TrainDelayed {
+                        line: route,
+                        delayed_from: stop_id
+                            .map(|id| self.location(id))
+                            .unwrap_or_else(|| origin.clone()),
+                        origin,
+                        destination,
+                        delay,
+                        cause: None,
+                        raw_time,
+                        tweet_time,
+                        time,
+                        link: None,
+                    },
+                });
+            }
+        }
+        events
+    }
+
+    fn reported_at(feed: &TripUpdateRoot) -> DateTime<Utc> {
+        Utc.timestamp_opt(feed.header.timestamp.unix_timestamp(), 0)
+            .single()
+            .unwrap_or_else(Utc::now)
+    }
+
+    /// What's left of a trip's events once it's running normally again. That's nothing, unless
+    ///  it's a bus that was cancelled, which is reinstated like it would be tweeted.
+    fn recovered(previous: Vec<Cancellations>, reported_at: DateTime<Utc>) -> Vec<Cancellations> {
+        previous
+            .into_iter()
+            .filter_map(|event| match event {
+                Cancellations::BusCancelled {
+                    route,
+                    origin,
+                    destination,
+                    raw_time,
+                    time,
+                    ..
+                } => Some(Cancellations::BusReinstated {
+                    route,
+                    origin,
+                    destination,
+                    cause: None,
+                    raw_time,
+                    tweet_time: reported_at,
+                    time,
+                    link: None,
+                }),
+                Cancellations::BusReinstated { .. } => Some(event),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The events for each trip as of the last download it was in. The feed is downloaded every
+    ///  few minutes, so the same trip turns up again and again, and its delay changes as it goes.
+    ///  A trip that's back to running normally loses its events. Trip ids are reused every day,
+    ///  so it's by trip and service date.
+    pub fn detect_latest(&self, feeds: &[TripUpdateRoot]) -> Vec<Cancellations> {
+        let mut latest: BTreeMap<(String, NaiveDate), Vec<Cancellations>> = BTreeMap::new();
+        for feed in feeds {
+            let reported_at = Self::reported_at(feed);
+            // A download can have more than one update for a trip, which all count
+            let mut downloaded: BTreeMap<(String, NaiveDate), Vec<Cancellations>> = BTreeMap::new();
+            for update in feed.trip_updates() {
+                let key = match (
+                    &update.trip.trip_id,
+                    Self::service_date(update, reported_at),
+                ) {
+                    (Some(trip_id), Some(date)) => (trip_id.clone(), date),
+                    _ => continue,
+                };
+                downloaded
+                    .entry(key)
+                    .or_default()
+                    .extend(self.detect(update, reported_at));
+            }
+            for (key, events) in downloaded {
+                if !events.is_empty() {
+                    latest.insert(key, events);
+                } else if let Some(previous) = latest.remove(&key) {
+                    let recovered = Self::recovered(previous, reported_at);
+                    if !recovered.is_empty() {
+                        latest.insert(key, recovered);
+                    }
+                }
+            }
+        }
+        let mut events: Vec<Cancellations> = latest.into_values().flatten().collect();
+        events.sort_by_key(|event| *event.time());
+        events
+    }
+}
+
+#[cfg(test)]
+mod test_trip_updates {
+    use super::*;
+    use crate::test_helpers::{
+        gtfs_calendar, gtfs_data, gtfs_route, gtfs_stop, gtfs_stop_time, gtfs_trip,
+    };
+    use metlink_gtfs_lib::gtfs::data::GtfsData;
+    use metlink_gtfs_lib::realtime::utils::{FeedFormat, RealtimeFeed};
+
+    fn database() -> Database {
+        let data = GtfsData {
+            calendar: vec![gtfs_calendar("DAILY", true)],
+            route: vec![
+                gtfs_route("HVL", "HVL", "", RouteType::Rail),
+                gtfs_route("1", "1", "Island Bay - Wellington", RouteType::Bus),
+            ],
+            stop: vec![
+                gtfs_stop("UPPE", "Upper Hutt Station"),
+                gtfs_stop("WATE", "Waterloo Station"),
+                gtfs_stop("PETO", "Petone Station"),
+                gtfs_stop("WELL", "Wellington Station"),
+                gtfs_stop("5000", "Wellington Station - Stop A"),
+                gtfs_stop("7000", "Island Bay - Stop B"),
+            ],
+            stop_time: vec![
+                gtfs_stop_time("HVL-1930", "UPPE", 1, "19:30:00"),
+                gtfs_stop_time("HVL-1930", "WATE", 2, "19:55:00"),
+                gtfs_stop_time("HVL-1930", "PETO", 3, "20:02:00"),
+                gtfs_stop_time("HVL-1930", "WELL", 4, "20:15:00"),
+                gtfs_stop_time("HVL-2000", "UPPE", 1, "20:00:00"),
+                gtfs_stop_time("HVL-2000", "WELL", 2, "20:45:00"),
+                gtfs_stop_time("1-0803", "7000", 1, "08:03:00"),
+                gtfs_stop_time("1-0803", "5000", 2, "08:40:00"),
+            ],
+            trip: vec![
+                gtfs_trip("HVL-1930", "HVL", "DAILY", "Wellington"),
+                gtfs_trip("HVL-2000", "HVL", "DAILY", "Wellington"),
+                gtfs_trip("1-0803", "1", "DAILY", "Wellington"),
+            ],
+            ..gtfs_data()
+        };
+        (&data).into()
+    }
+
+    #[test]
+    fn test_detect() {
+        let feed: TripUpdateRoot = serde_json::from_str(
+            r#"{
+              "header": { "gtfs_realtime_version": "2.0", "incrementality": 0, "timestamp": 1613455200 },
+              "entity": [
+                {
+                  "id": "1",
+                  "trip_update": {
+                    "trip": { "trip_id": "HVL-2000", "start_date": "20210216", "schedule_relationship": 3 },
+                    "vehicle": { "id": "4251" }
+                  }
+                },
+                {
+                  "id": "2",
+                  "trip_update": {
+                    "trip": { "trip_id": "HVL-1930", "start_time": "19:30:00", "start_date": "20210216" },
+                    "stop_time_update": [
+                      { "stop_sequence": 2, "schedule_relationship": "SKIPPED" },
+                      { "stop_id": "PETO", "schedule_relationship": "SKIPPED" },
+                      { "stop_sequence": 4, "arrival": { "delay": 780 } }
+                    ],
+                    "timestamp": 1613455260
+                  }
+                },
+                {
+                  "id": "3",
+                  "trip_update": {
+                    "trip": { "trip_id": "HVL-1930", "start_date": "20210216" },
+                    "stop_time_update": { "stop_sequence": 4, "departure": { "delay": 120 } }
+                  }
+                },
+                {
+                  "id": "4",
+                  "trip_update": { "trip": { "trip_id": "EXTRA-1", "schedule_relationship": "ADDED" } }
+                }
+              ]
+            }"#,
+        )
+        .unwrap();
+        let db = database();
+        let events = TripUpdateDetector::new(&db).detect_latest(&[feed]);
+
+        assert_eq!(3, events.len());
+        match &events[2] {
+            Cancellations::TrainCancelled {
+                line,
+                origin,
+                destination,
+                time,
+                ..
+            } => {
+                assert_eq!("HVL", line);
+                assert_eq!("Upper Hutt Station", origin.raw);
+                assert_eq!("Wellington Station", destination.raw);
+                assert_eq!(
+                    DateTime::parse_from_rfc3339("2021-02-16T20:00:00+13:00").unwrap(),
+                    *time
+                );
+            }
+            other => panic!("Expected a cancellation, got {:?}", other),
+        }
+        match &events[0] {
+            Cancellations::TrainPartCancelled {
+                cancelled_from,
+                cancelled_to,
+                raw_time,
+                tweet_time,
+                ..
+            } => {
+                assert_eq!("Waterloo Station", cancelled_from.raw);
+                assert_eq!("Petone Station", cancelled_to.raw);
+                assert_eq!("19:30", raw_time);
+                assert_eq!(1613455260, tweet_time.timestamp());
+            }
+            other => panic!("Expected a part cancellation, got {:?}", other),
+        }
+        match &events[1] {
+            Cancellations::TrainDelayed {
+                delayed_from,
+                delay,
+                ..
+            } => {
+                assert_eq!("Wellington Station", delayed_from.raw);
+                assert_eq!(Delay::Minutes(13), *delay);
+            }
+            other => panic!("Expected a delay, got {:?}", other),
+        }
+    }
+
+    fn delayed_feed(timestamp: i64, delay: i32) -> TripUpdateRoot {
+        serde_json::from_str(&format!(
+            r#"{{
+              "header": {{ "gtfs_realtime_version": "2.0", "incrementality": 0, "timestamp": {} }},
+              "entity": [
+                {{
+                  "id": "1",
+                  "trip_update": {{
+                    "trip": {{ "trip_id": "HVL-1930", "start_date": "20210216" }},
+                    "stop_time_update": {{ "stop_sequence": 4, "arrival": {{ "delay": {} }} }}
+                  }}
+                }}
+              ]
+            }}"#,
+            timestamp, delay
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_detect_latest() {
+        let db = database();
+        let mut tomorrow = delayed_feed(1613541600, 600);
+        tomorrow.entity[0].trip_update.trip.start_date = Some("20210217".to_string());
+        let feeds = vec![
+            delayed_feed(1613455200, 900),
+            delayed_feed(1613455500, 2280),
+            // Caught up a little, but still late
+            delayed_feed(1613455800, 1800),
+            // Another day's run of the same trip isn't collapsed into it
+            tomorrow,
+        ];
+
+        let events = TripUpdateDetector::new(&db).detect_latest(&feeds);
+        let delays: Vec<Option<&Delay>> = events.iter().map(|event| event.delay()).collect();
+        assert_eq!(
+            vec![Some(&Delay::Minutes(30)), Some(&Delay::Minutes(10))],
+            delays
+        );
+    }
+
+    #[test]
+    fn test_detect_latest_recovered() {
+        let feed = |timestamp: i64, relationship: &str, delay: i32| -> TripUpdateRoot {
+            serde_json::from_str(&format!(
+                r#"{{
+                  "header": {{ "gtfs_realtime_version": "2.0", "incrementality": 0, "timestamp": {} }},
+                  "entity": [
+                    {{
+                      "id": "1",
+                      "trip_update": {{ "trip": {{ "trip_id": "1-0803", "start_date": "20210216", "schedule_relationship": "{}" }} }}
+                    }},
+                    {{
+                      "id": "2",
+                      "trip_update": {{
+                        "trip": {{ "trip_id": "HVL-1930", "start_date": "20210216" }},
+                        "stop_time_update": {{ "stop_sequence": 4, "arrival": {{ "delay": {} }} }}
+                      }}
+                    }}
+                  ]
+                }}"#,
+                timestamp, relationship, delay
+            ))
+            .unwrap()
+        };
+        let db = database();
+        let detector = TripUpdateDetector::new(&db);
+
+        let cancelled = detector.detect_latest(&[feed(1613455200, "CANCELED", 900)]);
+        assert!(matches!(
+            cancelled[..],
+            [
+                Cancellations::BusCancelled { .. },
+                Cancellations::TrainDelayed { .. }
+            ]
+        ));
+
+        // The bus is reinstated, and the train caught up
+        let feeds = vec![
+            feed(1613455200, "CANCELED", 900),
+            feed(1613455500, "SCHEDULED", 60),
+        ];
+        let events = detector.detect_latest(&feeds);
+        assert_eq!(1, events.len());
+        match &events[0] {
+            Cancellations::BusReinstated {
+                route,
+                origin,
+                tweet_time,
+                ..
+            } => {
+                assert_eq!("1", route);
+                assert_eq!("Island Bay - Stop B", origin.raw);
+                assert_eq!(1613455500, tweet_time.timestamp());
+            }
+            other => panic!("Expected a reinstatement, got {:?}", other),
+        }
+
+        // Still running, so it stays reinstated
+        let feeds = vec![
+            feed(1613455200, "CANCELED", 900),
+            feed(1613455500, "SCHEDULED", 60),
+            feed(1613455800, "SCHEDULED", 0),
+        ];
+        assert_eq!(events, detector.detect_latest(&feeds));
+    }
+
+    #[test]
+    fn test_scheduled_time_dst() {
+        // The clocks went back an hour at 3am on the 4th of April 2021, so 19:30 is 13 hours
+        //  after midnight, not 12.
+        let mut feed = delayed_feed(1617500000, 900);
+        feed.entity[0].trip_update.trip.start_date = Some("20210404".to_string());
+        let db = database();
+        let events = TripUpdateDetector::new(&db).detect_latest(&[feed]);
+        assert_eq!(
+            DateTime::parse_from_rfc3339("2021-04-04T19:30:00+12:00").unwrap(),
+            *events[0].time()
+        );
+    }
+
+    fn varint(mut n: u64) -> Vec<u8> {
+        let mut bytes = vec![];
+        while n >= 0x80 {
+            bytes.push((n as u8) | 0x80);
+            n >>= 7;
+        }
+        bytes.push(n as u8);
+        bytes
+    }
+
+    fn field(number: u64, contents: &[u8]) -> Vec<u8> {
+        let mut bytes = varint(number << 3 | 2);
+        bytes.extend(varint(contents.len() as u64));
+        bytes.extend_from_slice(contents);
+        bytes
+    }
+
+    fn number(number: u64, value: u64) -> Vec<u8> {
+        let mut bytes = varint(number << 3);
+        bytes.extend(varint(value));
+        bytes
+    }
+
+    #[test]
+    fn test_detect_protobuf() {
+        let header = [field(1, b"2.0"), number(3, 1613455200)].concat();
+        let trip = [
+            field(1, b"HVL-1930"),
+            field(3, b"20210216"),
+            // An extension, which is skipped
+            field(1000, b"ignored"),
+        ]
+        .concat();
+        let delayed_stop = [
+            number(1, 4),
+            // A negative delay is sign extended to ten bytes.
+            field(2, &number(1, -60i64 as u64)),
+            field(3, &number(1, 780)),
+        ]
+        .concat();
+        let trip_update = [
+            field(1, &trip),
+            field(2, &[number(1, 2), number(5, 1)].concat()),
+            field(2, &delayed_stop),
+            field(3, &field(1, b"4251")),
+        ]
+        .concat();
+        let feed = [
+            field(1, &header),
+            field(2, &[field(1, b"1"), field(3, &trip_update)].concat()),
+        ]
+        .concat();
+
+        assert_eq!(
+            Some(FeedFormat::Protobuf),
+            FeedFormat::from_content_type("application/x-protobuf")
+        );
+        let feed = TripUpdateRoot::decode(&feed, FeedFormat::Protobuf).unwrap();
+        let update = &feed.entity[0].trip_update;
+        assert_eq!(
+            Some(-60),
+            update.stop_time_update[1].arrival.as_ref().unwrap().delay
+        );
+        assert_eq!(Some("4251"), update.vehicle.as_ref().unwrap().id.as_deref());
+
+        let db = database();
+        let events = TripUpdateDetector::new(&db).detect_latest(&[feed]);
+        assert!(matches!(
+            events[..],
+            [
+                Cancellations::TrainPartCancelled { .. },
+                Cancellations::TrainDelayed {
+                    delay: Delay::Minutes(13),
+                    ..
+                }
+            ]
+        ));
+        assert!(TripUpdateRoot::decode(&[0x0a, 0x05], FeedFormat::Protobuf).is_err());
+    }
+}
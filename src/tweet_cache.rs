@@ -1,84 +1,599 @@
 use chrono::DateTime;
 use chrono::Utc;
 use egg_mode::tweet::Tweet;
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::io::BufReader;
-use std::io::BufWriter;
-use std::{collections::BTreeMap, fs::File};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+const STORE_FILE: &str = "./twitter-store.jsonl";
+/// Where tweets were cached before the store. It's imported the first time the store is opened.
+const LEGACY_CACHE_FILE: &str = "./twitter-cache.json";
+/// 1 only had the id, time and text of each tweet, and 2 added replies and entities. Both were a
+///  single JSON file. 3 is the store.
+const STORE_VERSION: u32 = 3;
+
+lazy_static! {
+    static ref URL_RE: Regex = Regex::new(r"https://t\.co/[A-Za-z0-9]+").unwrap();
+    static ref HASHTAG_RE: Regex = Regex::new(r"(?:^|\s)#(\w+)").unwrap();
+    static ref MENTION_RE: Regex = Regex::new(r"(?:^|\s)@(\w+)").unwrap();
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialOrd, Ord, PartialEq, Eq)]
+pub struct TweetUrl {
+    /// The t.co link, as it appears in the text.
+    pub url: String,
+    pub expanded_url: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialOrd, Ord, PartialEq, Eq)]
+pub struct TweetEntities {
+    pub hashtags: Vec<String>,
+    pub urls: Vec<TweetUrl>,
+    /// Screen names, without the "@".
+    pub user_mentions: Vec<String>,
+}
+
+impl TweetEntities {
+    /// What can be picked out of the text, for tweets cached before entities were kept. The
+    ///  links aren't expanded.
+    pub fn from_text(text: &str) -> Self {
+        let captured = |re: &Regex| -> Vec<String> {
+            re.captures_iter(text)
+                .map(|capture| capture[1].to_string())
+                .collect()
+        };
+        TweetEntities {
+            hashtags: captured(&HASHTAG_RE),
+            urls: URL_RE
+                .find_iter(text)
+                .map(|url| TweetUrl {
+                    url: url.as_str().to_string(),
+                    expanded_url: None,
+                })
+                .collect(),
+            user_mentions: captured(&MENTION_RE),
+        }
+    }
+}
+
+/// Where an announcement was posted. Anything that isn't a tweet is still kept as a
+///  `TweetContent`, so it's parsed the same way.
+#[derive(
+    Deserialize, Serialize, Clone, Copy, Debug, Default, PartialOrd, Ord, PartialEq, Eq, Hash,
+)]
+pub enum SourceTag {
+    #[default]
+    Twitter,
+    /// A Mastodon (or other ActivityPub) outbox.
+    ActivityPub,
+    /// An RSS or Atom feed.
+    Feed,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, PartialOrd, Ord, PartialEq, Eq)]
 pub struct TweetContent {
     pub id: u64,
     pub created_at: DateTime<Utc>,
+    /// The part of the tweet that's shown, without the leading mentions of a reply.
     pub text: String,
+    /// The whole of a tweet that was cut short with "…", for parsing in its place. The API's
+    ///  `full_text` is already the `text`, so this is only for tweets that were truncated when they
+    ///  were posted, and it's filled in by hand with `triage`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expanded_text: Option<String>,
+    /// The API's `full_text`, when there's more to it than `text`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_text: Option<String>,
+    #[serde(default)]
+    pub in_reply_to_status_id: Option<u64>,
+    #[serde(default)]
+    pub entities: TweetEntities,
+    #[serde(default)]
+    pub source: SourceTag,
+}
+
+#[cfg(test)]
+impl TweetContent {
+    pub fn new(id: u64, created_at: DateTime<Utc>, text: &str) -> Self {
+        TweetContent {
+            id,
+            created_at,
+            text: text.to_string(),
+            expanded_text: None,
+            full_text: None,
+            in_reply_to_status_id: None,
+            entities: TweetEntities::from_text(text),
+            source: SourceTag::Twitter,
+        }
+    }
 }
+
+impl TweetContent {
+    /// Keeps the displayed part of a tweet's `full_text` as the `text`, which leaves out the
+    ///  mentions a reply starts with. The display range counts characters, not bytes.
+    pub fn from_full_text(
+        id: u64,
+        created_at: DateTime<Utc>,
+        full_text: &str,
+        display_text_range: Option<(usize, usize)>,
+        in_reply_to_status_id: Option<u64>,
+        entities: TweetEntities,
+    ) -> Self {
+        let text: String = match display_text_range {
+            Some((start, end)) => full_text
+                .chars()
+                .skip(start)
+                .take(end.saturating_sub(start))
+                .collect(),
+            None => full_text.to_string(),
+        };
+        TweetContent {
+            id,
+            created_at,
+            full_text: Some(full_text.to_string()).filter(|full_text| *full_text != text),
+            text,
+            expanded_text: None,
+            in_reply_to_status_id,
+            entities,
+            source: SourceTag::Twitter,
+        }
+    }
+}
+
 impl From<&Tweet> for TweetContent {
     fn from(t: &Tweet) -> Self {
-        Self {
-            id: t.id,
-            created_at: t.created_at,
-            text: t.text.clone(),
-        }
+        // The timeline is requested with `tweet_mode=extended`, so `t.text` is the `full_text`.
+        TweetContent::from_full_text(
+            t.id,
+            t.created_at,
+            &t.text,
+            t.display_text_range,
+            t.in_reply_to_status_id,
+            TweetEntities {
+                hashtags: t.entities.hashtags.iter().map(|h| h.text.clone()).collect(),
+                urls: t
+                    .entities
+                    .urls
+                    .iter()
+                    .map(|u| TweetUrl {
+                        url: u.url.clone(),
+                        expanded_url: u.expanded_url.clone(),
+                    })
+                    .collect(),
+                user_mentions: t
+                    .entities
+                    .user_mentions
+                    .iter()
+                    .map(|m| m.screen_name.clone())
+                    .collect(),
+            },
+        )
     }
 }
 
+fn unversioned() -> u32 {
+    1
+}
+
+/// The single JSON file tweets were cached in before the store.
 #[derive(Deserialize, Serialize, Clone, Debug)]
-pub struct TweetCache {
+pub struct JsonCache {
+    #[serde(default = "unversioned")]
+    pub version: u32,
     pub tweets: Vec<TweetContent>,
 }
 
+impl JsonCache {
+    /// Fills in what can be worked out for an older version of the file. Anything that was never
+    ///  stored (like what a tweet was replying to) is left empty.
+    pub fn migrate(&mut self) {
+        if self.version < 2 {
+            for tweet in self.tweets.iter_mut() {
+                tweet.entities = TweetEntities::from_text(&tweet.text);
+            }
+        }
+        self.version = STORE_VERSION;
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct StoreHeader {
+    version: u32,
+}
+
+type TweetKey = (u64, SourceTag);
+
+/// Groups tweets into reply chains, keeping the order they're given in. A tweet replying to one
+///  that isn't there starts a thread of its own.
+fn group_threads<'a, I>(tweets: I) -> Vec<Vec<&'a TweetContent>>
+where
+    I: Iterator<Item = &'a TweetContent>,
+{
+    let mut threads: Vec<Vec<&TweetContent>> = vec![];
+    let mut thread_of: HashMap<TweetKey, usize> = HashMap::new();
+    for tweet in tweets {
+        let idx = match tweet
+            .in_reply_to_status_id
+            .and_then(|id| thread_of.get(&(id, tweet.source)))
+        {
+            Some(idx) => *idx,
+            None => {
+                threads.push(vec![]);
+                threads.len() - 1
+            }
+        };
+        threads[idx].push(tweet);
+        thread_of.insert((tweet.id, tweet.source), idx);
+    }
+    threads
+}
+
+/// Tweets from every source, keyed by id and indexed by when they were tweeted.
+///
+/// On disk this is a header line and then a line of JSON per tweet. Writing only appends what's
+///  been added or changed since the last write, and when a tweet has more than one line the last
+///  one wins. Once most of the lines have been replaced by later ones, the file is rewritten with
+///  just the latest.
+#[derive(Debug)]
+pub struct TweetCache {
+    path: PathBuf,
+    tweets: BTreeMap<TweetKey, TweetContent>,
+    by_created_at: BTreeSet<(DateTime<Utc>, TweetKey)>,
+    unwritten: Vec<TweetKey>,
+    /// How many tweet lines the file has, including the ones that have been replaced.
+    lines: usize,
+}
+
+impl Default for TweetCache {
+    fn default() -> Self {
+        TweetCache {
+            path: PathBuf::from(STORE_FILE),
+            tweets: BTreeMap::new(),
+            by_created_at: BTreeSet::new(),
+            unwritten: vec![],
+            lines: 0,
+        }
+    }
+}
+
 impl TweetCache {
-    pub fn add_tweet(&mut self, tweet: &Tweet) {
-        if !self.tweets.iter().any(|c| c.id == tweet.id) {
-            self.tweets.push(TweetContent::from(tweet));
-            self.tweets.sort();
+    fn insert(&mut self, tweet: TweetContent) {
+        let key = (tweet.id, tweet.source);
+        if let Some(old) = self.tweets.get(&key) {
+            self.by_created_at.remove(&(old.created_at, key));
+        }
+        self.by_created_at.insert((tweet.created_at, key));
+        self.tweets.insert(key, tweet);
+    }
+    /// Adds the tweets that aren't already cached, from any source. Returns how many were new.
+    pub fn add_all<I: IntoIterator<Item = TweetContent>>(&mut self, tweets: I) -> usize {
+        let mut added = 0;
+        for tweet in tweets {
+            let key = (tweet.id, tweet.source);
+            if !self.tweets.contains_key(&key) {
+                self.insert(tweet);
+                self.unwritten.push(key);
+                added += 1;
+            }
         }
+        added
+    }
+    /// The tweet with this id. Ids are only unique within a source, but in practice they don't
+    ///  collide, so this is the first from any source.
+    pub fn get(&self, id: u64) -> Option<&TweetContent> {
+        // `Twitter` sorts first, so this starts at the lowest key with the id.
+        self.tweets
+            .range((id, SourceTag::Twitter)..)
+            .next()
+            .filter(|((tweet_id, _), _)| *tweet_id == id)
+            .map(|(_, tweet)| tweet)
+    }
+    /// Changes a cached tweet, so it's written out again. Returns false if it isn't cached.
+    pub fn update<F: FnOnce(&mut TweetContent)>(&mut self, id: u64, change: F) -> bool {
+        let mut tweet = match self.get(id) {
+            Some(tweet) => tweet.clone(),
+            None => return false,
+        };
+        change(&mut tweet);
+        self.unwritten.push((tweet.id, tweet.source));
+        self.insert(tweet);
+        true
+    }
+    /// Every tweet, in id order.
+    pub fn tweets(&self) -> impl Iterator<Item = &TweetContent> {
+        self.tweets.values()
+    }
+    pub fn len(&self) -> usize {
+        self.tweets.len()
+    }
+    /// The tweets from `start` up to but not including `end`, in the order they were tweeted.
+    pub fn created_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> impl Iterator<Item = &TweetContent> {
+        let lowest = (0, SourceTag::Twitter);
+        self.by_created_at
+            .range((start, lowest)..(end, lowest))
+            .map(move |(_, key)| &self.tweets[key])
+    }
+    /// The newest tweet, which is as far back as the API needs to go.
+    pub fn latest_id(&self) -> Option<u64> {
+        self.tweets
+            .keys()
+            .rev()
+            .find(|(_, source)| *source == SourceTag::Twitter)
+            .map(|(id, _)| *id)
     }
-    pub fn add_all_tweets(&mut self, tweets: &[Tweet]) {
-        tweets.iter().for_each(|t| self.add_tweet(t));
+    fn write_header<W: Write>(writer: &mut W) -> Result<()> {
+        serde_json::to_writer(
+            &mut *writer,
+            &StoreHeader {
+                version: STORE_VERSION,
+            },
+        )?;
+        writeln!(writer)?;
+        Ok(())
     }
-    pub fn write(&self) -> Result<()> {
-        let file = File::create("./twitter-cache.json")?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self)?;
+    /// Appends what's been added or changed since the last write, starting the file if need be.
+    ///  Compacts it instead if that would leave more replaced lines than tweets.
+    pub fn write(&mut self) -> Result<()> {
+        if self.lines + self.unwritten.len() > 2 * self.tweets.len() {
+            return self.compact();
+        }
+        let is_new = !self.path.exists();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut writer = BufWriter::new(file);
+        if is_new {
+            Self::write_header(&mut writer)?;
+        }
+        for key in self.unwritten.drain(..) {
+            serde_json::to_writer(&mut writer, &self.tweets[&key])?;
+            writeln!(writer)?;
+            self.lines += 1;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+    /// Rewrites the file with a line per tweet. It's written alongside and moved into place, so
+    ///  a run that stops part way leaves the old one.
+    pub fn compact(&mut self) -> Result<()> {
+        let partial = self.path.with_extension("partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        Self::write_header(&mut writer)?;
+        for tweet in self.tweets.values() {
+            serde_json::to_writer(&mut writer, tweet)?;
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        std::fs::rename(&partial, &self.path)?;
+        self.unwritten.clear();
+        self.lines = self.tweets.len();
         Ok(())
     }
     pub fn read() -> Result<Self> {
-        match File::open("./twitter-cache.json") {
+        Self::open(Path::new(STORE_FILE), Path::new(LEGACY_CACHE_FILE))
+    }
+    /// Opens the store at `path`, creating it from the old JSON cache if there isn't one yet.
+    pub fn open(path: &Path, legacy_path: &Path) -> Result<Self> {
+        let mut cache = TweetCache {
+            path: path.to_path_buf(),
+            ..TweetCache::default()
+        };
+        match File::open(path) {
             Ok(file) => {
-                let reader = BufReader::new(file);
-                // Read the JSON contents of the file as an instance of `User`.
-                let cache: TweetCache = serde_json::from_reader(reader)?;
-
-                let tweets = cache
-                    .tweets
-                    .into_iter()
-                    .map(|c| (c.id, c))
-                    .collect::<BTreeMap<_, _>>()
-                    .into_iter()
-                    .map(|(_, c)| c)
-                    .collect();
-
-                Ok(TweetCache { tweets })
-            }
-            Err(_) => {
-                let cache = TweetCache {
-                    tweets: Vec::with_capacity(50),
+                let mut lines = BufReader::new(file).lines();
+                let header: StoreHeader = match lines.next() {
+                    Some(line) => serde_json::from_str(&line?)?,
+                    None => return Err(format!("{} is empty", path.display()).into()),
                 };
+                if header.version > STORE_VERSION {
+                    return Err(format!(
+                        "{} is version {}, but this only reads up to {}",
+                        path.display(),
+                        header.version,
+                        STORE_VERSION
+                    )
+                    .into());
+                }
+                for line in lines {
+                    let line = line?;
+                    if !line.trim().is_empty() {
+                        cache.insert(serde_json::from_str(&line)?);
+                        cache.lines += 1;
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                match File::open(legacy_path) {
+                    Ok(file) => {
+                        let mut legacy: JsonCache = serde_json::from_reader(BufReader::new(file))?;
+                        legacy.migrate();
+                        let added = cache.add_all(legacy.tweets);
+                        println!(
+                            "Imported {} tweets from {} into {}",
+                            added,
+                            legacy_path.display(),
+                            path.display()
+                        );
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e.into()),
+                }
                 cache.write()?;
-                Ok(cache)
             }
+            Err(e) => return Err(e.into()),
         }
+        Ok(cache)
+    }
+    /// The reply chains among the tweets from `start` up to but not including `end`.
+    pub fn threads_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<Vec<&TweetContent>> {
+        group_threads(self.created_between(start, end))
     }
     pub fn has_recent_data(&self) -> bool {
         let now = Utc::now();
-        self.tweets
+        self.by_created_at
             .iter()
-            .map(|tweet| now - tweet.created_at)
-            .min()
-            .map(|diff| diff.num_hours() < 12)
+            .rev()
+            .find(|(_, (_, source))| *source == SourceTag::Twitter)
+            .map(|(created_at, _)| (now - *created_at).num_hours() < 12)
             .unwrap_or(false)
     }
 }
+
+#[cfg(test)]
+mod test_tweet_cache {
+    use super::*;
+    use crate::test_helpers::TempDir;
+
+    fn tweet(id: u64, in_reply_to_status_id: Option<u64>) -> TweetContent {
+        let created_at = DateTime::parse_from_rfc3339("2020-10-09T09:20:03Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        TweetContent {
+            in_reply_to_status_id,
+            ..TweetContent::new(id, created_at, "")
+        }
+    }
+
+    #[test]
+    fn test_migrate() {
+        let mut cache: JsonCache = serde_json::from_str(
+            r#"{"tweets": [{
+                "id": 1349586588838215680,
+                "created_at": "2021-01-14T05:18:11Z",
+                "text": "WRL: WRL: The 3:38pm svc from MAST to WELL is now on the move from MAYM https://t.co/EidCAPongw #metlink"
+            }]}"#,
+        )
+        .unwrap();
+        assert_eq!(1, cache.version);
+        cache.migrate();
+        assert_eq!(STORE_VERSION, cache.version);
+        assert_eq!(
+            TweetEntities {
+                hashtags: vec!["metlink".to_string()],
+                urls: vec![TweetUrl {
+                    url: "https://t.co/EidCAPongw".to_string(),
+                    expanded_url: None,
+                }],
+                user_mentions: vec![],
+            },
+            cache.tweets[0].entities
+        );
+        assert_eq!(None, cache.tweets[0].in_reply_to_status_id);
+    }
+
+    #[test]
+    fn test_threads() {
+        let mut cache = TweetCache::default();
+        cache.add_all(vec![
+            tweet(1, None),
+            tweet(2, None),
+            tweet(3, Some(1)),
+            tweet(4, Some(3)),
+            tweet(5, Some(99)),
+        ]);
+        let created_at = tweet(1, None).created_at;
+        let threads: Vec<Vec<u64>> = cache
+            .threads_between(created_at, created_at + chrono::Duration::seconds(1))
+            .iter()
+            .map(|thread| thread.iter().map(|tweet| tweet.id).collect())
+            .collect();
+        assert_eq!(vec![vec![1, 3, 4], vec![2], vec![5]], threads);
+    }
+
+    #[test]
+    fn test_store() {
+        let dir = TempDir::new("tweet-store");
+        let (path, legacy_path) = (dir.0.join("store.jsonl"), dir.0.join("cache.json"));
+        let legacy = JsonCache {
+            version: 2,
+            tweets: vec![tweet(2, None), tweet(1, None)],
+        };
+        serde_json::to_writer(File::create(&legacy_path).unwrap(), &legacy).unwrap();
+
+        let mut cache = TweetCache::open(&path, &legacy_path).unwrap();
+        assert_eq!(2, cache.len());
+        assert_eq!(1, cache.add_all(vec![tweet(3, Some(1)), tweet(1, None)]));
+        assert!(cache.update(2, |tweet| tweet.expanded_text =
+            Some("Expanded".to_string())));
+        assert!(!cache.update(99, |_| {}));
+        cache.write().unwrap();
+
+        let reopened = TweetCache::open(&path, &legacy_path).unwrap();
+        assert_eq!(
+            vec![1, 2, 3],
+            reopened.tweets().map(|t| t.id).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some("Expanded"),
+            reopened.get(2).and_then(|t| t.expanded_text.as_deref())
+        );
+        // The header, both imported tweets, then the new and changed ones
+        let lines = || std::fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(5, lines());
+
+        // Replacing a line at a time, until there'd be more replaced lines than tweets
+        let mut cache = reopened;
+        for (text, expected_lines) in &[("Once", 6), ("Twice", 7), ("Again", 4)] {
+            cache.update(2, |tweet| tweet.expanded_text = Some(text.to_string()));
+            cache.write().unwrap();
+            assert_eq!(*expected_lines, lines());
+        }
+        let reopened = TweetCache::open(&path, &legacy_path).unwrap();
+        assert_eq!(3, reopened.len());
+        assert_eq!(
+            Some("Again"),
+            reopened.get(2).and_then(|t| t.expanded_text.as_deref())
+        );
+        assert!(!path.with_extension("partial").exists());
+    }
+
+    #[test]
+    fn test_from_full_text() {
+        let created_at = Utc::now();
+        let short = "KPL: The 9:14pm svc from WELL to WAIK is running 21 mins late from PARA.";
+        let tweet = TweetContent::from_full_text(
+            1,
+            created_at,
+            &format!("@metlinkwgtn {}", short),
+            Some((13, 13 + short.chars().count())),
+            None,
+            TweetEntities::default(),
+        );
+        assert_eq!(short, tweet.text);
+        assert!(tweet.full_text.is_some());
+        assert_eq!(None, tweet.expanded_text);
+
+        let long = "Bus 1: The 8:03am service from Island Bay to Johnsonville West is cancelled due to a driver shortage. The next service is at 8:18am, and will be running 10 mins late.";
+        let tweet =
+            TweetContent::from_full_text(2, created_at, long, None, None, TweetEntities::default());
+        assert_eq!(long, tweet.text);
+        assert_eq!(None, tweet.full_text);
+        assert_eq!(None, tweet.expanded_text);
+
+        // A display range that ends before it starts
+        let tweet = TweetContent::from_full_text(
+            3,
+            created_at,
+            short,
+            Some((13, 5)),
+            None,
+            TweetEntities::default(),
+        );
+        assert_eq!("", tweet.text);
+    }
+}